@@ -28,79 +28,1445 @@ mod circuits {
         (mxe_balance, client_balance)
     }
 
+    /// Open a zero balance in an additional currency
+    #[instruction]
+    pub fn open_currency_balance(mxe: Mxe, client: Shared) -> (Enc<Mxe, u64>, Enc<Shared, u64>) {
+        (mxe.from_arcis(0u64), client.from_arcis(0u64))
+    }
+
     /// Process payment from sender to receiver
     /// Similar to player_hit - updates state and returns new encrypted values
+    /// The amount arrives encrypted by the sender and is never revealed.
+    /// Rejected, without revealing the totals, if it takes the sender's spending
+    /// for the day above their daily limit.
+    /// When the sender has a co-signer, an amount above their large-transfer
+    /// threshold is held back for approval; only that flag is revealed.
+    /// A sender with a minimum reserve must keep at least that much after the
+    /// debit; falling below it counts as insufficient funds.
+    /// When held for acceptance the receiver is not credited: the amount is
+    /// escrowed under the MXE key instead, and zero is escrowed otherwise.
     #[instruction]
     pub fn process_payment(
         sender_balance_ctxt: Enc<Mxe, u64>,
         receiver_balance_ctxt: Enc<Mxe, u64>,
-        amount: u64,
+        amount_ctxt: Enc<Shared, u64>,
+        fee_bps: u64,
+        flat_fee: u64,
+        tip_ctxt: Enc<Shared, u64>,
+        validator_rewards_ctxt: Enc<Mxe, u64>,
+        validator_rewards_initialized: bool,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        daily_limit_ctxt: Enc<Mxe, u64>,
+        has_daily_limit: bool,
+        spent_today_ctxt: Enc<Mxe, u64>,
+        reset_spent_today: bool,
+        large_transfer_threshold_ctxt: Enc<Mxe, u64>,
+        has_co_signer: bool,
+        min_reserve_ctxt: Enc<Mxe, u64>,
+        has_min_reserve: bool,
+        hold_for_acceptance: bool,
+        escrow: Mxe,
         receiver_key: Shared,
-    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, bool) {
+        sender_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        bool,
+        bool,
+        bool,
+        Enc<Mxe, u64>,
+    ) {
         // Decrypt balances within MPC
         let sender_balance = sender_balance_ctxt.to_arcis();
         let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let amount = amount_ctxt.to_arcis();
+        // Optional priority tip chosen by the sender (zero for none)
+        let tip = tip_ctxt.to_arcis();
+        let validator_rewards = if validator_rewards_initialized {
+            validator_rewards_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        // The sender pays the amount plus the operation fee and the tip
+        let fee = compute_fee(amount, fee_bps, flat_fee);
+        let total_debit = amount as u128 + fee as u128 + tip as u128;
+
+        // Check if sender has sufficient balance above their reserve. A credit that
+        // would overflow the receiver's u64 balance is treated the same way:
+        // nothing moves.
+        let min_reserve = reserve_or_zero(min_reserve_ctxt, has_min_reserve);
+        let receiver_fits = receiver_balance as u128 + amount as u128 <= u64::MAX as u128;
+        let is_sufficient =
+            sender_balance as u128 >= total_debit + min_reserve as u128 && receiver_fits;
 
-        // Check if sender has sufficient balance
-        let is_sufficient = sender_balance >= amount;
+        // The amount counts against the sender's daily limit, if one is set
+        let spent_today = if reset_spent_today {
+            0
+        } else {
+            spent_today_ctxt.to_arcis()
+        };
+        let daily_limit = daily_limit_ctxt.to_arcis();
+        let limit_exceeded =
+            has_daily_limit && spent_today as u128 + amount as u128 > daily_limit as u128;
+        let large_transfer_threshold = large_transfer_threshold_ctxt.to_arcis();
+        let needs_approval = has_co_signer && amount > large_transfer_threshold;
+        let approved = is_sufficient && !limit_exceeded && !needs_approval;
 
         // Calculate new balances
-        let new_sender_balance = if is_sufficient {
-            sender_balance - amount
+        let new_sender_balance = if approved {
+            (sender_balance as u128 - total_debit) as u64
         } else {
-            sender_balance // No change if insufficient
+            sender_balance // No change unless approved
         };
 
-        let new_receiver_balance = if is_sufficient {
+        let new_receiver_balance = if approved && !hold_for_acceptance {
             receiver_balance + amount
         } else {
-            receiver_balance // No change if insufficient
+            receiver_balance // No change unless approved and not held
+        };
+        let escrowed = if approved && hold_for_acceptance {
+            amount
+        } else {
+            0
         };
 
-        // Re-encrypt balances
+        let new_spent_today = if approved {
+            add_saturating(spent_today, amount)
+        } else {
+            spent_today
+        };
+        let spent_today_encrypted = spent_today_ctxt.owner.from_arcis(new_spent_today);
+
+        // Re-encrypt balances: MXE copies for later circuits, plus copies for the
+        // sender and the receiver to read straight after the payment
         let sender_encrypted = sender_balance_ctxt.owner.from_arcis(new_sender_balance);
+        let sender_client_encrypted = sender_key.from_arcis(new_sender_balance);
+        let receiver_mxe_encrypted = receiver_balance_ctxt.owner.from_arcis(new_receiver_balance);
         let receiver_encrypted = receiver_key.from_arcis(new_receiver_balance);
+        // Transfer amount for the transaction record, readable by the sender
+        let amount_encrypted = amount_ctxt.owner.from_arcis(amount);
+
+        let new_validator_rewards = if approved {
+            add_saturating(validator_rewards, tip)
+        } else {
+            validator_rewards
+        };
+        let validator_rewards_encrypted =
+            validator_rewards_ctxt.owner.from_arcis(new_validator_rewards);
+
+        // The fee is collected into the treasury
+        let new_treasury = if approved {
+            add_saturating(treasury, fee)
+        } else {
+            treasury
+        };
+        let treasury_encrypted = treasury_ctxt.owner.from_arcis(new_treasury);
+
+        (
+            sender_encrypted,
+            sender_client_encrypted,
+            receiver_mxe_encrypted,
+            receiver_encrypted,
+            amount_encrypted,
+            validator_rewards_encrypted,
+            treasury_encrypted,
+            spent_today_encrypted,
+            is_sufficient.reveal(),
+            limit_exceeded.reveal(),
+            needs_approval.reveal(),
+            escrow.from_arcis(escrowed),
+        )
+    }
+
+    /// Pay one receiver from the first of up to four source balances that covers it
+    /// Slots at index >= source_count are padding. The amount plus its payment fee
+    /// is debited from the first real slot holding enough; every source balance
+    /// is re-encrypted, so the stored ciphertexts do not show which one paid. The
+    /// chosen slot is returned encrypted to the sender, and the fee is collected
+    /// into the treasury. Nothing moves, and false is revealed, if no source covers
+    /// it or the receiver's balance would overflow.
+    #[instruction]
+    pub fn process_payment_multi_source(
+        source_0_ctxt: Enc<Mxe, u64>,
+        source_1_ctxt: Enc<Mxe, u64>,
+        source_2_ctxt: Enc<Mxe, u64>,
+        source_3_ctxt: Enc<Mxe, u64>,
+        source_count: u8,
+        receiver_balance_ctxt: Enc<Mxe, u64>,
+        amount_ctxt: Enc<Shared, u64>,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        source_0_key: Shared,
+        source_1_key: Shared,
+        source_2_key: Shared,
+        source_3_key: Shared,
+        receiver_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u8>,
+        Enc<Mxe, u64>,
+        bool,
+    ) {
+        let sources = [
+            source_0_ctxt.to_arcis(),
+            source_1_ctxt.to_arcis(),
+            source_2_ctxt.to_arcis(),
+            source_3_ctxt.to_arcis(),
+        ];
+        let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let amount = amount_ctxt.to_arcis();
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let fee = compute_fee(amount, fee_bps, flat_fee);
+        let total_debit = amount as u128 + fee as u128;
+        let receiver_fits = receiver_balance as u128 + amount as u128 <= u64::MAX as u128;
+
+        let mut found = false;
+        let mut source_index: u8 = 0;
+        for i in 0..4 {
+            let covers = (i as u8) < source_count && sources[i] as u128 >= total_debit;
+            if covers && !found {
+                source_index = i as u8;
+                found = true;
+            }
+        }
+        let approved = found && receiver_fits;
+
+        let mut new_sources = sources;
+        for i in 0..4 {
+            if approved && (i as u8) == source_index {
+                new_sources[i] = (sources[i] as u128 - total_debit) as u64;
+            }
+        }
+        let new_receiver_balance = if approved {
+            receiver_balance + amount
+        } else {
+            receiver_balance
+        };
+        let new_treasury = if approved {
+            add_saturating(treasury, fee)
+        } else {
+            treasury
+        };
+
+        (
+            source_0_ctxt.owner.from_arcis(new_sources[0]),
+            source_1_ctxt.owner.from_arcis(new_sources[1]),
+            source_2_ctxt.owner.from_arcis(new_sources[2]),
+            source_3_ctxt.owner.from_arcis(new_sources[3]),
+            source_0_key.from_arcis(new_sources[0]),
+            source_1_key.from_arcis(new_sources[1]),
+            source_2_key.from_arcis(new_sources[2]),
+            source_3_key.from_arcis(new_sources[3]),
+            receiver_balance_ctxt.owner.from_arcis(new_receiver_balance),
+            receiver_key.from_arcis(new_receiver_balance),
+            amount_ctxt.owner.from_arcis(source_index),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            approved.reveal(),
+        )
+    }
+
+    /// Encrypt an owner-chosen large-transfer threshold under the MXE key
+    #[instruction]
+    pub fn set_large_transfer_threshold(
+        threshold_ctxt: Enc<Shared, u64>,
+        mxe: Mxe,
+    ) -> Enc<Mxe, u64> {
+        mxe.from_arcis(threshold_ctxt.to_arcis())
+    }
+
+    /// Complete a large transfer held back for co-signer approval
+    /// The amount is the one stored on the transaction; the payment fee applies
+    /// and is collected into the treasury, and the amount counts against the
+    /// sender's daily limit on the day it is approved. Nothing moves, and false
+    /// is revealed, if the sender's balance no longer covers it above their
+    /// minimum reserve or the receiver's would overflow; the limit check is
+    /// revealed separately, like in process_payment. When held for acceptance
+    /// the receiver is not credited and the amount is escrowed instead.
+    #[instruction]
+    pub fn approve_large_transfer(
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        receiver_balance_ctxt: Enc<Mxe, u64>,
+        amount_ctxt: Enc<Shared, u64>,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        min_reserve_ctxt: Enc<Mxe, u64>,
+        has_min_reserve: bool,
+        daily_limit_ctxt: Enc<Mxe, u64>,
+        has_daily_limit: bool,
+        spent_today_ctxt: Enc<Mxe, u64>,
+        reset_spent_today: bool,
+        hold_for_acceptance: bool,
+        escrow: Mxe,
+        sender_key: Shared,
+        receiver_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        bool,
+        bool,
+    ) {
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let amount = amount_ctxt.to_arcis();
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let fee = compute_fee(amount, fee_bps, flat_fee);
+        let total_debit = amount as u128 + fee as u128;
+        // Checked against the balance now, which may have moved since it was sent
+        let min_reserve = reserve_or_zero(min_reserve_ctxt, has_min_reserve);
+        let receiver_fits = receiver_balance as u128 + amount as u128 <= u64::MAX as u128;
+        let is_sufficient =
+            sender_balance as u128 >= total_debit + min_reserve as u128 && receiver_fits;
+
+        // Held transfers skip the spend counter when sent, so it is counted here
+        let spent_today = if reset_spent_today {
+            0
+        } else {
+            spent_today_ctxt.to_arcis()
+        };
+        let daily_limit = daily_limit_ctxt.to_arcis();
+        let limit_exceeded =
+            has_daily_limit && spent_today as u128 + amount as u128 > daily_limit as u128;
+        let approved = is_sufficient && !limit_exceeded;
+
+        let (new_sender_balance, new_treasury, new_spent_today) = if approved {
+            (
+                (sender_balance as u128 - total_debit) as u64,
+                add_saturating(treasury, fee),
+                add_saturating(spent_today, amount),
+            )
+        } else {
+            (sender_balance, treasury, spent_today)
+        };
+        let new_receiver_balance = if approved && !hold_for_acceptance {
+            receiver_balance + amount
+        } else {
+            receiver_balance
+        };
+        let escrowed = if approved && hold_for_acceptance {
+            amount
+        } else {
+            0
+        };
+
+        (
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            sender_key.from_arcis(new_sender_balance),
+            receiver_balance_ctxt.owner.from_arcis(new_receiver_balance),
+            receiver_key.from_arcis(new_receiver_balance),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            spent_today_ctxt.owner.from_arcis(new_spent_today),
+            escrow.from_arcis(escrowed),
+            is_sufficient.reveal(),
+            limit_exceeded.reveal(),
+        )
+    }
+
+    /// Encrypt an owner-chosen minimum reserve under the MXE key
+    #[instruction]
+    pub fn set_min_reserve(reserve_ctxt: Enc<Shared, u64>, mxe: Mxe) -> Enc<Mxe, u64> {
+        let reserve = reserve_ctxt.to_arcis();
+        mxe.from_arcis(reserve)
+    }
+
+    /// Encrypt an owner-chosen daily spend limit under the MXE key
+    #[instruction]
+    pub fn set_daily_limit(limit_ctxt: Enc<Shared, u64>, mxe: Mxe) -> Enc<Mxe, u64> {
+        let limit = limit_ctxt.to_arcis();
+        mxe.from_arcis(limit)
+    }
+
+    /// Encrypt an owner-chosen spending cap for a delegate under the MXE key
+    #[instruction]
+    pub fn set_spending_cap(cap_ctxt: Enc<Shared, u64>, mxe: Mxe) -> Enc<Mxe, u64> {
+        let cap = cap_ctxt.to_arcis();
+        mxe.from_arcis(cap)
+    }
+
+    /// Process a payment made by a delegate against the owner's encrypted spending cap
+    /// The full debit (amount plus fee) must fit both the sender's balance and the
+    /// remaining cap, and the receiver's balance must not overflow; on success the
+    /// balance and cap are decremented by the debit and the fee is collected into
+    /// the treasury. Nothing changes otherwise. Reveals whether the balance and
+    /// the cap were each sufficient.
+    #[instruction]
+    pub fn delegated_payment(
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        receiver_balance_ctxt: Enc<Mxe, u64>,
+        cap_ctxt: Enc<Mxe, u64>,
+        amount: u64,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        receiver_key: Shared,
+        sender_key: Shared,
+        sender_balance_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        bool,
+        bool,
+    ) {
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let cap = cap_ctxt.to_arcis();
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let fee = compute_fee(amount, fee_bps, flat_fee);
+        let total_debit = amount as u128 + fee as u128;
+        // As in process_payment, a receiver overflow counts as insufficient
+        let receiver_fits = receiver_balance as u128 + amount as u128 <= u64::MAX as u128;
+        let is_sufficient = sender_balance as u128 >= total_debit && receiver_fits;
+        let within_cap = cap as u128 >= total_debit;
+        let approved = is_sufficient && within_cap;
+
+        let new_sender_balance = if approved {
+            (sender_balance as u128 - total_debit) as u64
+        } else {
+            sender_balance
+        };
+        let new_receiver_balance = if approved {
+            receiver_balance + amount
+        } else {
+            receiver_balance
+        };
+        let new_cap = if approved {
+            (cap as u128 - total_debit) as u64
+        } else {
+            cap
+        };
+        let new_treasury = if approved {
+            add_saturating(treasury, fee)
+        } else {
+            treasury
+        };
+
+        (
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            sender_balance_key.from_arcis(new_sender_balance),
+            receiver_balance_ctxt.owner.from_arcis(new_receiver_balance),
+            receiver_key.from_arcis(new_receiver_balance),
+            sender_key.from_arcis(amount),
+            cap_ctxt.owner.from_arcis(new_cap),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            is_sufficient.reveal(),
+            within_cap.reveal(),
+        )
+    }
+
+    /// Pay up to eight receivers from one sender balance, all or nothing
+    /// Slots at index >= receiver_count are padding and are left unchanged. The
+    /// sender must cover every amount plus its payment fee, and no receiver
+    /// balance may overflow; otherwise nothing changes and false is revealed.
+    /// Returns the receivers' new balances MXE-encrypted, then the same balances
+    /// encrypted to each receiver's own key, then the treasury with the fees
+    /// collected.
+    #[instruction]
+    pub fn batch_payment(
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        receiver_0_ctxt: Enc<Mxe, u64>,
+        receiver_1_ctxt: Enc<Mxe, u64>,
+        receiver_2_ctxt: Enc<Mxe, u64>,
+        receiver_3_ctxt: Enc<Mxe, u64>,
+        receiver_4_ctxt: Enc<Mxe, u64>,
+        receiver_5_ctxt: Enc<Mxe, u64>,
+        receiver_6_ctxt: Enc<Mxe, u64>,
+        receiver_7_ctxt: Enc<Mxe, u64>,
+        amount_0: u64,
+        amount_1: u64,
+        amount_2: u64,
+        amount_3: u64,
+        amount_4: u64,
+        amount_5: u64,
+        amount_6: u64,
+        amount_7: u64,
+        receiver_count: u8,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        receiver_0_key: Shared,
+        receiver_1_key: Shared,
+        receiver_2_key: Shared,
+        receiver_3_key: Shared,
+        receiver_4_key: Shared,
+        receiver_5_key: Shared,
+        receiver_6_key: Shared,
+        receiver_7_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        bool,
+    ) {
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let receiver_balances = [
+            receiver_0_ctxt.to_arcis(),
+            receiver_1_ctxt.to_arcis(),
+            receiver_2_ctxt.to_arcis(),
+            receiver_3_ctxt.to_arcis(),
+            receiver_4_ctxt.to_arcis(),
+            receiver_5_ctxt.to_arcis(),
+            receiver_6_ctxt.to_arcis(),
+            receiver_7_ctxt.to_arcis(),
+        ];
+        let amounts = [
+            amount_0, amount_1, amount_2, amount_3, amount_4, amount_5, amount_6, amount_7,
+        ];
+
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let mut total_debit: u128 = 0;
+        let mut total_fee: u128 = 0;
+        let mut fits = true;
+        for i in 0..8 {
+            if (i as u8) < receiver_count {
+                let fee = compute_fee(amounts[i], fee_bps, flat_fee) as u128;
+                total_debit += amounts[i] as u128 + fee;
+                total_fee += fee;
+                let credited = receiver_balances[i] as u128 + amounts[i] as u128;
+                fits = fits && credited <= u64::MAX as u128;
+            }
+        }
+        let approved = fits && sender_balance as u128 >= total_debit;
+
+        let new_sender_balance = if approved {
+            (sender_balance as u128 - total_debit) as u64
+        } else {
+            sender_balance
+        };
+        let mut new_receiver_balances = receiver_balances;
+        for i in 0..8 {
+            if approved && (i as u8) < receiver_count {
+                new_receiver_balances[i] = receiver_balances[i] + amounts[i];
+            }
+        }
+        // The sender covered total_fee, so it fits a u64
+        let new_treasury = if approved {
+            add_saturating(treasury, total_fee as u64)
+        } else {
+            treasury
+        };
+
+        (
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            receiver_0_ctxt.owner.from_arcis(new_receiver_balances[0]),
+            receiver_1_ctxt.owner.from_arcis(new_receiver_balances[1]),
+            receiver_2_ctxt.owner.from_arcis(new_receiver_balances[2]),
+            receiver_3_ctxt.owner.from_arcis(new_receiver_balances[3]),
+            receiver_4_ctxt.owner.from_arcis(new_receiver_balances[4]),
+            receiver_5_ctxt.owner.from_arcis(new_receiver_balances[5]),
+            receiver_6_ctxt.owner.from_arcis(new_receiver_balances[6]),
+            receiver_7_ctxt.owner.from_arcis(new_receiver_balances[7]),
+            receiver_0_key.from_arcis(new_receiver_balances[0]),
+            receiver_1_key.from_arcis(new_receiver_balances[1]),
+            receiver_2_key.from_arcis(new_receiver_balances[2]),
+            receiver_3_key.from_arcis(new_receiver_balances[3]),
+            receiver_4_key.from_arcis(new_receiver_balances[4]),
+            receiver_5_key.from_arcis(new_receiver_balances[5]),
+            receiver_6_key.from_arcis(new_receiver_balances[6]),
+            receiver_7_key.from_arcis(new_receiver_balances[7]),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            approved.reveal(),
+        )
+    }
+
+    /// Pay a receiver and a fee collector from one sender balance, all or nothing
+    /// The sender is debited amount + fee; if that exceeds their balance, or a
+    /// credit would overflow, nothing changes and false is revealed. Each of the
+    /// three balances comes back MXE-encrypted and encrypted to its owner.
+    #[instruction]
+    pub fn process_payment_with_fee(
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        receiver_balance_ctxt: Enc<Mxe, u64>,
+        collector_balance_ctxt: Enc<Mxe, u64>,
+        amount: u64,
+        fee: u64,
+        receiver_key: Shared,
+        collector_key: Shared,
+        sender_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        bool,
+    ) {
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let collector_balance = collector_balance_ctxt.to_arcis();
+
+        let total_debit = amount as u128 + fee as u128;
+        let receiver_fits = receiver_balance as u128 + amount as u128 <= u64::MAX as u128;
+        let collector_fits = collector_balance as u128 + fee as u128 <= u64::MAX as u128;
+        let is_sufficient =
+            sender_balance as u128 >= total_debit && receiver_fits && collector_fits;
+
+        let new_sender_balance = if is_sufficient {
+            (sender_balance as u128 - total_debit) as u64
+        } else {
+            sender_balance
+        };
+        let new_receiver_balance = if is_sufficient {
+            receiver_balance + amount
+        } else {
+            receiver_balance
+        };
+        let new_collector_balance = if is_sufficient {
+            collector_balance + fee
+        } else {
+            collector_balance
+        };
+
+        (
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            sender_key.from_arcis(new_sender_balance),
+            receiver_balance_ctxt.owner.from_arcis(new_receiver_balance),
+            receiver_key.from_arcis(new_receiver_balance),
+            collector_balance_ctxt.owner.from_arcis(new_collector_balance),
+            collector_key.from_arcis(new_collector_balance),
+            is_sufficient.reveal(),
+        )
+    }
+
+    /// Encrypt a credit limit under the MXE key, with nothing drawn yet
+    #[instruction]
+    pub fn open_credit_line(
+        limit_ctxt: Enc<Shared, u64>,
+        limit_mxe: Mxe,
+        drawn_mxe: Mxe,
+    ) -> (Enc<Mxe, u64>, Enc<Mxe, u64>) {
+        let limit = limit_ctxt.to_arcis();
+        (limit_mxe.from_arcis(limit), drawn_mxe.from_arcis(0u64))
+    }
+
+    /// Pay from an account that may go negative down to its credit limit
+    /// The sender's position is balance - drawn as a signed value; the payment
+    /// moves it down by the amount plus fee and splits it back into a balance
+    /// and a drawn amount, so any balance repays earlier draws first. The fee is
+    /// collected into the treasury.
+    /// Only the within_credit_limit bool is revealed; it is also false when the
+    /// credit would overflow the receiver, and then nothing changes.
+    #[instruction]
+    pub fn process_payment_signed(
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        sender_drawn_ctxt: Enc<Mxe, u64>,
+        credit_limit_ctxt: Enc<Mxe, u64>,
+        receiver_balance_ctxt: Enc<Mxe, u64>,
+        amount_ctxt: Enc<Shared, u64>,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        receiver_key: Shared,
+        sender_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        bool,
+    ) {
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let sender_drawn = sender_drawn_ctxt.to_arcis();
+        let credit_limit = credit_limit_ctxt.to_arcis();
+        let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let amount = amount_ctxt.to_arcis();
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let fee = compute_fee(amount, fee_bps, flat_fee);
+        let total_debit = amount as i128 + fee as i128;
+        let position = sender_balance as i128 - sender_drawn as i128;
+        let new_position = position - total_debit;
+
+        let receiver_fits = receiver_balance as u128 + amount as u128 <= u64::MAX as u128;
+        let within_credit_limit = new_position >= -(credit_limit as i128) && receiver_fits;
+
+        let (new_sender_balance, new_sender_drawn) = if !within_credit_limit {
+            (sender_balance, sender_drawn)
+        } else if new_position >= 0 {
+            (new_position as u64, 0u64)
+        } else {
+            (0u64, (-new_position) as u64)
+        };
+        let new_receiver_balance = if within_credit_limit {
+            receiver_balance + amount
+        } else {
+            receiver_balance
+        };
+        let new_treasury = if within_credit_limit {
+            add_saturating(treasury, fee)
+        } else {
+            treasury
+        };
+
+        (
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            sender_key.from_arcis(new_sender_balance),
+            sender_drawn_ctxt.owner.from_arcis(new_sender_drawn),
+            receiver_balance_ctxt.owner.from_arcis(new_receiver_balance),
+            receiver_key.from_arcis(new_receiver_balance),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            within_credit_limit.reveal(),
+        )
+    }
+
+    /// Re-encrypt what a credit-line account can still spend to its owner
+    /// That is the limit plus the balance less the drawn amount, so a positive
+    /// balance adds to the full limit and a draw eats into it. The result
+    /// saturates at u64::MAX and never goes below zero.
+    #[instruction]
+    pub fn reveal_available_credit(
+        balance_ctxt: Enc<Mxe, u64>,
+        drawn_ctxt: Enc<Mxe, u64>,
+        credit_limit_ctxt: Enc<Mxe, u64>,
+        owner: Shared,
+    ) -> Enc<Shared, u64> {
+        let balance = balance_ctxt.to_arcis();
+        let drawn = drawn_ctxt.to_arcis();
+        let credit_limit = credit_limit_ctxt.to_arcis();
+
+        let available = credit_limit as i128 + balance as i128 - drawn as i128;
+        let available = if available < 0 {
+            0u64
+        } else if available > u64::MAX as i128 {
+            u64::MAX
+        } else {
+            available as u64
+        };
 
-        (sender_encrypted, receiver_encrypted, is_sufficient.reveal())
+        owner.from_arcis(available)
+    }
+
+    /// Return a completed payment's amount from its receiver to its sender
+    /// The amount is the one stored on the original transaction; no fee is charged
+    /// and no spend limit applies. Nothing moves, and false is revealed, if the
+    /// receiver's balance no longer covers it or the sender's would overflow.
+    #[instruction]
+    pub fn refund_payment(
+        receiver_balance_ctxt: Enc<Mxe, u64>,
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        amount_ctxt: Enc<Shared, u64>,
+        receiver_key: Shared,
+        sender_key: Shared,
+        refund_amount_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Shared, u64>,
+        bool,
+    ) {
+        let receiver_balance = receiver_balance_ctxt.to_arcis();
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let amount = amount_ctxt.to_arcis();
+
+        let sender_fits = sender_balance as u128 + amount as u128 <= u64::MAX as u128;
+        let is_sufficient = receiver_balance >= amount && sender_fits;
+
+        let (new_receiver_balance, new_sender_balance) = if is_sufficient {
+            (receiver_balance - amount, sender_balance + amount)
+        } else {
+            (receiver_balance, sender_balance)
+        };
+
+        (
+            receiver_balance_ctxt.owner.from_arcis(new_receiver_balance),
+            receiver_key.from_arcis(new_receiver_balance),
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            sender_key.from_arcis(new_sender_balance),
+            // The refund's own record is readable by the receiver, who sends it
+            refund_amount_key.from_arcis(amount),
+            is_sufficient.reveal(),
+        )
+    }
+
+    /// Move a whole balance to another account, leaving the source at zero
+    /// Nothing moves, and false is revealed, if the destination would overflow.
+    #[instruction]
+    pub fn sweep_balance(
+        source_balance_ctxt: Enc<Mxe, u64>,
+        destination_balance_ctxt: Enc<Mxe, u64>,
+        source_key: Shared,
+        destination_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        bool,
+    ) {
+        let source_balance = source_balance_ctxt.to_arcis();
+        let (new_destination_balance, fits) =
+            credit(destination_balance_ctxt.to_arcis(), source_balance);
+        let new_source_balance = if fits { 0 } else { source_balance };
+
+        (
+            source_balance_ctxt.owner.from_arcis(new_source_balance),
+            source_key.from_arcis(new_source_balance),
+            destination_balance_ctxt.owner.from_arcis(new_destination_balance),
+            destination_key.from_arcis(new_destination_balance),
+            fits.reveal(),
+        )
+    }
+
+    /// Move a closing account's whole balance to another account
+    /// Same as sweep_balance; the callback also closes the source once it is empty.
+    #[instruction]
+    pub fn close_account(
+        source_balance_ctxt: Enc<Mxe, u64>,
+        destination_balance_ctxt: Enc<Mxe, u64>,
+        source_key: Shared,
+        destination_key: Shared,
+    ) -> (
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        Enc<Mxe, u64>,
+        Enc<Shared, u64>,
+        bool,
+    ) {
+        let source_balance = source_balance_ctxt.to_arcis();
+        let (new_destination_balance, fits) =
+            credit(destination_balance_ctxt.to_arcis(), source_balance);
+        let new_source_balance = if fits { 0 } else { source_balance };
+
+        (
+            source_balance_ctxt.owner.from_arcis(new_source_balance),
+            source_key.from_arcis(new_source_balance),
+            destination_balance_ctxt.owner.from_arcis(new_destination_balance),
+            destination_key.from_arcis(new_destination_balance),
+            fits.reveal(),
+        )
+    }
+
+    /// Zero a balance, returning what it held encrypted to the seizing authority
+    /// The owner's copy of the new balance is an encrypted zero.
+    #[instruction]
+    pub fn seize_funds(
+        balance_ctxt: Enc<Mxe, u64>,
+        owner_key: Shared,
+        authority_key: Shared,
+    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, Enc<Shared, u64>) {
+        let seized = balance_ctxt.to_arcis();
+        (
+            balance_ctxt.owner.from_arcis(0u64),
+            owner_key.from_arcis(0u64),
+            authority_key.from_arcis(seized),
+        )
+    }
+
+    /// Move a scheduled payment's amount out of the sender's balance into escrow
+    /// Nothing moves, and false is revealed, if the balance does not cover it
+    /// above the sender's minimum reserve.
+    #[instruction]
+    pub fn schedule_payment(
+        sender_balance_ctxt: Enc<Mxe, u64>,
+        amount_ctxt: Enc<Shared, u64>,
+        min_reserve_ctxt: Enc<Mxe, u64>,
+        has_min_reserve: bool,
+        escrow: Mxe,
+        sender_key: Shared,
+    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, Enc<Mxe, u64>, bool) {
+        let sender_balance = sender_balance_ctxt.to_arcis();
+        let amount = amount_ctxt.to_arcis();
+        let min_reserve = reserve_or_zero(min_reserve_ctxt, has_min_reserve);
+
+        let is_sufficient = sender_balance as u128 >= amount as u128 + min_reserve as u128;
+        let (new_sender_balance, escrowed) = if is_sufficient {
+            (sender_balance - amount, amount)
+        } else {
+            (sender_balance, 0)
+        };
+
+        (
+            sender_balance_ctxt.owner.from_arcis(new_sender_balance),
+            sender_key.from_arcis(new_sender_balance),
+            escrow.from_arcis(escrowed),
+            is_sufficient.reveal(),
+        )
+    }
+
+    /// Credit an escrowed amount to the receiver, or back to the sender
+    /// Nothing moves, and false is revealed, if the balance would overflow.
+    #[instruction]
+    pub fn release_scheduled_payment(
+        escrow_ctxt: Enc<Mxe, u64>,
+        balance_ctxt: Enc<Mxe, u64>,
+        owner_key: Shared,
+    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, bool) {
+        let (new_balance, fits) = credit(balance_ctxt.to_arcis(), escrow_ctxt.to_arcis());
+        (
+            balance_ctxt.owner.from_arcis(new_balance),
+            owner_key.from_arcis(new_balance),
+            fits.reveal(),
+        )
+    }
+
+    /// Credit a payment escrowed for acceptance to the receiver, or back to the sender
+    /// Nothing moves, and false is revealed, if the balance would overflow.
+    #[instruction]
+    pub fn release_escrowed_payment(
+        escrow_ctxt: Enc<Mxe, u64>,
+        balance_ctxt: Enc<Mxe, u64>,
+        owner_key: Shared,
+    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, bool) {
+        let (new_balance, fits) = credit(balance_ctxt.to_arcis(), escrow_ctxt.to_arcis());
+        (
+            balance_ctxt.owner.from_arcis(new_balance),
+            owner_key.from_arcis(new_balance),
+            fits.reveal(),
+        )
+    }
+
+    /// Withdraw a plaintext amount (plus the withdrawal fee) from an encrypted balance
+    /// Leaves the balance unchanged and returns false if it does not cover the debit
+    /// above the minimum reserve. The new balance also comes back encrypted to the
+    /// owner, and the fee is collected into the treasury.
+    #[instruction]
+    pub fn withdraw(
+        balance_ctxt: Enc<Mxe, u64>,
+        amount: u64,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        min_reserve_ctxt: Enc<Mxe, u64>,
+        has_min_reserve: bool,
+        owner: Shared,
+    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, Enc<Mxe, u64>, bool) {
+        let balance = balance_ctxt.to_arcis();
+        let min_reserve = reserve_or_zero(min_reserve_ctxt, has_min_reserve);
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let fee = compute_fee(amount, fee_bps, flat_fee);
+        let total_debit = amount as u128 + fee as u128;
+        let is_sufficient = balance as u128 >= total_debit + min_reserve as u128;
+
+        let (new_balance, new_treasury) = if is_sufficient {
+            ((balance as u128 - total_debit) as u64, add_saturating(treasury, fee))
+        } else {
+            (balance, treasury) // No change if insufficient
+        };
+
+        (
+            balance_ctxt.owner.from_arcis(new_balance),
+            owner.from_arcis(new_balance),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            is_sufficient.reveal(),
+        )
+    }
+
+    /// Deposit a plaintext amount into an encrypted balance
+    /// Leaves the balance unchanged and returns false if the sum would overflow u64.
+    /// The new balance also comes back encrypted to the owner.
+    #[instruction]
+    pub fn deposit(
+        balance_ctxt: Enc<Mxe, u64>,
+        amount: u64,
+        owner: Shared,
+    ) -> (Enc<Mxe, u64>, Enc<Shared, u64>, bool) {
+        let (new_balance, fits) = credit(balance_ctxt.to_arcis(), amount);
+        (
+            balance_ctxt.owner.from_arcis(new_balance),
+            owner.from_arcis(new_balance),
+            fits.reveal(),
+        )
+    }
+
+    /// Credit the balance value of redeemed reward points
+    /// Same overflow handling as `deposit`.
+    #[instruction]
+    pub fn redeem_rewards(balance_ctxt: Enc<Mxe, u64>, amount: u64) -> (Enc<Mxe, u64>, bool) {
+        let (new_balance, fits) = credit(balance_ctxt.to_arcis(), amount);
+        (balance_ctxt.owner.from_arcis(new_balance), fits.reveal())
+    }
+
+    /// Add `amount` to `balance`, or leave it unchanged and return false on overflow
+    fn credit(balance: u64, amount: u64) -> (u64, bool) {
+        let sum = balance as u128 + amount as u128;
+        let fits = sum <= u64::MAX as u128;
+
+        let new_balance = if fits {
+            sum as u64
+        } else {
+            balance // No change on overflow
+        };
+
+        (new_balance, fits)
+    }
+
+    /// Compound interest on an encrypted balance over the elapsed periods
+    /// Each of the first `periods` (at most 32) periods adds rate_bps of the running
+    /// balance, rounded down and saturating at u64::MAX. The accrual fee is then
+    /// taken out of the interest earned, never out of the original balance, and
+    /// collected into the treasury.
+    #[instruction]
+    pub fn accrue_interest(
+        balance_ctxt: Enc<Mxe, u64>,
+        rate_bps: u64,
+        periods: u8,
+        fee_bps: u64,
+        flat_fee: u64,
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+    ) -> (Enc<Mxe, u64>, Enc<Mxe, u64>, u8) {
+        let balance = balance_ctxt.to_arcis();
+        let treasury = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let mut compounded = balance as u128;
+        for i in 0..32 {
+            if (i as u8) < periods {
+                compounded = compounded + compounded * rate_bps as u128 / 10000;
+                if compounded > u64::MAX as u128 {
+                    compounded = u64::MAX as u128;
+                }
+            }
+        }
+
+        let interest = compounded as u64 - balance;
+        let fee = compute_fee(interest, fee_bps, flat_fee);
+        let net_interest = if fee < interest { interest - fee } else { 0 };
+        // Only what the interest covered is actually charged
+        let new_treasury = add_saturating(treasury, interest - net_interest);
+
+        (
+            balance_ctxt.owner.from_arcis(balance + net_interest),
+            treasury_ctxt.owner.from_arcis(new_treasury),
+            periods,
+        )
+    }
+
+    /// Re-encrypt the MXE-held balance to the key of a recovered account's new owner
+    /// The MXE ciphertext itself is unaffected; this only produces a copy the
+    /// new owner can decrypt.
+    #[instruction]
+    pub fn recover_account(balance_ctxt: Enc<Mxe, u64>, new_owner: Shared) -> Enc<Shared, u64> {
+        let balance = balance_ctxt.to_arcis();
+        new_owner.from_arcis(balance)
+    }
+
+    /// Re-encrypt the MXE-held balance to the owner's replacement client key
+    #[instruction]
+    pub fn rotate_encryption_key(balance_ctxt: Enc<Mxe, u64>, new_key: Shared) -> Enc<Shared, u64> {
+        let balance = balance_ctxt.to_arcis();
+        new_key.from_arcis(balance)
     }
 
     /// Check if balance meets threshold for compliance
     /// Similar to player_stand - checks state and returns boolean
+    /// The account id is passed back as is, so the callback can check the result
+    /// reached the account it was computed for.
     #[instruction]
     pub fn check_balance(
         balance_ctxt: Enc<Mxe, u64>,
         threshold: u64,
-    ) -> bool {
+        account_id: u64,
+    ) -> (bool, u64) {
+        let balance = balance_ctxt.to_arcis();
+        ((balance >= threshold).reveal(), account_id)
+    }
+
+    /// Re-encrypt the MXE-held balance to its owner under a fresh nonce
+    /// Gives wallets a current reading without storing or revealing anything.
+    #[instruction]
+    pub fn reveal_balance_to_owner(balance_ctxt: Enc<Mxe, u64>, owner: Shared) -> Enc<Shared, u64> {
+        let balance = balance_ctxt.to_arcis();
+        owner.from_arcis(balance)
+    }
+
+    /// Bucket a balance into a compliance bracket without revealing it
+    /// 0 = below `low`, 1 = from `low` up to `high`, 2 = above `high`.
+    #[instruction]
+    pub fn check_balance_range(balance_ctxt: Enc<Mxe, u64>, low: u64, high: u64) -> u8 {
         let balance = balance_ctxt.to_arcis();
-        (balance >= threshold).reveal()
+
+        let bracket: u8 = if balance < low {
+            0
+        } else if balance <= high {
+            1
+        } else {
+            2
+        };
+
+        bracket.reveal()
+    }
+
+    /// Compare two balances without revealing either
+    /// 0 = first below second, 1 = equal, 2 = first above second.
+    #[instruction]
+    pub fn compare_balances(first_ctxt: Enc<Mxe, u64>, second_ctxt: Enc<Mxe, u64>) -> u8 {
+        let first = first_ctxt.to_arcis();
+        let second = second_ctxt.to_arcis();
+
+        let ordering: u8 = if first < second {
+            0
+        } else if first == second {
+            1
+        } else {
+            2
+        };
+
+        ordering.reveal()
+    }
+
+    /// Re-encrypt the treasury's collected fees to the admin's key
+    #[instruction]
+    pub fn reveal_treasury(
+        treasury_ctxt: Enc<Mxe, u64>,
+        treasury_initialized: bool,
+        admin: Shared,
+    ) -> Enc<Shared, u64> {
+        let total = if treasury_initialized {
+            treasury_ctxt.to_arcis()
+        } else {
+            0
+        };
+        admin.from_arcis(total)
+    }
+
+    /// Total up to eight balances for an auditor without revealing any of them
+    /// Slots at index >= account_count are padding and are left out. The total
+    /// saturates at u64::MAX, and whether it did is revealed.
+    #[instruction]
+    pub fn aggregate_balances(
+        balance_0_ctxt: Enc<Mxe, u64>,
+        balance_1_ctxt: Enc<Mxe, u64>,
+        balance_2_ctxt: Enc<Mxe, u64>,
+        balance_3_ctxt: Enc<Mxe, u64>,
+        balance_4_ctxt: Enc<Mxe, u64>,
+        balance_5_ctxt: Enc<Mxe, u64>,
+        balance_6_ctxt: Enc<Mxe, u64>,
+        balance_7_ctxt: Enc<Mxe, u64>,
+        account_count: u8,
+        auditor: Shared,
+    ) -> (Enc<Shared, u64>, bool) {
+        let balances = [
+            balance_0_ctxt.to_arcis(),
+            balance_1_ctxt.to_arcis(),
+            balance_2_ctxt.to_arcis(),
+            balance_3_ctxt.to_arcis(),
+            balance_4_ctxt.to_arcis(),
+            balance_5_ctxt.to_arcis(),
+            balance_6_ctxt.to_arcis(),
+            balance_7_ctxt.to_arcis(),
+        ];
+
+        let mut total: u128 = 0;
+        for i in 0..8 {
+            if (i as u8) < account_count {
+                total += balances[i] as u128;
+            }
+        }
+        let overflow = total > u64::MAX as u128;
+        let total = if overflow { u64::MAX } else { total as u64 };
+
+        (auditor.from_arcis(total), overflow.reveal())
     }
 
     /// Calculate rewards based on transaction activity
     /// Calculate reward points based on transaction count and balance
+    /// Also reveals the balance tier reached (0 = Basic ... 3 = Premium).
+    /// The per-transaction rate and tier thresholds come from the program config.
+    /// The account id is passed back as is, like in check_balance.
     #[instruction]
     pub fn calculate_rewards(
         transaction_count: u64,
         balance_ctxt: Enc<Mxe, u64>,
-    ) -> u64 {
+        points_per_tx: u64,
+        silver_threshold: u64,
+        gold_threshold: u64,
+        premium_threshold: u64,
+        account_id: u64,
+    ) -> (u64, u8, u64) {
         let balance = balance_ctxt.to_arcis();
         
         // Reward calculation logic:
-        // - Base: 10 points per transaction
+        // - Base: points_per_tx points per transaction
         // - Bonus: Additional points based on balance tier
-        let base_rewards = transaction_count * 10;
+        let base_rewards = transaction_count * points_per_tx;
         
-        let balance_bonus = if balance >= 10000 {
-            100 // Premium tier
-        } else if balance >= 5000 {
-            50 // Gold tier
-        } else if balance >= 1000 {
-            25 // Silver tier
+        let tier =
+            tier_for_thresholds(balance, silver_threshold, gold_threshold, premium_threshold);
+        let balance_bonus = tier_bonus(tier);
+
+        let total_rewards = base_rewards + balance_bonus;
+        
+        (total_rewards.reveal(), tier.reveal(), account_id)
+    }
+
+    /// Check whether an account qualifies for the premium tier
+    /// All three criteria must hold; only the combined `eligible` bool is revealed,
+    /// never which criterion failed.
+    #[instruction]
+    pub fn check_premium_eligibility(
+        balance_ctxt: Enc<Mxe, u64>,
+        transaction_count: u64,
+        account_age: u64,
+        min_balance: u64,
+        min_transaction_count: u64,
+        min_account_age: u64,
+    ) -> bool {
+        let balance = balance_ctxt.to_arcis();
+
+        let eligible = balance >= min_balance
+            && transaction_count >= min_transaction_count
+            && account_age >= min_account_age;
+
+        eligible.reveal()
+    }
+
+    /// Add one transfer to an account's AML throughput and flag excessive velocity
+    /// Inflows and outflows both increase the accumulator, so it measures gross
+    /// (not net) throughput within the window. When `reset_window` is set the
+    /// stored accumulator is ignored and counting starts from zero.
+    /// Only the `flagged` bool is revealed.
+    #[instruction]
+    pub fn check_aml_velocity(
+        throughput_ctxt: Enc<Mxe, u64>,
+        amount_ctxt: Enc<Shared, u64>,
+        reset_window: bool,
+        threshold: u64,
+    ) -> (Enc<Mxe, u64>, bool) {
+        let previous = if reset_window {
+            0
+        } else {
+            throughput_ctxt.to_arcis()
+        };
+        let amount = amount_ctxt.to_arcis();
+
+        // Saturate rather than wrap so a huge flow can never reset the counter
+        let throughput = add_saturating(previous, amount);
+        let flagged = throughput > threshold;
+
+        (throughput_ctxt.owner.from_arcis(throughput), flagged.reveal())
+    }
+
+    /// Attest that a payment's amount reached `threshold` without revealing it
+    /// Only the `meets_threshold` bool is revealed.
+    #[instruction]
+    pub fn prove_payment_received(amount_ctxt: Enc<Shared, u64>, threshold: u64) -> bool {
+        let amount = amount_ctxt.to_arcis();
+        (amount >= threshold).reveal()
+    }
+
+    /// Re-encrypt a payment's amount, stored encrypted to the sender, to one party
+    #[instruction]
+    pub fn reveal_transaction_amount(
+        amount_ctxt: Enc<Shared, u64>,
+        party: Shared,
+    ) -> Enc<Shared, u64> {
+        let amount = amount_ctxt.to_arcis();
+        party.from_arcis(amount)
+    }
+
+    /// Health check: encrypt a known constant under the MXE key and decrypt it again
+    #[instruction]
+    pub fn ping(mxe: Mxe) -> bool {
+        let ping_value: u64 = 0x1BA4_4B1C;
+        let probe = mxe.from_arcis(ping_value);
+        (probe.to_arcis() == ping_value).reveal()
+    }
+
+    /// The stored minimum reserve, or zero for an account without one
+    fn reserve_or_zero(min_reserve_ctxt: Enc<Mxe, u64>, has_min_reserve: bool) -> u64 {
+        if has_min_reserve {
+            min_reserve_ctxt.to_arcis()
+        } else {
+            0
+        }
+    }
+
+    /// u64 addition clamped at u64::MAX instead of wrapping
+    fn add_saturating(a: u64, b: u64) -> u64 {
+        let sum = a as u128 + b as u128;
+        if sum > u64::MAX as u128 {
+            u64::MAX
+        } else {
+            sum as u64
+        }
+    }
+
+    /// Fee charged on an operation: `fee_bps` basis points of `amount` plus `flat_fee`
+    /// Computed in u128 so the basis-point product cannot wrap.
+    fn compute_fee(amount: u64, fee_bps: u64, flat_fee: u64) -> u64 {
+        let fee = (amount as u128 * fee_bps as u128) / 10000 + flat_fee as u128;
+        if fee > u64::MAX as u128 {
+            u64::MAX
+        } else {
+            fee as u64
+        }
+    }
+
+    /// Balance tier for the given minimum balances of Silver, Gold and Premium
+    fn tier_for_thresholds(
+        balance: u64,
+        silver_threshold: u64,
+        gold_threshold: u64,
+        premium_threshold: u64,
+    ) -> u8 {
+        if balance >= premium_threshold {
+            3 // Premium tier
+        } else if balance >= gold_threshold {
+            2 // Gold tier
+        } else if balance >= silver_threshold {
+            1 // Silver tier
         } else {
             0 // Basic tier
+        }
+    }
+
+    /// Reward bonus for a balance tier
+    fn tier_bonus(tier: u8) -> u64 {
+        if tier == 3 {
+            100
+        } else if tier == 2 {
+            50
+        } else if tier == 1 {
+            25
+        } else {
+            0
+        }
+    }
+
+    /// Compute a single loyalty tier across up to five accounts of one customer
+    /// Slots at index >= account_count are padding and are ignored.
+    /// Score = points_per_tx per transaction + the balance tier bonus of each
+    /// account under the configured thresholds, summed in u128 so a large
+    /// transaction count cannot wrap it. Only the resulting tier (0-3) is
+    /// revealed. The primary account's id is passed back as is, like in
+    /// check_balance.
+    #[instruction]
+    pub fn compute_aggregate_loyalty(
+        balance_0_ctxt: Enc<Mxe, u64>,
+        balance_1_ctxt: Enc<Mxe, u64>,
+        balance_2_ctxt: Enc<Mxe, u64>,
+        balance_3_ctxt: Enc<Mxe, u64>,
+        balance_4_ctxt: Enc<Mxe, u64>,
+        total_transaction_count: u64,
+        account_count: u8,
+        points_per_tx: u64,
+        silver_threshold: u64,
+        gold_threshold: u64,
+        premium_threshold: u64,
+        account_id: u64,
+    ) -> (u8, u64) {
+        let balances = [
+            balance_0_ctxt.to_arcis(),
+            balance_1_ctxt.to_arcis(),
+            balance_2_ctxt.to_arcis(),
+            balance_3_ctxt.to_arcis(),
+            balance_4_ctxt.to_arcis(),
+        ];
+
+        let mut score = total_transaction_count as u128 * points_per_tx as u128;
+        for i in 0..5 {
+            if (i as u8) < account_count {
+                let tier = tier_for_thresholds(
+                    balances[i],
+                    silver_threshold,
+                    gold_threshold,
+                    premium_threshold,
+                );
+                score += tier_bonus(tier) as u128;
+            }
+        }
+
+        let tier: u8 = if score >= 1000 {
+            3
+        } else if score >= 500 {
+            2
+        } else if score >= 100 {
+            1
+        } else {
+            0
         };
 
-        let total_rewards = base_rewards + balance_bonus;
-        
-        total_rewards.reveal()
+        (tier.reveal(), account_id)
     }
 }
@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked};
 use arcium_anchor::prelude::*;
 use arcium_client::idl::arcium::types::CallbackAccount;
 
@@ -6,10 +7,319 @@ use arcium_client::idl::arcium::types::CallbackAccount;
 const COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS: u32 = comp_def_offset("initialize_accounts");
 const COMP_DEF_OFFSET_PROCESS_PAYMENT: u32 = comp_def_offset("process_payment");
 const COMP_DEF_OFFSET_CHECK_BALANCE: u32 = comp_def_offset("check_balance");
+const COMP_DEF_OFFSET_REVEAL_BALANCE_TO_OWNER: u32 = comp_def_offset("reveal_balance_to_owner");
+const COMP_DEF_OFFSET_CHECK_BALANCE_RANGE: u32 = comp_def_offset("check_balance_range");
+const COMP_DEF_OFFSET_COMPARE_BALANCES: u32 = comp_def_offset("compare_balances");
 const COMP_DEF_OFFSET_CALCULATE_REWARDS: u32 = comp_def_offset("calculate_rewards");
+const COMP_DEF_OFFSET_CHECK_PREMIUM_ELIGIBILITY: u32 =
+    comp_def_offset("check_premium_eligibility");
+const COMP_DEF_OFFSET_COMPUTE_AGGREGATE_LOYALTY: u32 =
+    comp_def_offset("compute_aggregate_loyalty");
+const COMP_DEF_OFFSET_CHECK_AML_VELOCITY: u32 = comp_def_offset("check_aml_velocity");
+const COMP_DEF_OFFSET_WITHDRAW: u32 = comp_def_offset("withdraw");
+const COMP_DEF_OFFSET_DEPOSIT: u32 = comp_def_offset("deposit");
+const COMP_DEF_OFFSET_REDEEM_REWARDS: u32 = comp_def_offset("redeem_rewards");
+const COMP_DEF_OFFSET_ACCRUE_INTEREST: u32 = comp_def_offset("accrue_interest");
+const COMP_DEF_OFFSET_SET_SPENDING_CAP: u32 = comp_def_offset("set_spending_cap");
+const COMP_DEF_OFFSET_DELEGATED_PAYMENT: u32 = comp_def_offset("delegated_payment");
+const COMP_DEF_OFFSET_BATCH_PAYMENT: u32 = comp_def_offset("batch_payment");
+const COMP_DEF_OFFSET_PROCESS_PAYMENT_WITH_FEE: u32 = comp_def_offset("process_payment_with_fee");
+const COMP_DEF_OFFSET_OPEN_CREDIT_LINE: u32 = comp_def_offset("open_credit_line");
+const COMP_DEF_OFFSET_PROCESS_PAYMENT_SIGNED: u32 = comp_def_offset("process_payment_signed");
+const COMP_DEF_OFFSET_REVEAL_AVAILABLE_CREDIT: u32 = comp_def_offset("reveal_available_credit");
+const COMP_DEF_OFFSET_OPEN_CURRENCY_BALANCE: u32 = comp_def_offset("open_currency_balance");
+const COMP_DEF_OFFSET_REFUND_PAYMENT: u32 = comp_def_offset("refund_payment");
+const COMP_DEF_OFFSET_SWEEP_BALANCE: u32 = comp_def_offset("sweep_balance");
+const COMP_DEF_OFFSET_CLOSE_ACCOUNT: u32 = comp_def_offset("close_account");
+const COMP_DEF_OFFSET_SCHEDULE_PAYMENT: u32 = comp_def_offset("schedule_payment");
+const COMP_DEF_OFFSET_RELEASE_SCHEDULED_PAYMENT: u32 =
+    comp_def_offset("release_scheduled_payment");
+const COMP_DEF_OFFSET_RECOVER_ACCOUNT: u32 = comp_def_offset("recover_account");
+const COMP_DEF_OFFSET_ROTATE_ENCRYPTION_KEY: u32 = comp_def_offset("rotate_encryption_key");
+const COMP_DEF_OFFSET_SET_DAILY_LIMIT: u32 = comp_def_offset("set_daily_limit");
+const COMP_DEF_OFFSET_SET_LARGE_TRANSFER_THRESHOLD: u32 =
+    comp_def_offset("set_large_transfer_threshold");
+const COMP_DEF_OFFSET_APPROVE_LARGE_TRANSFER: u32 = comp_def_offset("approve_large_transfer");
+const COMP_DEF_OFFSET_AGGREGATE_BALANCES: u32 = comp_def_offset("aggregate_balances");
+const COMP_DEF_OFFSET_REVEAL_TREASURY: u32 = comp_def_offset("reveal_treasury");
+const COMP_DEF_OFFSET_SEIZE_FUNDS: u32 = comp_def_offset("seize_funds");
+const COMP_DEF_OFFSET_SET_MIN_RESERVE: u32 = comp_def_offset("set_min_reserve");
+const COMP_DEF_OFFSET_RELEASE_ESCROWED_PAYMENT: u32 =
+    comp_def_offset("release_escrowed_payment");
+const COMP_DEF_OFFSET_PROCESS_PAYMENT_MULTI_SOURCE: u32 =
+    comp_def_offset("process_payment_multi_source");
+const COMP_DEF_OFFSET_PROVE_PAYMENT_RECEIVED: u32 = comp_def_offset("prove_payment_received");
+const COMP_DEF_OFFSET_REVEAL_TRANSACTION_AMOUNT: u32 =
+    comp_def_offset("reveal_transaction_amount");
+const COMP_DEF_OFFSET_PING: u32 = comp_def_offset("ping");
+
+const PROGRAM_CONFIG_SEED: &[u8] = b"program_config";
+const VALIDATOR_REWARDS_SEED: &[u8] = b"validator_rewards";
+const TREASURY_SEED: &[u8] = b"treasury";
+const USER_TX_INDEX_SEED: &[u8] = b"user_tx_index";
+const SCHEDULED_PAYMENT_SEED: &[u8] = b"scheduled_payment";
+const TOKEN_VAULT_SEED: &[u8] = b"token_vault";
+const PENDING_DEPOSIT_SEED: &[u8] = b"pending_deposit";
+
+/// Reward points redeemed for one unit of balance unless the admin changes it
+const DEFAULT_POINTS_PER_UNIT: u64 = 100;
+
+/// `Transaction::aml_recorded` bits marking which party's AML throughput includes it
+const AML_RECORDED_SENDER: u8 = 1 << 0;
+const AML_RECORDED_RECEIVER: u8 = 1 << 1;
+
+/// Maximum number of recovery guardians per account
+const MAX_GUARDIANS: usize = 5;
+
+/// Maximum number of accounts combined by `compute_aggregate_loyalty`
+const MAX_LOYALTY_ACCOUNTS: usize = 5;
+/// Maximum number of accounts totalled by one `aggregate_balances`
+const MAX_AGGREGATE_ACCOUNTS: usize = 8;
+/// Maximum number of comp defs one `bootstrap` call initializes
+const MAX_BOOTSTRAP_COMP_DEFS: usize = 5;
+
+/// Maximum number of receivers paid by one `batch_payment`
+const MAX_BATCH_RECEIVERS: usize = 8;
+/// Maximum number of source accounts one `process_payment_multi_source` may draw from
+const MAX_PAYMENT_SOURCES: usize = 4;
+/// Number of client-supplied nonces each account remembers to reject reuse
+const RECENT_NONCE_COUNT: usize = 8;
+
+/// Number of recent transaction ids kept by a `UserTxIndex`
+const MAX_TX_INDEX_ENTRIES: usize = 8;
+
+/// Byte offset of `UserAccount::mxe_encrypted_balance`: discriminator, account_id,
+/// owner_pubkey, encrypted_balance and balance_nonce precede it
+const USER_ACCOUNT_MXE_BALANCE_OFFSET: u32 = 8 + 8 + 32 + 32 + 16;
+/// Byte offset of `UserAccount::encrypted_daily_limit`
+const USER_ACCOUNT_DAILY_LIMIT_OFFSET: u32 = USER_ACCOUNT_MXE_BALANCE_OFFSET + 32 + 16;
+/// Byte offset of `UserAccount::encrypted_spent_today`
+const USER_ACCOUNT_SPENT_TODAY_OFFSET: u32 = USER_ACCOUNT_DAILY_LIMIT_OFFSET + 32 + 16;
+/// Byte offset of `UserAccount::credit_line.encrypted_limit`
+const USER_ACCOUNT_CREDIT_LIMIT_OFFSET: u32 = USER_ACCOUNT_SPENT_TODAY_OFFSET + 32 + 16;
+/// Byte offset of `UserAccount::credit_line.encrypted_drawn`
+const USER_ACCOUNT_CREDIT_DRAWN_OFFSET: u32 = USER_ACCOUNT_CREDIT_LIMIT_OFFSET + 32 + 16;
+/// Byte offset of `UserAccount::currencies`, after the credit line's `is_open`
+const USER_ACCOUNT_CURRENCIES_OFFSET: u32 = USER_ACCOUNT_CREDIT_DRAWN_OFFSET + 32 + 16 + 1;
+/// Serialized size of one `CurrencyBalance`
+const CURRENCY_BALANCE_SIZE: u32 = 2 + 32 + 16 + 32 + 16 + 1;
+/// Byte offset of `UserAccount::encrypted_large_transfer_threshold`
+const USER_ACCOUNT_LARGE_TRANSFER_THRESHOLD_OFFSET: u32 =
+    USER_ACCOUNT_CURRENCIES_OFFSET + MAX_CURRENCIES as u32 * CURRENCY_BALANCE_SIZE;
+/// Byte offset of `UserAccount::encrypted_min_reserve`
+const USER_ACCOUNT_MIN_RESERVE_OFFSET: u32 = USER_ACCOUNT_LARGE_TRANSFER_THRESHOLD_OFFSET + 32 + 16;
+
+/// Byte offset of `ScheduledPayment::encrypted_amount`
+const SCHEDULED_PAYMENT_AMOUNT_OFFSET: u32 = 8 + 8 + 32 + 32;
+/// Byte offset of `Transaction::encrypted_escrow`
+const TRANSACTION_ESCROW_OFFSET: u32 = 8 + 8 + 32 + 32 + 32 + 16 + 8 + 1 + 1 + 2 + 64 + 16;
+
+/// Number of currencies an account holds besides its base currency
+const MAX_CURRENCIES: usize = 4;
+
+/// Space of the `sign_pda_account` every queue instruction creates: discriminator
+/// plus `SignerAccount`
+const SIGN_PDA_SPACE: usize = 8 + 1;
+// Stops the build if `SignerAccount` outgrows the space allocated for it
+const _: () = assert!(SIGN_PDA_SPACE == 8 + SignerAccount::INIT_SPACE);
+
+const SECONDS_PER_DAY: i64 = 86_400;
+/// Inactivity after which `mark_dormant` may freeze an account, until the admin
+/// changes it
+const DEFAULT_DORMANCY_THRESHOLD: i64 = 365 * SECONDS_PER_DAY;
+/// How long reward points stay redeemable after they were last earned, until
+/// the admin changes it
+const DEFAULT_POINTS_EXPIRY_PERIOD: i64 = 365 * SECONDS_PER_DAY;
+/// Balance-rewriting computations an account may have queued at once, until the
+/// admin changes it
+const DEFAULT_MAX_IN_FLIGHT: u8 = 1;
+/// Length of the AML velocity window, until the admin changes it
+const DEFAULT_AML_WINDOW: i64 = SECONDS_PER_DAY;
+/// Gross AML throughput above which an account is flagged; nothing is flagged
+/// until the admin sets a real threshold
+const DEFAULT_AML_THRESHOLD: u64 = u64::MAX;
+/// Seconds after which an account's in-flight computations are presumed lost,
+/// since a callback that fails cannot release them
+const IN_FLIGHT_TIMEOUT: i64 = 10 * 60;
+
+/// Byte offset of the MXE-encrypted balance in `UserAccount::currencies[slot]`
+fn currency_mxe_balance_offset(slot: usize) -> u32 {
+    USER_ACCOUNT_CURRENCIES_OFFSET + slot as u32 * CURRENCY_BALANCE_SIZE + 2
+}
+
+/// Maximum number of interest periods compounded by one `accrue_interest`; the
+/// circuit's loop bound must match
+const MAX_INTEREST_PERIODS: i64 = 32;
+
+/// Most decimal places an account's amounts may be displayed with
+const MAX_DECIMALS: u8 = 18;
+
+/// Largest UTC offset `set_utc_offset` accepts, either side of UTC
+const MAX_UTC_OFFSET_SECONDS: i64 = 14 * 3_600;
+
+/// Local day number of a unix timestamp, used to reset the daily spend counter
+fn spend_day(timestamp: i64, utc_offset_seconds: i64) -> i64 {
+    (timestamp + utc_offset_seconds).div_euclid(SECONDS_PER_DAY)
+}
+
+/// Circuit arguments crediting a scheduled payment's escrow to `beneficiary`.
+fn release_scheduled_payment_arguments(
+    scheduled: &Account<ScheduledPayment>,
+    beneficiary: &Account<UserAccount>,
+    beneficiary_new_nonce: u128,
+) -> Vec<Argument> {
+    vec![
+        Argument::PlaintextU128(scheduled.amount_nonce),
+        Argument::Account(scheduled.key(), SCHEDULED_PAYMENT_AMOUNT_OFFSET, 32),
+        Argument::PlaintextU128(beneficiary.mxe_balance_nonce),
+        Argument::Account(beneficiary.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+        Argument::ArcisPubkey(beneficiary.owner_enc_pubkey),
+        Argument::PlaintextU128(beneficiary_new_nonce),
+    ]
+}
+
+/// Circuit arguments crediting a payment escrowed for acceptance to `beneficiary`.
+fn release_escrowed_payment_arguments(
+    transaction: &Account<Transaction>,
+    beneficiary: &Account<UserAccount>,
+    beneficiary_new_nonce: u128,
+) -> Result<Vec<Argument>> {
+    let mut args = vec![
+        Argument::PlaintextU128(transaction.escrow_nonce),
+        Argument::Account(transaction.key(), TRANSACTION_ESCROW_OFFSET, 32),
+    ];
+    args.extend(beneficiary.mxe_balance_arguments(beneficiary.key(), transaction.currency_id)?);
+    args.extend([
+        Argument::ArcisPubkey(beneficiary.owner_enc_pubkey),
+        Argument::PlaintextU128(beneficiary_new_nonce),
+    ]);
+    Ok(args)
+}
+
+/// Circuit arguments charging the configured fee for `operation`.
+///
+/// Every fee-bearing circuit takes `fee_bps` and `flat_fee` right after the amount
+/// and computes the fee with the shared `compute_fee` helper, so all operations
+/// charge the same way. An all-zero schedule charges nothing.
+fn fee_arguments(config: &ProgramConfig, operation: FeeOperation) -> [Argument; 2] {
+    let rule = config.fee_schedule.rule(operation);
+    [
+        Argument::PlaintextU64(rule.fee_bps as u64),
+        Argument::PlaintextU64(rule.flat_fee),
+    ]
+}
+
+/// Arguments of the `initialize_accounts` circuit, one group per parameter:
+/// initial_balance, mxe, client, client_again.
+fn initialize_accounts_arguments(
+    initial_balance: u64,
+    mxe_nonce: u128,
+    client_pubkey: [u8; 32],
+    client_nonce: u128,
+) -> Vec<Argument> {
+    vec![
+        Argument::PlaintextU64(initial_balance),
+        Argument::PlaintextU128(mxe_nonce),
+        Argument::ArcisPubkey(client_pubkey),
+        Argument::PlaintextU128(client_nonce),
+        Argument::ArcisPubkey(client_pubkey),
+        Argument::PlaintextU128(client_nonce),
+    ]
+}
+
+/// Emits `ComputationAbortedEvent` for a callback whose computation did not
+/// succeed, and returns the `Ok` the callback finishes with.
+///
+/// The callback first releases whatever its queue instruction reserved (in-flight
+/// slots, a record's pending status) and then succeeds, so that the cleanup and
+/// the event are not rolled back with it. Callbacks that reject a successful
+/// computation's result (e.g. an overflowing deposit) emit the event the same way.
+fn computation_aborted(operation: &str, account_id: u64) -> Result<()> {
+    emit!(ComputationAbortedEvent {
+        operation: operation.to_string(),
+        account_id,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Releases the in-flight slot of every `UserAccount` passed as a remaining
+/// account, for callbacks whose computation aborted before reaching them.
+fn end_remaining_computations(accounts: &[AccountInfo]) -> Result<()> {
+    for info in accounts {
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+        let mut data = info.try_borrow_mut_data()?;
+        let mut account = UserAccount::try_deserialize(&mut &data[..])?;
+        account.end_computation();
+        account.try_serialize(&mut &mut data[..])?;
+    }
+    Ok(())
+}
+
+/// Loads the `PendingDeposit` a deposit callback was queued with, checking that
+/// it records a deposit into `user_account`. A record already reclaimed has
+/// been closed and fails here, so its tokens are never credited as well.
+fn load_pending_deposit(info: &AccountInfo, user_account: Pubkey) -> Result<PendingDeposit> {
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+    let pending = PendingDeposit::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require_keys_eq!(pending.user_account, user_account, ErrorCode::Unauthorized);
+    Ok(pending)
+}
+
+/// Closes a program-owned account from a callback, where it cannot be an
+/// `Account` with a `close` constraint, returning its rent to `destination`.
+fn close_program_account(info: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let lamports = info.lamports();
+    let destination_lamports = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ErrorCode::Overflow)?;
+    **destination.try_borrow_mut_lamports()? = destination_lamports;
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&anchor_lang::system_program::ID);
+    info.resize(0)?;
+    Ok(())
+}
+
+/// Initializes one circuit's computation definition from the `Bootstrap` accounts
+/// and the comp def account passed alongside them.
+macro_rules! bootstrap_comp_def {
+    ($accounts:expr, $comp_def:expr, $init_accounts:ident) => {{
+        let mut init_accounts = $init_accounts {
+            payer: $accounts.payer.clone(),
+            mxe_account: $accounts.mxe_account.clone(),
+            comp_def_account: UncheckedAccount::try_from($comp_def),
+            arcium_program: $accounts.arcium_program.clone(),
+            system_program: $accounts.system_program.clone(),
+        };
+        init_comp_def(&mut init_accounts, true, 0, None, None)?;
+        // The MXE account records every comp def, so pick up the new one
+        $accounts.mxe_account.reload()?;
+    }};
+}
 
 declare_id!("Hcmhr2Leu8S6XgsjCjXX4yqgHFYP4X7Rvc23kUmmDJ22");
 
+/// Every instruction that reads or changes an account's private data must be
+/// signed by the account's `owner_pubkey`, and fails with `Unauthorized`
+/// otherwise. The exceptions act through a designated authority instead:
+///
+/// - the program admin: `check_balance_range`, `compare_balances`,
+///   `aggregate_balances`, `reveal_treasury`, `accrue_interest`, `check_aml_velocity`,
+///   `open_credit_line`, `flag_transaction`, `resolve_dispute`, `mark_dormant`,
+///   `set_paused` and the `update_*` config instructions; `freeze_account` and `unfreeze_account`
+///   accept either the owner or the admin
+/// - a spending delegate: `delegated_payment`, within its cap
+/// - the account's guardians: `approve_recovery`, after which anyone may send
+///   `recover_account` to apply the quorum's decision
+/// - the receiver of a payment: `refund_transaction`, or the admin once the
+///   payment is held
+/// - the sender's co-signer: `approve_large_transfer`, and `remove_co_signer`
+///   together with the owner
+/// - the receiver of a payment held for acceptance: `accept_payment` and
+///   `reject_payment`
+/// - the configured seizure authority: `seize_funds`, against frozen accounts
+/// - `initialize_user_account`, whose signer becomes the owner
 #[arcium_program]
 pub mod ibank {
     use super::*;
@@ -31,10 +341,16 @@ pub mod ibank {
     ///
     /// # Arguments
     /// * `account_id` - Unique identifier for this account
-    /// * `initial_balance` - Starting balance (will be encrypted)
+    /// * `initial_balance` - Starting balance (will be encrypted), at most the
+    ///   configured `max_initial_balance`
     /// * `mxe_nonce` - Cryptographic nonce for MXE operations  
-    /// * `client_pubkey` - User's encryption public key
+    /// * `client_pubkey` - User's encryption public key, not all zero
     /// * `client_nonce` - User's cryptographic nonce
+    /// * `currency_id` - Currency of the initial balance, the account's base currency
+    /// * `decimals` - Decimal places wallets display amounts with, at most
+    ///   `MAX_DECIMALS`; stored metadata only, balances stay raw units
+    /// * `label_ciphertext` - Account label encrypted by the owner's client
+    /// * `label_nonce` - Nonce used for `label_ciphertext`
     pub fn initialize_user_account(
         ctx: Context<InitializeUserAccount>,
         computation_offset: u64,
@@ -43,7 +359,18 @@ pub mod ibank {
         mxe_nonce: u128,
         client_pubkey: [u8; 32],
         client_nonce: u128,
+        currency_id: u16,
+        decimals: u8,
+        label_ciphertext: [u8; 32],
+        label_nonce: u128,
     ) -> Result<()> {
+        require!(
+            initial_balance <= ctx.accounts.program_config.max_initial_balance,
+            ErrorCode::InitialBalanceTooHigh
+        );
+        require!(decimals <= MAX_DECIMALS, ErrorCode::InvalidDecimals);
+        require!(client_pubkey != [0; 32], ErrorCode::InvalidEncryptionPubkey);
+
         // Initialize the user account
         let user_account = &mut ctx.accounts.user_account;
         user_account.bump = ctx.bumps.user_account;
@@ -51,20 +378,56 @@ pub mod ibank {
         user_account.owner_pubkey = ctx.accounts.payer.key();
         user_account.encrypted_balance = [0; 32];
         user_account.balance_nonce = 0;
+        user_account.mxe_encrypted_balance = [0; 32];
+        user_account.mxe_balance_nonce = 0;
+        user_account.encrypted_daily_limit = [0; 32];
+        user_account.daily_limit_nonce = 0;
+        user_account.encrypted_spent_today = [0; 32];
+        user_account.spent_today_nonce = 0;
+        user_account.credit_line = CreditLine::default();
+        user_account.currencies = [CurrencyBalance::default(); MAX_CURRENCIES];
+        user_account.encrypted_large_transfer_threshold = [0; 32];
+        user_account.large_transfer_threshold_nonce = 0;
+        user_account.encrypted_min_reserve = [0; 32];
+        user_account.min_reserve_nonce = 0;
+        user_account.currency_count = 0;
+        user_account.base_currency_id = currency_id;
+        user_account.decimals = decimals;
+        // The label never enters the computation; it is stored as the client sent it
+        user_account.encrypted_label = label_ciphertext;
+        user_account.label_nonce = label_nonce;
+        user_account.has_daily_limit = false;
+        user_account.co_signer = None;
+        user_account.has_large_transfer_threshold = false;
+        user_account.has_min_reserve = false;
+        user_account.require_acceptance = false;
+        user_account.last_spend_reset = 0;
+        user_account.utc_offset_seconds = 0;
         user_account.transaction_count = 0;
+        user_account.sequence = 0;
         user_account.reward_points = 0;
+        user_account.points_updated_ts = 0;
+        user_account.points_expiry_ts = 0;
         user_account.owner_enc_pubkey = client_pubkey;
         user_account.account_state = AccountState::Initializing;
+        user_account.created_at = Clock::get()?.unix_timestamp;
+        user_account.last_accrual_ts = user_account.created_at;
+        user_account.last_activity_ts = user_account.created_at;
+        user_account.recent_nonces = [0; RECENT_NONCE_COUNT];
+        user_account.recent_nonce_cursor = 0;
+        user_account.in_flight_count = 0;
+        user_account.in_flight_since = 0;
+        user_account.use_nonce(mxe_nonce)?;
+        user_account.use_nonce(client_nonce)?;
+
+        // Shared by every account of this owner, so it may already exist
+        let tx_index = &mut ctx.accounts.tx_index;
+        tx_index.owner = ctx.accounts.payer.key();
+        tx_index.bump = ctx.bumps.tx_index;
 
         // Queue the account initialization computation
-        let args = vec![
-            Argument::PlaintextU64(initial_balance),
-            Argument::PlaintextU128(mxe_nonce),
-            Argument::ArcisPubkey(client_pubkey),
-            Argument::PlaintextU128(client_nonce),
-            Argument::ArcisPubkey(client_pubkey),
-            Argument::PlaintextU128(client_nonce),
-        ];
+        let args =
+            initialize_accounts_arguments(initial_balance, mxe_nonce, client_pubkey, client_nonce);
 
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
@@ -95,7 +458,17 @@ pub mod ibank {
                     field_1: client_bal,
                 },
             }) => (mxe_bal, client_bal),
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+            _ => {
+                // Returning an error would leave the account stuck in Initializing;
+                // mark it failed so the owner can retry_initialization
+                let user_account = &mut ctx.accounts.user_account;
+                user_account.set_state(AccountState::InitFailed)?;
+                emit!(AccountInitFailedEvent {
+                    account_id: user_account.account_id,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+                return Ok(());
+            }
         };
 
         let balance_nonce = client_balance.nonce;
@@ -104,16 +477,71 @@ pub mod ibank {
         let user_account = &mut ctx.accounts.user_account;
         user_account.encrypted_balance = balance_ciphertext;
         user_account.balance_nonce = balance_nonce;
-        user_account.account_state = AccountState::Active;
+        user_account.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+        user_account.mxe_balance_nonce = mxe_balance.nonce;
+        user_account.set_state(AccountState::Active)?;
 
         emit!(AccountInitializedEvent {
             account_id: user_account.account_id,
             owner: user_account.owner_pubkey,
             balance_nonce,
+            decimals: user_account.decimals,
         });
         Ok(())
     }
 
+    /// Re-queues the initialization of an account whose first attempt aborted.
+    ///
+    /// Only valid in `InitFailed`, and only for the account owner. Takes the
+    /// same inputs as `initialize_user_account`.
+    pub fn retry_initialization(
+        ctx: Context<RetryInitialization>,
+        computation_offset: u64,
+        _account_id: u64,
+        initial_balance: u64,
+        mxe_nonce: u128,
+        client_pubkey: [u8; 32],
+        client_nonce: u128,
+    ) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(client_pubkey != [0; 32], ErrorCode::InvalidEncryptionPubkey);
+        require!(
+            user_account.account_state == AccountState::InitFailed,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            initial_balance <= ctx.accounts.program_config.max_initial_balance,
+            ErrorCode::InitialBalanceTooHigh
+        );
+        user_account.use_nonce(mxe_nonce)?;
+        user_account.use_nonce(client_nonce)?;
+        user_account.owner_enc_pubkey = client_pubkey;
+        user_account.set_state(AccountState::Initializing)?;
+
+        let args =
+            initialize_accounts_arguments(initial_balance, mxe_nonce, client_pubkey, client_nonce);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![InitializeAccountsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
     pub fn init_process_payment_comp_def(
         ctx: Context<InitProcessPaymentCompDef>,
     ) -> Result<()> {
@@ -125,9 +553,39 @@ pub mod ibank {
         ctx: Context<ProcessPayment>,
         computation_offset: u64,
         transaction_id: u64,
-        amount: u64,
+        amount_ciphertext: [u8; 32],
+        amount_nonce: u128,
+        sender_enc_pubkey: [u8; 32],
         receiver_new_nonce: u128,
+        tip_ciphertext: [u8; 32],
+        tip_nonce: u128,
+        sender_new_nonce: u128,
+        escrow_nonce: u128,
+        currency_id: u16,
+        memo_ciphertext: [u8; 64],
+        memo_nonce: u128,
+        expected_sequence: u64,
     ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.sender_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        // A retried request carries the sequence it was built against, so it
+        // cannot be applied a second time under a new transaction id
+        require!(
+            expected_sequence == ctx.accounts.sender_account.sequence,
+            ErrorCode::StaleSequence
+        );
+        require!(
+            ctx.accounts.sender_account.key() != ctx.accounts.receiver_account.key(),
+            ErrorCode::SelfTransfer
+        );
+        // The stored amount is re-encrypted under this key, so it must be the sender's
+        require!(
+            sender_enc_pubkey == ctx.accounts.sender_account.owner_enc_pubkey,
+            ErrorCode::InvalidEncryptionPubkey
+        );
         require!(
             ctx.accounts.sender_account.account_state == AccountState::Active,
             ErrorCode::InvalidAccountState
@@ -137,7 +595,12 @@ pub mod ibank {
             ErrorCode::InvalidAccountState
         );
 
+        // A freshly created transaction PDA is zeroed; a used one records its sender
         let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.sender == Pubkey::default(),
+            ErrorCode::DuplicateTransactionId
+        );
         transaction.bump = ctx.bumps.transaction;
         transaction.transaction_id = transaction_id;
         transaction.sender = ctx.accounts.sender_account.key();
@@ -146,17 +609,105 @@ pub mod ibank {
         transaction.amount_nonce = 0;
         transaction.timestamp = Clock::get()?.unix_timestamp;
         transaction.status = TransactionStatus::Processing;
+        transaction.aml_recorded = 0;
+        transaction.currency_id = currency_id;
+        // The memo never enters the computation; it is stored as the client sent it
+        transaction.encrypted_memo = memo_ciphertext;
+        transaction.memo_nonce = memo_nonce;
+        transaction.computation_offset = computation_offset;
+        transaction.encrypted_escrow = [0; 32];
+        transaction.escrow_nonce = 0;
+        transaction.requires_acceptance = ctx.accounts.receiver_account.require_acceptance;
 
-        let args = vec![
-            Argument::PlaintextU128(ctx.accounts.sender_account.balance_nonce),
-            Argument::Account(ctx.accounts.sender_account.key(), 8 + 8 + 32, 32),
-            Argument::PlaintextU128(ctx.accounts.receiver_account.balance_nonce),
-            Argument::Account(ctx.accounts.receiver_account.key(), 8 + 8 + 32, 32),
-            Argument::PlaintextU64(amount),
+        // When both accounts share an owner the two indexes are the same account;
+        // recording into both copies leaves it with a single entry either way
+        ctx.accounts.sender_tx_index.record(transaction_id);
+        ctx.accounts.receiver_tx_index.record(transaction_id);
+
+        let sender_account = &mut ctx.accounts.sender_account;
+        sender_account.sequence = sender_account
+            .sequence
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+        sender_account.last_activity_ts = ctx.accounts.transaction.timestamp;
+        sender_account.use_nonce(sender_new_nonce)?;
+
+        ctx.accounts.validator_rewards.bump = ctx.bumps.validator_rewards;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+
+        // The first payment of the sender's new local day starts the spend
+        // counter from zero
+        let last_reset = ctx.accounts.sender_account.last_spend_reset;
+        let utc_offset = ctx.accounts.sender_account.utc_offset_seconds;
+        let reset_spent_today =
+            spend_day(last_reset, utc_offset) != spend_day(transaction.timestamp, utc_offset);
+
+        // Both balances are read from the slot holding the payment's currency
+        let sender_account = &ctx.accounts.sender_account;
+        let receiver_account = &ctx.accounts.receiver_account;
+        let mut args = Vec::new();
+        args.extend(sender_account.mxe_balance_arguments(sender_account.key(), currency_id)?);
+        args.extend(receiver_account.mxe_balance_arguments(receiver_account.key(), currency_id)?);
+        args.extend([
+            Argument::ArcisPubkey(sender_enc_pubkey),
+            Argument::PlaintextU128(amount_nonce),
+            Argument::EncryptedU64(amount_ciphertext),
+        ]);
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Payment));
+        args.extend([
+            Argument::ArcisPubkey(sender_enc_pubkey),
+            Argument::PlaintextU128(tip_nonce),
+            Argument::EncryptedU64(tip_ciphertext),
+            Argument::PlaintextU128(ctx.accounts.validator_rewards.total_nonce),
+            Argument::Account(ctx.accounts.validator_rewards.key(), 8, 32),
+            Argument::PlaintextBool(ctx.accounts.validator_rewards.initialized),
+            Argument::PlaintextU128(ctx.accounts.treasury.total_nonce),
+            Argument::Account(ctx.accounts.treasury.key(), 8, 32),
+            Argument::PlaintextBool(ctx.accounts.treasury.initialized),
+            Argument::PlaintextU128(ctx.accounts.sender_account.daily_limit_nonce),
+            Argument::Account(
+                ctx.accounts.sender_account.key(),
+                USER_ACCOUNT_DAILY_LIMIT_OFFSET,
+                32,
+            ),
+            Argument::PlaintextBool(ctx.accounts.sender_account.has_daily_limit),
+            Argument::PlaintextU128(ctx.accounts.sender_account.spent_today_nonce),
+            Argument::Account(
+                ctx.accounts.sender_account.key(),
+                USER_ACCOUNT_SPENT_TODAY_OFFSET,
+                32,
+            ),
+            Argument::PlaintextBool(reset_spent_today),
+            Argument::PlaintextU128(ctx.accounts.sender_account.large_transfer_threshold_nonce),
+            Argument::Account(
+                ctx.accounts.sender_account.key(),
+                USER_ACCOUNT_LARGE_TRANSFER_THRESHOLD_OFFSET,
+                32,
+            ),
+            Argument::PlaintextBool(
+                ctx.accounts.sender_account.co_signer.is_some()
+                    && ctx.accounts.sender_account.has_large_transfer_threshold,
+            ),
+        ]);
+        args.extend(
+            ctx.accounts
+                .sender_account
+                .min_reserve_arguments(ctx.accounts.sender_account.key(), currency_id),
+        );
+        args.extend([
+            Argument::PlaintextBool(ctx.accounts.transaction.requires_acceptance),
+            Argument::PlaintextU128(escrow_nonce),
             Argument::ArcisPubkey(ctx.accounts.receiver_account.owner_enc_pubkey),
             Argument::PlaintextU128(receiver_new_nonce),
-        ];
+            Argument::ArcisPubkey(sender_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+        ]);
 
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.validator_rewards.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
         queue_computation(
@@ -169,6 +720,22 @@ pub mod ibank {
                     pubkey: ctx.accounts.transaction.key(),
                     is_writable: true,
                 },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.validator_rewards.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.treasury.key(),
+                    is_writable: true,
+                },
             ])],
         )?;
         Ok(())
@@ -179,61 +746,314 @@ pub mod ibank {
         ctx: Context<ProcessPaymentCallback>,
         output: ComputationOutputs<ProcessPaymentOutput>,
     ) -> Result<()> {
-        let (_new_sender_balance, _new_receiver_balance, is_sufficient) = match output {
+        let (
+            new_sender_balance,
+            new_sender_client_balance,
+            new_receiver_mxe_balance,
+            new_receiver_balance,
+            encrypted_amount,
+            new_validator_rewards,
+            new_treasury,
+            new_spent_today,
+            is_sufficient,
+            limit_exceeded,
+            needs_approval,
+            escrowed_amount,
+        ) = match output {
             ComputationOutputs::Success(ProcessPaymentOutput {
                 field_0: ProcessPaymentOutputStruct0 {
                     field_0: sender_bal,
-                    field_1: receiver_bal,
-                    field_2: sufficient,
+                    field_1: sender_client_bal,
+                    field_2: receiver_mxe_bal,
+                    field_3: receiver_bal,
+                    field_4: amount,
+                    field_5: rewards,
+                    field_6: treasury,
+                    field_7: spent_today,
+                    field_8: sufficient,
+                    field_9: exceeded,
+                    field_10: approval,
+                    field_11: escrowed,
                 },
-            }) => (sender_bal, receiver_bal, sufficient),
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+            }) => (
+                sender_bal,
+                sender_client_bal,
+                receiver_mxe_bal,
+                receiver_bal,
+                amount,
+                rewards,
+                treasury,
+                spent_today,
+                sufficient,
+                exceeded,
+                approval,
+                escrowed,
+            ),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                ctx.accounts.receiver_account.end_computation();
+                ctx.accounts.validator_rewards.end_computation();
+                ctx.accounts.treasury.end_computation();
+                ctx.accounts.transaction.status = TransactionStatus::Failed;
+                return computation_aborted(
+                    "process_payment",
+                    ctx.accounts.sender_account.account_id,
+                )
+            }
         };
 
+        ctx.accounts.sender_account.end_computation();
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.validator_rewards.end_computation();
+        ctx.accounts.treasury.end_computation();
+
         if !is_sufficient {
             ctx.accounts.transaction.status = TransactionStatus::Failed;
             emit!(PaymentFailedEvent {
                 transaction_id: ctx.accounts.transaction.transaction_id,
+                computation_offset: ctx.accounts.transaction.computation_offset,
                 reason: "Insufficient balance".to_string(),
             });
-            return Err(ErrorCode::InsufficientBalance.into());
+            return Ok(());
+        }
+
+        // The spend counter is stored even when the limit rejects the payment, so
+        // a new day's reset sticks. Nothing else moves when over the limit.
+        let transaction_timestamp = ctx.accounts.transaction.timestamp;
+        let sender_account = &mut ctx.accounts.sender_account;
+        sender_account.encrypted_spent_today = new_spent_today.ciphertexts[0];
+        sender_account.spent_today_nonce = new_spent_today.nonce;
+        let utc_offset = sender_account.utc_offset_seconds;
+        if spend_day(sender_account.last_spend_reset, utc_offset)
+            != spend_day(transaction_timestamp, utc_offset)
+        {
+            sender_account.last_spend_reset = transaction_timestamp;
+        }
+
+        if limit_exceeded {
+            ctx.accounts.transaction.status = TransactionStatus::Failed;
+            emit!(SpendLimitExceededEvent {
+                transaction_id: ctx.accounts.transaction.transaction_id,
+                computation_offset: ctx.accounts.transaction.computation_offset,
+                account_id: ctx.accounts.sender_account.account_id,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            return Ok(());
+        }
+
+        // Held back for the co-signer: only the amount is kept, for the approval
+        if needs_approval {
+            let transaction = &mut ctx.accounts.transaction;
+            transaction.encrypted_amount = encrypted_amount.ciphertexts[0];
+            transaction.amount_nonce = encrypted_amount.nonce;
+            transaction.status = TransactionStatus::PendingApproval;
+            emit!(ApprovalRequiredEvent {
+                transaction_id: transaction.transaction_id,
+                computation_offset: transaction.computation_offset,
+                account_id: ctx.accounts.sender_account.account_id,
+                co_signer: ctx.accounts.sender_account.co_signer.unwrap_or_default(),
+            });
+            return Ok(());
+        }
+
+        // Both balances are stored MXE-encrypted for later circuits, and each
+        // party also gets a copy encrypted to their own key.
+        let currency_id = ctx.accounts.transaction.currency_id;
+        let sender_account = &mut ctx.accounts.sender_account;
+        let prev_balance_nonce = sender_account.client_balance_nonce(currency_id)?;
+        sender_account.store_balance(
+            currency_id,
+            (new_sender_balance.ciphertexts[0], new_sender_balance.nonce),
+            (new_sender_client_balance.ciphertexts[0], new_sender_client_balance.nonce),
+        )?;
+        sender_account.transaction_count = sender_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // A receiver requiring acceptance is credited by `accept_payment` instead
+        let requires_acceptance = ctx.accounts.transaction.requires_acceptance;
+        if !requires_acceptance {
+            let receiver_account = &mut ctx.accounts.receiver_account;
+            receiver_account.store_balance(
+                currency_id,
+                (new_receiver_mxe_balance.ciphertexts[0], new_receiver_mxe_balance.nonce),
+                (new_receiver_balance.ciphertexts[0], new_receiver_balance.nonce),
+            )?;
+            receiver_account.transaction_count = receiver_account
+                .transaction_count
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
         }
 
-        ctx.accounts.transaction.status = TransactionStatus::Completed;
+        let validator_rewards = &mut ctx.accounts.validator_rewards;
+        validator_rewards.encrypted_total = new_validator_rewards.ciphertexts[0];
+        validator_rewards.total_nonce = new_validator_rewards.nonce;
+        validator_rewards.initialized = true;
+
+        ctx.accounts.treasury.store_total((new_treasury.ciphertexts[0], new_treasury.nonce));
+
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.encrypted_amount = encrypted_amount.ciphertexts[0];
+        transaction.amount_nonce = encrypted_amount.nonce;
+        if requires_acceptance {
+            transaction.encrypted_escrow = escrowed_amount.ciphertexts[0];
+            transaction.escrow_nonce = escrowed_amount.nonce;
+            transaction.status = TransactionStatus::PendingAcceptance;
+            emit!(PaymentPendingAcceptanceEvent {
+                transaction_id: transaction.transaction_id,
+                computation_offset: transaction.computation_offset,
+                sender: transaction.sender,
+                receiver: transaction.receiver,
+                timestamp: transaction.timestamp,
+            });
+            return Ok(());
+        }
+        transaction.status = TransactionStatus::Completed;
 
         emit!(PaymentProcessedEvent {
             transaction_id: ctx.accounts.transaction.transaction_id,
+            computation_offset: ctx.accounts.transaction.computation_offset,
             sender: ctx.accounts.transaction.sender,
             receiver: ctx.accounts.transaction.receiver,
             timestamp: ctx.accounts.transaction.timestamp,
+            sender_new_balance: new_sender_client_balance.ciphertexts[0],
+            sender_new_balance_nonce: new_sender_client_balance.nonce,
+            prev_balance_nonce,
         });
         Ok(())
     }
 
-    pub fn init_check_balance_comp_def(
-        ctx: Context<InitCheckBalanceCompDef>,
-    ) -> Result<()> {
+    pub fn init_batch_payment_comp_def(ctx: Context<InitBatchPaymentCompDef>) -> Result<()> {
         init_comp_def(ctx.accounts, true, 0, None, None)?;
         Ok(())
     }
 
-    pub fn check_balance(
-        ctx: Context<CheckBalance>,
+    /// Pays several receivers from one sender balance atomically.
+    ///
+    /// The receivers' `UserAccount`s are passed as remaining accounts, in the same
+    /// order as `payments`, and must share the sender's base currency. The payment
+    /// fees go to the treasury. If the balance does not cover every amount plus
+    /// its payment fee, no account changes and `BatchPaymentFailedEvent` is
+    /// emitted. The reserve, daily limit and co-signer checks of `process_payment`
+    /// are not run here, so a sender with any of them is refused, and receivers
+    /// that require acceptance are refused since nothing is escrowed.
+    ///
+    /// # Arguments
+    /// * `payments` - Receivers and amounts, at most `MAX_BATCH_RECEIVERS`
+    pub fn batch_payment(
+        ctx: Context<BatchPayment>,
         computation_offset: u64,
-        _account_id: u64,
-        threshold: u64,
+        payments: Vec<BatchPaymentEntry>,
     ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let sender = &ctx.accounts.sender_account;
         require!(
-            ctx.accounts.user_account.account_state == AccountState::Active,
+            sender.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            sender.account_state == AccountState::Active,
             ErrorCode::InvalidAccountState
         );
+        require!(
+            !sender.has_min_reserve && !sender.has_daily_limit && sender.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(
+            !payments.is_empty()
+                && payments.len() <= MAX_BATCH_RECEIVERS
+                && payments.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidAccountCount
+        );
 
-        let args = vec![
-            Argument::PlaintextU128(ctx.accounts.user_account.balance_nonce),
-            Argument::Account(ctx.accounts.user_account.key(), 8 + 8 + 32, 32),
-            Argument::PlaintextU64(threshold),
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        // (account, balance nonce, encryption key, output nonce) per slot
+        let mut receivers = Vec::with_capacity(MAX_BATCH_RECEIVERS);
+        for (payment, info) in payments.iter().zip(ctx.remaining_accounts.iter()) {
+            require_keys_eq!(info.key(), payment.receiver, ErrorCode::InvalidAccountCount);
+            require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+            require!(info.key() != sender.key(), ErrorCode::SelfTransfer);
+            require!(
+                receivers.iter().all(|(key, _, _, _)| *key != info.key()),
+                ErrorCode::DuplicateAccount
+            );
+            let mut data = info.try_borrow_mut_data()?;
+            let mut receiver = UserAccount::try_deserialize(&mut &data[..])?;
+            require!(
+                receiver.account_state == AccountState::Active,
+                ErrorCode::InvalidAccountState
+            );
+            require!(
+                receiver.base_currency_id == sender.base_currency_id,
+                ErrorCode::CurrencyNotSupported
+            );
+            require!(!receiver.require_acceptance, ErrorCode::UnsupportedAccountSettings);
+            // Credited by the callback, so counted in flight; an error on a later
+            // receiver reverts the earlier counts with the rest of the instruction
+            receiver.begin_computation(max_in_flight)?;
+            receiver.try_serialize(&mut &mut data[..])?;
+            receivers.push((
+                info.key(),
+                receiver.mxe_balance_nonce,
+                receiver.owner_enc_pubkey,
+                payment.receiver_new_nonce,
+            ));
+        }
+        let receiver_count = receivers.len() as u8;
+
+        let mut args = vec![
+            Argument::PlaintextU128(sender.mxe_balance_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+        ];
+        // Padding slots read the sender's balance and are ignored by the circuit
+        for slot in 0..MAX_BATCH_RECEIVERS {
+            let (key, nonce) = receivers
+                .get(slot)
+                .map(|(key, nonce, _, _)| (*key, *nonce))
+                .unwrap_or((sender.key(), sender.mxe_balance_nonce));
+            args.push(Argument::PlaintextU128(nonce));
+            args.push(Argument::Account(key, USER_ACCOUNT_MXE_BALANCE_OFFSET, 32));
+        }
+        for slot in 0..MAX_BATCH_RECEIVERS {
+            let amount = payments.get(slot).map(|p| p.amount).unwrap_or(0);
+            args.push(Argument::PlaintextU64(amount));
+        }
+        args.push(Argument::PlaintextU8(receiver_count));
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Payment));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+        for slot in 0..MAX_BATCH_RECEIVERS {
+            // Fresh per-computation nonce for the discarded padding outputs
+            let (enc_pubkey, nonce) = receivers
+                .get(slot)
+                .map(|(_, _, enc_pubkey, nonce)| (*enc_pubkey, *nonce))
+                .unwrap_or((
+                    sender.owner_enc_pubkey,
+                    (computation_offset as u128) | ((slot as u128) << 64),
+                ));
+            args.push(Argument::ArcisPubkey(enc_pubkey));
+            args.push(Argument::PlaintextU128(nonce));
+        }
+
+        // Named callback accounts first, then the receivers as remaining accounts
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: sender.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.treasury.key(),
+                is_writable: true,
+            },
         ];
+        callback_accounts.extend(receivers.iter().map(|(key, _, _, _)| CallbackAccount {
+            pubkey: *key,
+            is_writable: true,
+        }));
 
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
         queue_computation(
@@ -241,55 +1061,257 @@ pub mod ibank {
             computation_offset,
             args,
             None,
-            vec![CheckBalanceCallback::callback_ix(&[CallbackAccount {
-                pubkey: ctx.accounts.user_account.key(),
-                is_writable: true,
-            }])],
+            vec![BatchPaymentCallback::callback_ix(&callback_accounts)],
         )?;
         Ok(())
     }
 
-    #[arcium_callback(encrypted_ix = "check_balance")]
-    pub fn check_balance_callback(
-        ctx: Context<CheckBalanceCallback>,
-        output: ComputationOutputs<CheckBalanceOutput>,
+    #[arcium_callback(encrypted_ix = "batch_payment")]
+    pub fn batch_payment_callback(
+        ctx: Context<BatchPaymentCallback>,
+        output: ComputationOutputs<BatchPaymentOutput>,
     ) -> Result<()> {
-        let is_above_threshold = match output {
-            ComputationOutputs::Success(CheckBalanceOutput { field_0: result }) => result,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+        let (
+            new_sender_balance,
+            new_receiver_mxe_balances,
+            new_receiver_balances,
+            new_treasury,
+            approved,
+        ) = match output {
+            ComputationOutputs::Success(BatchPaymentOutput {
+                field_0: BatchPaymentOutputStruct0 {
+                    field_0: sender,
+                    field_1: receiver_mxe_0,
+                    field_2: receiver_mxe_1,
+                    field_3: receiver_mxe_2,
+                    field_4: receiver_mxe_3,
+                    field_5: receiver_mxe_4,
+                    field_6: receiver_mxe_5,
+                    field_7: receiver_mxe_6,
+                    field_8: receiver_mxe_7,
+                    field_9: receiver_0,
+                    field_10: receiver_1,
+                    field_11: receiver_2,
+                    field_12: receiver_3,
+                    field_13: receiver_4,
+                    field_14: receiver_5,
+                    field_15: receiver_6,
+                    field_16: receiver_7,
+                    field_17: treasury,
+                    field_18: approved,
+                },
+            }) => (
+                sender,
+                [
+                    receiver_mxe_0,
+                    receiver_mxe_1,
+                    receiver_mxe_2,
+                    receiver_mxe_3,
+                    receiver_mxe_4,
+                    receiver_mxe_5,
+                    receiver_mxe_6,
+                    receiver_mxe_7,
+                ],
+                [
+                    receiver_0, receiver_1, receiver_2, receiver_3, receiver_4, receiver_5,
+                    receiver_6, receiver_7,
+                ],
+                treasury,
+                approved,
+            ),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                end_remaining_computations(ctx.remaining_accounts)?;
+                ctx.accounts.treasury.end_computation();
+                return computation_aborted("batch_payment", ctx.accounts.sender_account.account_id)
+            }
         };
 
-        emit!(BalanceCheckEvent {
-            account_id: ctx.accounts.user_account.account_id,
-            is_above_threshold,
-            timestamp: Clock::get()?.unix_timestamp,
+        ctx.accounts.sender_account.end_computation();
+        ctx.accounts.treasury.end_computation();
+
+        // Receivers were checked when queued; only the real slots are passed back.
+        // Each one is released, and credited on success.
+        let balances = new_receiver_mxe_balances.into_iter().zip(new_receiver_balances);
+        for (info, (mxe_balance, balance)) in ctx.remaining_accounts.iter().zip(balances) {
+            require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+            let mut data = info.try_borrow_mut_data()?;
+            let mut receiver = UserAccount::try_deserialize(&mut &data[..])?;
+            receiver.end_computation();
+            if approved {
+                receiver.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+                receiver.mxe_balance_nonce = mxe_balance.nonce;
+                receiver.encrypted_balance = balance.ciphertexts[0];
+                receiver.balance_nonce = balance.nonce;
+                receiver.transaction_count = receiver
+                    .transaction_count
+                    .checked_add(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            receiver.try_serialize(&mut &mut data[..])?;
+        }
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !approved {
+            emit!(BatchPaymentFailedEvent {
+                sender: ctx.accounts.sender_account.key(),
+                reason: "Insufficient balance".to_string(),
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        ctx.accounts.treasury.store_total((new_treasury.ciphertexts[0], new_treasury.nonce));
+
+        let sender_account = &mut ctx.accounts.sender_account;
+        sender_account.mxe_encrypted_balance = new_sender_balance.ciphertexts[0];
+        sender_account.mxe_balance_nonce = new_sender_balance.nonce;
+        sender_account.transaction_count = sender_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(BatchPaymentEvent {
+            sender: sender_account.key(),
+            receiver_count: ctx.remaining_accounts.len() as u8,
+            timestamp,
         });
         Ok(())
     }
 
-    pub fn init_calculate_rewards_comp_def(
-        ctx: Context<InitCalculateRewardsCompDef>,
+    pub fn init_process_payment_multi_source_comp_def(
+        ctx: Context<InitProcessPaymentMultiSourceCompDef>,
     ) -> Result<()> {
         init_comp_def(ctx.accounts, true, 0, None, None)?;
         Ok(())
     }
 
-    pub fn calculate_rewards(
-        ctx: Context<CalculateRewards>,
+    /// Pays a receiver from whichever of the owner's accounts can cover it.
+    ///
+    /// The source `UserAccount`s are passed as remaining accounts in order of
+    /// preference, at most `MAX_PAYMENT_SOURCES`. The first one whose base-currency
+    /// balance covers the amount plus the payment fee is debited, and the fee goes
+    /// to the treasury. All of them get fresh ciphertexts, and the chosen slot is
+    /// only returned encrypted to the sender. The spend limit, reserve, co-signer
+    /// and acceptance checks of `process_payment` are not run here, so accounts
+    /// using them are refused.
+    ///
+    /// # Arguments
+    /// * `amount_ciphertext` - Amount encrypted to the first source's key
+    /// * `amount_nonce` - Nonce used for `amount_ciphertext`
+    /// * `source_new_nonces` - Nonce for each source balance encrypted to its key
+    /// * `receiver_new_nonce` - Nonce for the receiver balance encrypted to their key
+    pub fn process_payment_multi_source(
+        ctx: Context<ProcessPaymentMultiSource>,
         computation_offset: u64,
-        _account_id: u64,
+        amount_ciphertext: [u8; 32],
+        amount_nonce: u128,
+        source_new_nonces: Vec<u128>,
+        receiver_new_nonce: u128,
     ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let receiver = &ctx.accounts.receiver_account;
         require!(
-            ctx.accounts.user_account.account_state == AccountState::Active,
+            receiver.account_state == AccountState::Active,
             ErrorCode::InvalidAccountState
         );
+        require!(!receiver.require_acceptance, ErrorCode::UnsupportedAccountSettings);
+        require!(
+            !ctx.remaining_accounts.is_empty()
+                && ctx.remaining_accounts.len() <= MAX_PAYMENT_SOURCES
+                && source_new_nonces.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidAccountCount
+        );
 
-        let args = vec![
-            Argument::PlaintextU64(ctx.accounts.user_account.transaction_count),
-            Argument::PlaintextU128(ctx.accounts.user_account.balance_nonce),
-            Argument::Account(ctx.accounts.user_account.key(), 8 + 8 + 32, 32),
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        // (account, balance nonce, encryption key) per slot
+        let mut sources = Vec::with_capacity(MAX_PAYMENT_SOURCES);
+        for info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+            require!(info.key() != receiver.key(), ErrorCode::SelfTransfer);
+            require!(
+                sources.iter().all(|(key, _, _)| *key != info.key()),
+                ErrorCode::DuplicateAccount
+            );
+            let mut data = info.try_borrow_mut_data()?;
+            let mut source = UserAccount::try_deserialize(&mut &data[..])?;
+            require!(
+                source.owner_pubkey == ctx.accounts.payer.key(),
+                ErrorCode::Unauthorized
+            );
+            require!(
+                source.account_state == AccountState::Active,
+                ErrorCode::InvalidAccountState
+            );
+            require!(
+                source.base_currency_id == receiver.base_currency_id,
+                ErrorCode::CurrencyNotSupported
+            );
+            require!(
+                !source.has_daily_limit && !source.has_min_reserve && source.co_signer.is_none(),
+                ErrorCode::UnsupportedAccountSettings
+            );
+            // Any source may be debited, so each is counted in flight; an error on
+            // a later one reverts the earlier counts with the rest of the instruction
+            source.begin_computation(max_in_flight)?;
+            source.try_serialize(&mut &mut data[..])?;
+            sources.push((info.key(), source.mxe_balance_nonce, source.owner_enc_pubkey));
+        }
+        let source_count = sources.len() as u8;
+
+        let mut args = Vec::with_capacity(MAX_PAYMENT_SOURCES * 4 + 10);
+        // Padding slots read the first source and are ignored by the circuit
+        for slot in 0..MAX_PAYMENT_SOURCES {
+            let (key, nonce, _) = sources.get(slot).copied().unwrap_or(sources[0]);
+            args.push(Argument::PlaintextU128(nonce));
+            args.push(Argument::Account(key, USER_ACCOUNT_MXE_BALANCE_OFFSET, 32));
+        }
+        args.extend([
+            Argument::PlaintextU8(source_count),
+            Argument::PlaintextU128(receiver.mxe_balance_nonce),
+            Argument::Account(receiver.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(sources[0].2),
+            Argument::PlaintextU128(amount_nonce),
+            Argument::EncryptedU64(amount_ciphertext),
+        ]);
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Payment));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+        for slot in 0..MAX_PAYMENT_SOURCES {
+            // Fresh per-computation nonce for the discarded padding outputs
+            let (enc_pubkey, nonce) = match sources.get(slot) {
+                Some((_, _, enc_pubkey)) => (*enc_pubkey, source_new_nonces[slot]),
+                None => (
+                    sources[0].2,
+                    (computation_offset as u128) | ((slot as u128) << 64),
+                ),
+            };
+            args.push(Argument::ArcisPubkey(enc_pubkey));
+            args.push(Argument::PlaintextU128(nonce));
+        }
+        args.extend([
+            Argument::ArcisPubkey(receiver.owner_enc_pubkey),
+            Argument::PlaintextU128(receiver_new_nonce),
+        ]);
+
+        // Named callback accounts first, then the sources as remaining accounts
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: receiver.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.treasury.key(),
+                is_writable: true,
+            },
         ];
+        callback_accounts.extend(sources.iter().map(|(key, _, _)| CallbackAccount {
+            pubkey: *key,
+            is_writable: true,
+        }));
 
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
         queue_computation(
@@ -297,57 +1319,10934 @@ pub mod ibank {
             computation_offset,
             args,
             None,
-            vec![CalculateRewardsCallback::callback_ix(&[CallbackAccount {
-                pubkey: ctx.accounts.user_account.key(),
-                is_writable: true,
-            }])],
+            vec![ProcessPaymentMultiSourceCallback::callback_ix(&callback_accounts)],
         )?;
         Ok(())
     }
 
-    #[arcium_callback(encrypted_ix = "calculate_rewards")]
-    pub fn calculate_rewards_callback(
-        ctx: Context<CalculateRewardsCallback>,
-        output: ComputationOutputs<CalculateRewardsOutput>,
+    #[arcium_callback(encrypted_ix = "process_payment_multi_source")]
+    pub fn process_payment_multi_source_callback(
+        ctx: Context<ProcessPaymentMultiSourceCallback>,
+        output: ComputationOutputs<ProcessPaymentMultiSourceOutput>,
     ) -> Result<()> {
-        let reward_points = match output {
-            ComputationOutputs::Success(CalculateRewardsOutput { field_0: points }) => points,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+        let (
+            new_source_mxe_balances,
+            new_source_balances,
+            new_receiver_mxe_balance,
+            new_receiver_balance,
+            source_index,
+            new_treasury,
+            approved,
+        ) = match output {
+            ComputationOutputs::Success(ProcessPaymentMultiSourceOutput {
+                field_0: ProcessPaymentMultiSourceOutputStruct0 {
+                    field_0: source_mxe_0,
+                    field_1: source_mxe_1,
+                    field_2: source_mxe_2,
+                    field_3: source_mxe_3,
+                    field_4: source_0,
+                    field_5: source_1,
+                    field_6: source_2,
+                    field_7: source_3,
+                    field_8: receiver_mxe,
+                    field_9: receiver,
+                    field_10: source_index,
+                    field_11: treasury,
+                    field_12: approved,
+                },
+            }) => (
+                [source_mxe_0, source_mxe_1, source_mxe_2, source_mxe_3],
+                [source_0, source_1, source_2, source_3],
+                receiver_mxe,
+                receiver,
+                source_index,
+                treasury,
+                approved,
+            ),
+            _ => {
+                ctx.accounts.receiver_account.end_computation();
+                end_remaining_computations(ctx.remaining_accounts)?;
+                ctx.accounts.treasury.end_computation();
+                return computation_aborted(
+                    "process_payment_multi_source",
+                    ctx.accounts.receiver_account.account_id,
+                )
+            }
         };
 
-        ctx.accounts.user_account.reward_points += reward_points;
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.treasury.end_computation();
 
-        emit!(RewardsCalculatedEvent {
-            account_id: ctx.accounts.user_account.account_id,
-            reward_points,
-            total_rewards: ctx.accounts.user_account.reward_points,
+        // Sources were checked when queued; only the real slots are passed back.
+        // Each one is released, and on success rewritten so the paying account
+        // does not stand out.
+        let balances = new_source_mxe_balances.into_iter().zip(new_source_balances);
+        for (info, (mxe_balance, balance)) in ctx.remaining_accounts.iter().zip(balances) {
+            require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+            let mut data = info.try_borrow_mut_data()?;
+            let mut source = UserAccount::try_deserialize(&mut &data[..])?;
+            source.end_computation();
+            if approved {
+                source.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+                source.mxe_balance_nonce = mxe_balance.nonce;
+                source.encrypted_balance = balance.ciphertexts[0];
+                source.balance_nonce = balance.nonce;
+            }
+            source.try_serialize(&mut &mut data[..])?;
+        }
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !approved {
+            emit!(MultiSourcePaymentFailedEvent {
+                receiver: ctx.accounts.receiver_account.key(),
+                reason: "Insufficient balance".to_string(),
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        ctx.accounts.treasury.store_total((new_treasury.ciphertexts[0], new_treasury.nonce));
+
+        let receiver_account = &mut ctx.accounts.receiver_account;
+        receiver_account.mxe_encrypted_balance = new_receiver_mxe_balance.ciphertexts[0];
+        receiver_account.mxe_balance_nonce = new_receiver_mxe_balance.nonce;
+        receiver_account.encrypted_balance = new_receiver_balance.ciphertexts[0];
+        receiver_account.balance_nonce = new_receiver_balance.nonce;
+        receiver_account.transaction_count = receiver_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(MultiSourcePaymentEvent {
+            receiver: receiver_account.key(),
+            source_count: ctx.remaining_accounts.len() as u8,
+            source_index: source_index.ciphertexts[0],
+            source_index_nonce: source_index.nonce,
+            timestamp,
         });
         Ok(())
     }
-}
 
-// ============================================================================
-// ACCOUNT CONTEXTS - Initialize Accounts
-// ============================================================================
+    pub fn init_process_payment_with_fee_comp_def(
+        ctx: Context<InitProcessPaymentWithFeeCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
 
-#[queue_computation_accounts("initialize_accounts", payer)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64, account_id: u64)]
-pub struct InitializeUserAccount<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = payer,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
-    #[account(
-        address = derive_mxe_pda!()
-    )]
+    /// Pays `amount` to the receiver and `fee` to a fee-collecting account in one
+    /// computation, all or nothing.
+    ///
+    /// The sender is debited `amount + fee`. If that does not fit the balance, or
+    /// either credit would overflow, no balance changes and
+    /// `FeePaymentFailedEvent` is emitted. All three accounts must share a base
+    /// currency, and a sender with a minimum reserve, a daily limit or a co-signer
+    /// is refused, as is a receiver or collector that requires acceptance. Signed
+    /// by the sender's owner.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount credited to the receiver
+    /// * `fee` - Amount credited to the fee collector
+    /// * `receiver_new_nonce` - Nonce for the receiver's balance encrypted to their key
+    /// * `collector_new_nonce` - Nonce for the collector's balance encrypted to their key
+    /// * `sender_new_nonce` - Nonce for the sender's balance encrypted to their key
+    pub fn process_payment_with_fee(
+        ctx: Context<ProcessPaymentWithFee>,
+        computation_offset: u64,
+        amount: u64,
+        fee: u64,
+        receiver_new_nonce: u128,
+        collector_new_nonce: u128,
+        sender_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let sender = &ctx.accounts.sender_account;
+        let receiver = &ctx.accounts.receiver_account;
+        let collector = &ctx.accounts.fee_collector;
+        require!(
+            sender.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            sender.key() != receiver.key() && sender.key() != collector.key(),
+            ErrorCode::SelfTransfer
+        );
+        require!(receiver.key() != collector.key(), ErrorCode::DuplicateAccount);
+        require!(
+            !sender.has_min_reserve && !sender.has_daily_limit && sender.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(
+            !receiver.require_acceptance && !collector.require_acceptance,
+            ErrorCode::UnsupportedAccountSettings
+        );
+        for account in [sender, receiver, collector] {
+            require!(
+                account.account_state == AccountState::Active,
+                ErrorCode::InvalidAccountState
+            );
+            require!(
+                account.base_currency_id == sender.base_currency_id,
+                ErrorCode::CurrencyNotSupported
+            );
+        }
+
+        let mut args = Vec::new();
+        for account in [sender, receiver, collector] {
+            args.push(Argument::PlaintextU128(account.mxe_balance_nonce));
+            args.push(Argument::Account(account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32));
+        }
+        args.extend([
+            Argument::PlaintextU64(amount),
+            Argument::PlaintextU64(fee),
+            Argument::ArcisPubkey(receiver.owner_enc_pubkey),
+            Argument::PlaintextU128(receiver_new_nonce),
+            Argument::ArcisPubkey(collector.owner_enc_pubkey),
+            Argument::PlaintextU128(collector_new_nonce),
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+        ]);
+        let callback_accounts = [sender, receiver, collector].map(|account| CallbackAccount {
+            pubkey: account.key(),
+            is_writable: true,
+        });
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.fee_collector.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessPaymentWithFeeCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_payment_with_fee")]
+    pub fn process_payment_with_fee_callback(
+        ctx: Context<ProcessPaymentWithFeeCallback>,
+        output: ComputationOutputs<ProcessPaymentWithFeeOutput>,
+    ) -> Result<()> {
+        let (sender, receiver, collector, is_sufficient) = match output {
+            ComputationOutputs::Success(ProcessPaymentWithFeeOutput {
+                field_0: ProcessPaymentWithFeeOutputStruct0 {
+                    field_0: sender_mxe,
+                    field_1: sender_client,
+                    field_2: receiver_mxe,
+                    field_3: receiver_client,
+                    field_4: collector_mxe,
+                    field_5: collector_client,
+                    field_6: sufficient,
+                },
+            }) => (
+                (sender_mxe, sender_client),
+                (receiver_mxe, receiver_client),
+                (collector_mxe, collector_client),
+                sufficient,
+            ),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                ctx.accounts.receiver_account.end_computation();
+                ctx.accounts.fee_collector.end_computation();
+                return computation_aborted(
+                    "process_payment_with_fee",
+                    ctx.accounts.sender_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.sender_account.end_computation();
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.fee_collector.end_computation();
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !is_sufficient {
+            emit!(FeePaymentFailedEvent {
+                sender: ctx.accounts.sender_account.key(),
+                reason: "Insufficient balance".to_string(),
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        let accounts = [
+            (&mut ctx.accounts.sender_account, sender),
+            (&mut ctx.accounts.receiver_account, receiver),
+            (&mut ctx.accounts.fee_collector, collector),
+        ];
+        for (account, (mxe_balance, client_balance)) in accounts {
+            account.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+            account.mxe_balance_nonce = mxe_balance.nonce;
+            account.encrypted_balance = client_balance.ciphertexts[0];
+            account.balance_nonce = client_balance.nonce;
+            account.transaction_count = account
+                .transaction_count
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        emit!(FeePaymentEvent {
+            sender: ctx.accounts.sender_account.key(),
+            receiver: ctx.accounts.receiver_account.key(),
+            fee_collector: ctx.accounts.fee_collector.key(),
+            timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_open_credit_line_comp_def(ctx: Context<InitOpenCreditLineCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Grants an account an overdraft allowance for `process_payment_signed`.
+    /// Admin only, and only once per account.
+    ///
+    /// # Arguments
+    /// * `admin_enc_pubkey` - Encryption pubkey `limit_ciphertext` was encrypted with
+    /// * `limit_ciphertext` - Credit limit encrypted to `admin_enc_pubkey`
+    /// * `limit_nonce` - Nonce used for `limit_ciphertext`
+    /// * `limit_mxe_nonce` - Nonce for the stored MXE-encrypted limit
+    /// * `drawn_mxe_nonce` - Nonce for the stored MXE-encrypted drawn amount (zero)
+    pub fn open_credit_line(
+        ctx: Context<OpenCreditLine>,
+        computation_offset: u64,
+        _account_id: u64,
+        admin_enc_pubkey: [u8; 32],
+        limit_ciphertext: [u8; 32],
+        limit_nonce: u128,
+        limit_mxe_nonce: u128,
+        drawn_mxe_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        // Reopening would reset the drawn amount and forgive the debt
+        require!(
+            !ctx.accounts.user_account.credit_line.is_open,
+            ErrorCode::CreditLineAlreadyOpen
+        );
+
+        let args = vec![
+            Argument::ArcisPubkey(admin_enc_pubkey),
+            Argument::PlaintextU128(limit_nonce),
+            Argument::EncryptedU64(limit_ciphertext),
+            Argument::PlaintextU128(limit_mxe_nonce),
+            Argument::PlaintextU128(drawn_mxe_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![OpenCreditLineCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "open_credit_line")]
+    pub fn open_credit_line_callback(
+        ctx: Context<OpenCreditLineCallback>,
+        output: ComputationOutputs<OpenCreditLineOutput>,
+    ) -> Result<()> {
+        let (limit, drawn) = match output {
+            ComputationOutputs::Success(OpenCreditLineOutput {
+                field_0: OpenCreditLineOutputStruct0 {
+                    field_0: limit,
+                    field_1: drawn,
+                },
+            }) => (limit, drawn),
+            _ => {
+                return computation_aborted("open_credit_line", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.credit_line = CreditLine {
+            encrypted_limit: limit.ciphertexts[0],
+            limit_nonce: limit.nonce,
+            encrypted_drawn: drawn.ciphertexts[0],
+            drawn_nonce: drawn.nonce,
+            is_open: true,
+        };
+
+        emit!(CreditLineOpenedEvent {
+            account_id: user_account.account_id,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_process_payment_signed_comp_def(
+        ctx: Context<InitProcessPaymentSignedCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Pays from an account with an open credit line, letting it go negative.
+    ///
+    /// Works like `process_payment` without the tip: the amount stays encrypted
+    /// and the payment fee applies, collected into the treasury. Whatever the
+    /// balance does not cover is drawn from the credit line; a payment that
+    /// would draw past the limit changes nothing and emits
+    /// `CreditLimitExceededEvent`. Both accounts must share a base currency; a
+    /// minimum reserve cannot apply to a balance that may go negative, and the
+    /// daily spend and co-signer threshold are not checked here, so a sender
+    /// with any of them is refused, as is a receiver that requires acceptance.
+    /// Signed by the sender's owner.
+    ///
+    /// # Arguments
+    /// * `amount_ciphertext` - Amount encrypted to the sender's key
+    /// * `amount_nonce` - Nonce used for `amount_ciphertext`
+    /// * `receiver_new_nonce` - Nonce for the receiver's balance encrypted to their key
+    /// * `sender_new_nonce` - Nonce for the sender's balance encrypted to their key
+    pub fn process_payment_signed(
+        ctx: Context<ProcessPaymentSigned>,
+        computation_offset: u64,
+        amount_ciphertext: [u8; 32],
+        amount_nonce: u128,
+        receiver_new_nonce: u128,
+        sender_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let sender = &ctx.accounts.sender_account;
+        let receiver = &ctx.accounts.receiver_account;
+        require!(
+            sender.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(sender.key() != receiver.key(), ErrorCode::SelfTransfer);
+        require!(
+            sender.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            receiver.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            receiver.base_currency_id == sender.base_currency_id,
+            ErrorCode::CurrencyNotSupported
+        );
+        require!(sender.credit_line.is_open, ErrorCode::NoCreditLine);
+        require!(
+            !sender.has_min_reserve && !sender.has_daily_limit && sender.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(!receiver.require_acceptance, ErrorCode::UnsupportedAccountSettings);
+
+        let mut args = vec![
+            Argument::PlaintextU128(sender.mxe_balance_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU128(sender.credit_line.drawn_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_CREDIT_DRAWN_OFFSET, 32),
+            Argument::PlaintextU128(sender.credit_line.limit_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_CREDIT_LIMIT_OFFSET, 32),
+            Argument::PlaintextU128(receiver.mxe_balance_nonce),
+            Argument::Account(receiver.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(amount_nonce),
+            Argument::EncryptedU64(amount_ciphertext),
+        ];
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Payment));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+        args.extend([
+            Argument::ArcisPubkey(receiver.owner_enc_pubkey),
+            Argument::PlaintextU128(receiver_new_nonce),
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+        ]);
+        let callback_accounts = [sender.key(), receiver.key(), ctx.accounts.treasury.key()]
+            .map(|pubkey| CallbackAccount {
+                pubkey,
+                is_writable: true,
+            });
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessPaymentSignedCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_payment_signed")]
+    pub fn process_payment_signed_callback(
+        ctx: Context<ProcessPaymentSignedCallback>,
+        output: ComputationOutputs<ProcessPaymentSignedOutput>,
+    ) -> Result<()> {
+        let (
+            sender_mxe,
+            sender_client,
+            drawn,
+            receiver_mxe,
+            receiver_client,
+            treasury,
+            within_limit,
+        ) = match output {
+            ComputationOutputs::Success(ProcessPaymentSignedOutput {
+                field_0: ProcessPaymentSignedOutputStruct0 {
+                    field_0: sender_mxe,
+                    field_1: sender_client,
+                    field_2: drawn,
+                    field_3: receiver_mxe,
+                    field_4: receiver_client,
+                    field_5: treasury,
+                    field_6: within_credit_limit,
+                },
+            }) => (
+                sender_mxe,
+                sender_client,
+                drawn,
+                receiver_mxe,
+                receiver_client,
+                treasury,
+                within_credit_limit,
+            ),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                ctx.accounts.receiver_account.end_computation();
+                ctx.accounts.treasury.end_computation();
+                return computation_aborted(
+                    "process_payment_signed",
+                    ctx.accounts.sender_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.sender_account.end_computation();
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.treasury.end_computation();
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !within_limit {
+            emit!(CreditLimitExceededEvent {
+                account_id: ctx.accounts.sender_account.account_id,
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        ctx.accounts.treasury.store_total((treasury.ciphertexts[0], treasury.nonce));
+
+        let sender = &mut ctx.accounts.sender_account;
+        sender.mxe_encrypted_balance = sender_mxe.ciphertexts[0];
+        sender.mxe_balance_nonce = sender_mxe.nonce;
+        sender.encrypted_balance = sender_client.ciphertexts[0];
+        sender.balance_nonce = sender_client.nonce;
+        sender.credit_line.encrypted_drawn = drawn.ciphertexts[0];
+        sender.credit_line.drawn_nonce = drawn.nonce;
+        sender.transaction_count = sender
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let receiver = &mut ctx.accounts.receiver_account;
+        receiver.mxe_encrypted_balance = receiver_mxe.ciphertexts[0];
+        receiver.mxe_balance_nonce = receiver_mxe.nonce;
+        receiver.encrypted_balance = receiver_client.ciphertexts[0];
+        receiver.balance_nonce = receiver_client.nonce;
+        receiver.transaction_count = receiver
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(SignedPaymentProcessedEvent {
+            sender: ctx.accounts.sender_account.key(),
+            receiver: ctx.accounts.receiver_account.key(),
+            sender_new_balance: sender_client.ciphertexts[0],
+            sender_new_balance_nonce: sender_client.nonce,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_reveal_available_credit_comp_def(
+        ctx: Context<InitRevealAvailableCreditCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Re-encrypts what an account can still spend through `process_payment_signed`
+    /// to the owner's key and emits it in `AvailableCreditRevealedEvent`.
+    ///
+    /// That is the credit limit plus the balance, less whatever is drawn. Owner
+    /// only; requires an open credit line and an active session. Nothing is stored.
+    ///
+    /// # Arguments
+    /// * `client_nonce` - Fresh nonce for the returned ciphertext
+    pub fn reveal_available_credit(
+        ctx: Context<RevealAvailableCredit>,
+        computation_offset: u64,
+        _account_id: u64,
+        client_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let user_account = &ctx.accounts.user_account;
+        require!(
+            user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(user_account.credit_line.is_open, ErrorCode::NoCreditLine);
+
+        let args = vec![
+            Argument::PlaintextU128(user_account.mxe_balance_nonce),
+            Argument::Account(user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU128(user_account.credit_line.drawn_nonce),
+            Argument::Account(user_account.key(), USER_ACCOUNT_CREDIT_DRAWN_OFFSET, 32),
+            Argument::PlaintextU128(user_account.credit_line.limit_nonce),
+            Argument::Account(user_account.key(), USER_ACCOUNT_CREDIT_LIMIT_OFFSET, 32),
+            Argument::ArcisPubkey(user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(client_nonce),
+        ];
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+        ctx.accounts.user_account.last_activity_ts = now;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealAvailableCreditCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_available_credit")]
+    pub fn reveal_available_credit_callback(
+        ctx: Context<RevealAvailableCreditCallback>,
+        output: ComputationOutputs<RevealAvailableCreditOutput>,
+    ) -> Result<()> {
+        let available = match output {
+            ComputationOutputs::Success(RevealAvailableCreditOutput { field_0: available }) => {
+                available
+            }
+            _ => {
+                return computation_aborted(
+                    "reveal_available_credit",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        emit!(AvailableCreditRevealedEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            encrypted_available: available.ciphertexts[0],
+            available_nonce: available.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_open_currency_balance_comp_def(
+        ctx: Context<InitOpenCurrencyBalanceCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Adds a zero balance in another currency to an account. Owner only.
+    ///
+    /// The slot is reserved here and filled by the callback, so payments and
+    /// balance checks can target `currency_id` once it lands.
+    ///
+    /// # Arguments
+    /// * `currency_id` - Currency to hold; must differ from those already held
+    /// * `mxe_nonce` - Nonce for the MXE-encrypted balance
+    /// * `client_nonce` - Nonce for the balance encrypted to the owner's key
+    pub fn add_currency(
+        ctx: Context<AddCurrency>,
+        computation_offset: u64,
+        _account_id: u64,
+        currency_id: u16,
+        mxe_nonce: u128,
+        client_nonce: u128,
+    ) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let count = user_account.currency_count as usize;
+        require!(
+            currency_id != user_account.base_currency_id
+                && user_account.currencies[..count]
+                    .iter()
+                    .all(|currency| currency.currency_id != currency_id),
+            ErrorCode::CurrencyAlreadyHeld
+        );
+        require!(count < MAX_CURRENCIES, ErrorCode::TooManyCurrencies);
+        user_account.currencies[count].currency_id = currency_id;
+        user_account.currency_count += 1;
+
+        let args = vec![
+            Argument::PlaintextU128(mxe_nonce),
+            Argument::ArcisPubkey(user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(client_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![OpenCurrencyBalanceCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "open_currency_balance")]
+    pub fn open_currency_balance_callback(
+        ctx: Context<OpenCurrencyBalanceCallback>,
+        output: ComputationOutputs<OpenCurrencyBalanceOutput>,
+    ) -> Result<()> {
+        let (mxe_balance, client_balance) = match output {
+            ComputationOutputs::Success(OpenCurrencyBalanceOutput {
+                field_0: OpenCurrencyBalanceOutputStruct0 {
+                    field_0: mxe_balance,
+                    field_1: client_balance,
+                },
+            }) => (mxe_balance, client_balance),
+            _ => {
+                return computation_aborted(
+                    "open_currency_balance",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        // Every reserved slot starts at zero, so concurrent additions may fill
+        // them in either order
+        let user_account = &mut ctx.accounts.user_account;
+        let count = user_account.currency_count as usize;
+        let currency = user_account.currencies[..count]
+            .iter_mut()
+            .find(|currency| !currency.initialized)
+            .ok_or(ErrorCode::InvalidAccountState)?;
+        currency.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+        currency.mxe_balance_nonce = mxe_balance.nonce;
+        currency.encrypted_balance = client_balance.ciphertexts[0];
+        currency.balance_nonce = client_balance.nonce;
+        currency.initialized = true;
+        let currency_id = currency.currency_id;
+
+        emit!(CurrencyAddedEvent {
+            account_id: user_account.account_id,
+            currency_id,
+        });
+        Ok(())
+    }
+
+    pub fn init_refund_payment_comp_def(ctx: Context<InitRefundPaymentCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Refunds a completed payment in full. Signed by the payment's receiver, or
+    /// by the admin for a payment `Held` by `flag_transaction`.
+    ///
+    /// The amount moves back to the sender without a fee, recorded as a new
+    /// transaction linked to the original through `refunded_transaction_id`. The
+    /// original is marked `Refunded` here so it cannot be refunded twice, and
+    /// returns to `Completed` (or `Held`) if the receiver's balance no longer
+    /// covers it. A held payment is refunded even if its receiver was frozen.
+    ///
+    /// # Arguments
+    /// * `refund_transaction_id` - Id of the refund's own transaction record
+    /// * `receiver_new_nonce` - Nonce for the receiver's balance encrypted to their key
+    /// * `sender_new_nonce` - Nonce for the sender's balance encrypted to their key
+    /// * `refund_amount_nonce` - Nonce for the refund record's amount
+    pub fn refund_transaction(
+        ctx: Context<RefundTransaction>,
+        computation_offset: u64,
+        _transaction_id: u64,
+        refund_transaction_id: u64,
+        receiver_new_nonce: u128,
+        sender_new_nonce: u128,
+        refund_amount_nonce: u128,
+    ) -> Result<()> {
+        let original = &mut ctx.accounts.original_transaction;
+        require!(
+            original.status != TransactionStatus::Refunded,
+            ErrorCode::TransactionAlreadyRefunded
+        );
+        let held = original.status == TransactionStatus::Held;
+        // Refund records are not themselves refundable
+        require!(
+            (original.status == TransactionStatus::Completed || held)
+                && original.refunded_transaction_id.is_none(),
+            ErrorCode::InvalidTransactionStatus
+        );
+        if held {
+            require!(
+                ctx.accounts.program_config.admin == ctx.accounts.payer.key(),
+                ErrorCode::Unauthorized
+            );
+        } else {
+            require!(
+                ctx.accounts.receiver_account.owner_pubkey == ctx.accounts.payer.key(),
+                ErrorCode::Unauthorized
+            );
+            require!(
+                ctx.accounts.receiver_account.account_state == AccountState::Active,
+                ErrorCode::InvalidAccountState
+            );
+        }
+        require!(
+            ctx.accounts.sender_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        original.status = TransactionStatus::Refunded;
+        let currency_id = original.currency_id;
+
+        let refund = &mut ctx.accounts.refund_transaction;
+        require!(
+            refund.sender == Pubkey::default(),
+            ErrorCode::DuplicateTransactionId
+        );
+        refund.bump = ctx.bumps.refund_transaction;
+        refund.transaction_id = refund_transaction_id;
+        refund.sender = ctx.accounts.receiver_account.key();
+        refund.receiver = ctx.accounts.sender_account.key();
+        refund.encrypted_amount = [0; 32];
+        refund.amount_nonce = 0;
+        refund.timestamp = Clock::get()?.unix_timestamp;
+        refund.status = TransactionStatus::Processing;
+        refund.aml_recorded = 0;
+        refund.currency_id = currency_id;
+        refund.refunded_transaction_id = Some(ctx.accounts.original_transaction.transaction_id);
+        refund.computation_offset = computation_offset;
+
+        ctx.accounts.receiver_tx_index.record(refund_transaction_id);
+        ctx.accounts.sender_tx_index.record(refund_transaction_id);
+
+        let receiver_account = &ctx.accounts.receiver_account;
+        let sender_account = &ctx.accounts.sender_account;
+        let mut args = Vec::new();
+        args.extend(receiver_account.mxe_balance_arguments(receiver_account.key(), currency_id)?);
+        args.extend(sender_account.mxe_balance_arguments(sender_account.key(), currency_id)?);
+        // The original amount, still encrypted to the sender's key
+        args.extend([
+            Argument::ArcisPubkey(sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(ctx.accounts.original_transaction.amount_nonce),
+            Argument::Account(ctx.accounts.original_transaction.key(), 8 + 8 + 32 + 32, 32),
+            Argument::ArcisPubkey(receiver_account.owner_enc_pubkey),
+            Argument::PlaintextU128(receiver_new_nonce),
+            Argument::ArcisPubkey(sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+            Argument::ArcisPubkey(receiver_account.owner_enc_pubkey),
+            Argument::PlaintextU128(refund_amount_nonce),
+        ]);
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RefundPaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.original_transaction.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.refund_transaction.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "refund_payment")]
+    pub fn refund_payment_callback(
+        ctx: Context<RefundPaymentCallback>,
+        output: ComputationOutputs<RefundPaymentOutput>,
+    ) -> Result<()> {
+        let (
+            receiver_mxe_balance,
+            receiver_balance,
+            sender_mxe_balance,
+            sender_balance,
+            refund_amount,
+            is_sufficient,
+        ) = match output {
+            ComputationOutputs::Success(RefundPaymentOutput {
+                field_0: RefundPaymentOutputStruct0 {
+                    field_0: receiver_mxe_bal,
+                    field_1: receiver_bal,
+                    field_2: sender_mxe_bal,
+                    field_3: sender_bal,
+                    field_4: amount,
+                    field_5: sufficient,
+                },
+            }) => (
+                receiver_mxe_bal,
+                receiver_bal,
+                sender_mxe_bal,
+                sender_bal,
+                amount,
+                sufficient,
+            ),
+            _ => {
+                ctx.accounts.receiver_account.end_computation();
+                ctx.accounts.sender_account.end_computation();
+                // The payment stays refundable, as when the receiver cannot cover it
+                ctx.accounts.original_transaction.status = if ctx.accounts.original_transaction.disputed {
+                    TransactionStatus::Held
+                } else {
+                    TransactionStatus::Completed
+                };
+                ctx.accounts.refund_transaction.status = TransactionStatus::Failed;
+                return computation_aborted(
+                    "refund_payment",
+                    ctx.accounts.receiver_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.sender_account.end_computation();
+
+        let transaction_id = ctx.accounts.original_transaction.transaction_id;
+        let refund_transaction_id = ctx.accounts.refund_transaction.transaction_id;
+        let disputed = ctx.accounts.original_transaction.disputed;
+        let computation_offset = ctx.accounts.refund_transaction.computation_offset;
+        if !is_sufficient {
+            // The payment stays refundable once the receiver can cover it
+            ctx.accounts.original_transaction.status = if disputed {
+                TransactionStatus::Held
+            } else {
+                TransactionStatus::Completed
+            };
+            ctx.accounts.refund_transaction.status = TransactionStatus::Failed;
+            emit!(RefundFailedEvent {
+                transaction_id,
+                refund_transaction_id,
+                computation_offset,
+                reason: "Insufficient balance".to_string(),
+            });
+            return Ok(());
+        }
+
+        let currency_id = ctx.accounts.refund_transaction.currency_id;
+        let receiver_account = &mut ctx.accounts.receiver_account;
+        receiver_account.store_balance(
+            currency_id,
+            (receiver_mxe_balance.ciphertexts[0], receiver_mxe_balance.nonce),
+            (receiver_balance.ciphertexts[0], receiver_balance.nonce),
+        )?;
+        receiver_account.transaction_count = receiver_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let sender_account = &mut ctx.accounts.sender_account;
+        sender_account.store_balance(
+            currency_id,
+            (sender_mxe_balance.ciphertexts[0], sender_mxe_balance.nonce),
+            (sender_balance.ciphertexts[0], sender_balance.nonce),
+        )?;
+        sender_account.transaction_count = sender_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let refund = &mut ctx.accounts.refund_transaction;
+        refund.encrypted_amount = refund_amount.ciphertexts[0];
+        refund.amount_nonce = refund_amount.nonce;
+        refund.status = TransactionStatus::Completed;
+
+        emit!(TransactionRefundedEvent {
+            transaction_id,
+            refund_transaction_id,
+            computation_offset,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        if disputed {
+            emit!(DisputeResolvedEvent {
+                transaction_id,
+                refunded: true,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    /// Puts a completed payment on hold while a chargeback or fraud report is
+    /// investigated. Admin only.
+    ///
+    /// A held payment cannot be refunded by its receiver. It leaves the hold
+    /// through `resolve_dispute`, or through `refund_transaction` signed by the
+    /// admin.
+    ///
+    /// # Arguments
+    /// * `freeze_receiver` - Also freeze the receiver's account if it is active
+    pub fn flag_transaction(
+        ctx: Context<FlagTransaction>,
+        _transaction_id: u64,
+        freeze_receiver: bool,
+    ) -> Result<()> {
+        let transaction = &mut ctx.accounts.transaction;
+        // Refund records are not themselves disputable
+        require!(
+            transaction.status == TransactionStatus::Completed
+                && transaction.refunded_transaction_id.is_none(),
+            ErrorCode::InvalidTransactionStatus
+        );
+        transaction.status = TransactionStatus::Held;
+        transaction.disputed = true;
+
+        let receiver_account = &mut ctx.accounts.receiver_account;
+        let receiver_frozen =
+            freeze_receiver && receiver_account.account_state == AccountState::Active;
+        if receiver_frozen {
+            receiver_account.set_state(AccountState::Frozen)?;
+        }
+
+        emit!(TransactionHeldEvent {
+            transaction_id: transaction.transaction_id,
+            receiver_frozen,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Releases a held payment back to `Completed`. Admin only.
+    ///
+    /// A receiver frozen by `flag_transaction` stays frozen until
+    /// `unfreeze_account`.
+    pub fn resolve_dispute(ctx: Context<ResolveDispute>, _transaction_id: u64) -> Result<()> {
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.status == TransactionStatus::Held,
+            ErrorCode::InvalidTransactionStatus
+        );
+        transaction.status = TransactionStatus::Completed;
+        transaction.disputed = false;
+
+        emit!(DisputeResolvedEvent {
+            transaction_id: transaction.transaction_id,
+            refunded: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_seize_funds_comp_def(ctx: Context<InitSeizeFundsCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Destroys a frozen account's base-currency balance on a regulator's order.
+    /// Signed by the configured `seizure_authority`.
+    ///
+    /// The balance becomes an encrypted zero. The seized amount is returned in
+    /// `FundsSeizedEvent`, encrypted to the authority's key for its records.
+    ///
+    /// # Arguments
+    /// * `authority_enc_pubkey` - Encryption key the seized amount is encrypted to
+    /// * `authority_nonce` - Nonce for the encrypted seized amount
+    /// * `owner_new_nonce` - Nonce for the zero balance encrypted to the owner
+    pub fn seize_funds(
+        ctx: Context<SeizeFunds>,
+        computation_offset: u64,
+        _account_id: u64,
+        authority_enc_pubkey: [u8; 32],
+        authority_nonce: u128,
+        owner_new_nonce: u128,
+    ) -> Result<()> {
+        let user_account = &ctx.accounts.user_account;
+        require!(
+            user_account.account_state == AccountState::Frozen,
+            ErrorCode::InvalidAccountState
+        );
+
+        let args = vec![
+            Argument::PlaintextU128(user_account.mxe_balance_nonce),
+            Argument::Account(user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(owner_new_nonce),
+            Argument::ArcisPubkey(authority_enc_pubkey),
+            Argument::PlaintextU128(authority_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SeizeFundsCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "seize_funds")]
+    pub fn seize_funds_callback(
+        ctx: Context<SeizeFundsCallback>,
+        output: ComputationOutputs<SeizeFundsOutput>,
+    ) -> Result<()> {
+        let (mxe_balance, balance, seized) = match output {
+            ComputationOutputs::Success(SeizeFundsOutput {
+                field_0: SeizeFundsOutputStruct0 {
+                    field_0: mxe_balance,
+                    field_1: balance,
+                    field_2: seized,
+                },
+            }) => (mxe_balance, balance, seized),
+            _ => {
+                ctx.accounts.user_account.end_computation();
+                return computation_aborted("seize_funds", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        ctx.accounts.user_account.end_computation();
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+        user_account.mxe_balance_nonce = mxe_balance.nonce;
+        user_account.encrypted_balance = balance.ciphertexts[0];
+        user_account.balance_nonce = balance.nonce;
+
+        emit!(FundsSeizedEvent {
+            account_id: user_account.account_id,
+            seized_amount: seized.ciphertexts[0],
+            seized_amount_nonce: seized.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_sweep_balance_comp_def(ctx: Context<InitSweepBalanceCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Moves an account's entire base-currency balance to another account.
+    ///
+    /// Signed by the source account's owner, who need not know the amount. Both
+    /// accounts must share a base currency, and a source with a minimum reserve, a
+    /// daily limit or a co-signer is refused, as is a destination that requires
+    /// acceptance. The source is left holding an encrypted zero; if the
+    /// destination's balance would overflow, neither changes and
+    /// `SweepFailedEvent` is emitted.
+    ///
+    /// # Arguments
+    /// * `source_new_nonce` - Nonce for the source balance encrypted to its owner
+    /// * `destination_new_nonce` - Nonce for the destination balance encrypted to its owner
+    pub fn sweep_balance(
+        ctx: Context<SweepBalance>,
+        computation_offset: u64,
+        source_new_nonce: u128,
+        destination_new_nonce: u128,
+    ) -> Result<()> {
+        let source = &ctx.accounts.source_account;
+        let destination = &ctx.accounts.destination_account;
+        require!(
+            source.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(source.key() != destination.key(), ErrorCode::SelfTransfer);
+        require!(
+            source.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            destination.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            destination.base_currency_id == source.base_currency_id,
+            ErrorCode::CurrencyNotSupported
+        );
+        // Sweeping empties the balance, which can never keep a reserve, and the
+        // daily spend and co-signer threshold are not checked here
+        require!(
+            !source.has_min_reserve && !source.has_daily_limit && source.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(!destination.require_acceptance, ErrorCode::UnsupportedAccountSettings);
+
+        let args = vec![
+            Argument::PlaintextU128(source.mxe_balance_nonce),
+            Argument::Account(source.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU128(destination.mxe_balance_nonce),
+            Argument::Account(destination.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(source.owner_enc_pubkey),
+            Argument::PlaintextU128(source_new_nonce),
+            Argument::ArcisPubkey(destination.owner_enc_pubkey),
+            Argument::PlaintextU128(destination_new_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.source_account.begin_computation(max_in_flight)?;
+        ctx.accounts.destination_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SweepBalanceCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.source_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.destination_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "sweep_balance")]
+    pub fn sweep_balance_callback(
+        ctx: Context<SweepBalanceCallback>,
+        output: ComputationOutputs<SweepBalanceOutput>,
+    ) -> Result<()> {
+        let (
+            source_mxe_balance,
+            source_balance,
+            destination_mxe_balance,
+            destination_balance,
+            fits,
+        ) = match output {
+            ComputationOutputs::Success(SweepBalanceOutput {
+                field_0: SweepBalanceOutputStruct0 {
+                    field_0: source_mxe_bal,
+                    field_1: source_bal,
+                    field_2: destination_mxe_bal,
+                    field_3: destination_bal,
+                    field_4: fits,
+                },
+            }) => (source_mxe_bal, source_bal, destination_mxe_bal, destination_bal, fits),
+            _ => {
+                ctx.accounts.source_account.end_computation();
+                ctx.accounts.destination_account.end_computation();
+                return computation_aborted("sweep_balance", ctx.accounts.source_account.account_id)
+            }
+        };
+
+        ctx.accounts.source_account.end_computation();
+        ctx.accounts.destination_account.end_computation();
+
+        let source_account_id = ctx.accounts.source_account.account_id;
+        let destination_account_id = ctx.accounts.destination_account.account_id;
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !fits {
+            emit!(SweepFailedEvent {
+                source_account_id,
+                destination_account_id,
+                reason: "Destination balance would overflow".to_string(),
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        let source = &mut ctx.accounts.source_account;
+        source.mxe_encrypted_balance = source_mxe_balance.ciphertexts[0];
+        source.mxe_balance_nonce = source_mxe_balance.nonce;
+        source.encrypted_balance = source_balance.ciphertexts[0];
+        source.balance_nonce = source_balance.nonce;
+
+        let destination = &mut ctx.accounts.destination_account;
+        destination.mxe_encrypted_balance = destination_mxe_balance.ciphertexts[0];
+        destination.mxe_balance_nonce = destination_mxe_balance.nonce;
+        destination.encrypted_balance = destination_balance.ciphertexts[0];
+        destination.balance_nonce = destination_balance.nonce;
+
+        emit!(BalanceSweptEvent {
+            source_account_id,
+            destination_account_id,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_close_account_comp_def(ctx: Context<InitCloseAccountCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Closes an account, moving its entire base-currency balance to another
+    /// account first. Signed by the closing account's owner.
+    ///
+    /// Takes the same checks as `sweep_balance`, and the account must also hold
+    /// no other currency and have no credit line, since neither would be moved
+    /// or settled. Once the callback has emptied it the account is `Closed`,
+    /// which nothing leaves; if the destination's balance would overflow,
+    /// nothing changes and `SweepFailedEvent` is emitted.
+    ///
+    /// # Arguments
+    /// * `source_new_nonce` - Nonce for the source balance encrypted to its owner
+    /// * `destination_new_nonce` - Nonce for the destination balance encrypted to its owner
+    pub fn close_account(
+        ctx: Context<CloseAccount>,
+        computation_offset: u64,
+        source_new_nonce: u128,
+        destination_new_nonce: u128,
+    ) -> Result<()> {
+        let source = &ctx.accounts.source_account;
+        let destination = &ctx.accounts.destination_account;
+        require!(
+            source.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(source.key() != destination.key(), ErrorCode::SelfTransfer);
+        require!(
+            source.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            destination.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            destination.base_currency_id == source.base_currency_id,
+            ErrorCode::CurrencyNotSupported
+        );
+        require!(
+            !source.has_min_reserve && !source.has_daily_limit && source.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(
+            source.currency_count == 0 && !source.credit_line.is_open,
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(!destination.require_acceptance, ErrorCode::UnsupportedAccountSettings);
+
+        let args = vec![
+            Argument::PlaintextU128(source.mxe_balance_nonce),
+            Argument::Account(source.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU128(destination.mxe_balance_nonce),
+            Argument::Account(destination.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(source.owner_enc_pubkey),
+            Argument::PlaintextU128(source_new_nonce),
+            Argument::ArcisPubkey(destination.owner_enc_pubkey),
+            Argument::PlaintextU128(destination_new_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.source_account.begin_computation(max_in_flight)?;
+        ctx.accounts.destination_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CloseAccountCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.source_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.destination_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "close_account")]
+    pub fn close_account_callback(
+        ctx: Context<CloseAccountCallback>,
+        output: ComputationOutputs<CloseAccountOutput>,
+    ) -> Result<()> {
+        let (
+            source_mxe_balance,
+            source_balance,
+            destination_mxe_balance,
+            destination_balance,
+            fits,
+        ) = match output {
+            ComputationOutputs::Success(CloseAccountOutput {
+                field_0: CloseAccountOutputStruct0 {
+                    field_0: source_mxe_bal,
+                    field_1: source_bal,
+                    field_2: destination_mxe_bal,
+                    field_3: destination_bal,
+                    field_4: fits,
+                },
+            }) => (source_mxe_bal, source_bal, destination_mxe_bal, destination_bal, fits),
+            _ => {
+                ctx.accounts.source_account.end_computation();
+                ctx.accounts.destination_account.end_computation();
+                return computation_aborted("close_account", ctx.accounts.source_account.account_id)
+            }
+        };
+
+        ctx.accounts.source_account.end_computation();
+        ctx.accounts.destination_account.end_computation();
+
+        let source_account_id = ctx.accounts.source_account.account_id;
+        let destination_account_id = ctx.accounts.destination_account.account_id;
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !fits {
+            emit!(SweepFailedEvent {
+                source_account_id,
+                destination_account_id,
+                reason: "Destination balance would overflow".to_string(),
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        let source = &mut ctx.accounts.source_account;
+        source.mxe_encrypted_balance = source_mxe_balance.ciphertexts[0];
+        source.mxe_balance_nonce = source_mxe_balance.nonce;
+        source.encrypted_balance = source_balance.ciphertexts[0];
+        source.balance_nonce = source_balance.nonce;
+        source.set_state(AccountState::Closed)?;
+
+        let destination = &mut ctx.accounts.destination_account;
+        destination.mxe_encrypted_balance = destination_mxe_balance.ciphertexts[0];
+        destination.mxe_balance_nonce = destination_mxe_balance.nonce;
+        destination.encrypted_balance = destination_balance.ciphertexts[0];
+        destination.balance_nonce = destination_balance.nonce;
+
+        emit!(AccountClosedEvent {
+            account_id: source_account_id,
+            destination_account_id,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_schedule_payment_comp_def(
+        ctx: Context<InitSchedulePaymentCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_release_scheduled_payment_comp_def(
+        ctx: Context<InitReleaseScheduledPaymentCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Schedules a payment that the receiver gets once `release_ts` has passed.
+    ///
+    /// The amount is escrowed straight away: the callback debits it from the
+    /// sender's base-currency balance into the `ScheduledPayment` account, as long
+    /// as the balance keeps the sender's minimum reserve. Anyone
+    /// may then execute the payment from `release_ts`, and the sender may cancel
+    /// it before then. The daily spend and co-signer threshold are not checked
+    /// here, so a sender with either is refused, and the payment is credited
+    /// without escrow, so a receiver that requires acceptance is refused too.
+    /// Signed by the sender's owner.
+    ///
+    /// # Arguments
+    /// * `schedule_id` - Id of the new `ScheduledPayment`
+    /// * `amount_ciphertext` - Amount encrypted to the sender's key
+    /// * `amount_nonce` - Nonce used for `amount_ciphertext`
+    /// * `escrow_nonce` - Nonce for the MXE-encrypted escrowed amount
+    /// * `sender_new_nonce` - Nonce for the sender balance encrypted to their key
+    /// * `release_ts` - Unix timestamp from which the payment can execute
+    pub fn schedule_payment(
+        ctx: Context<SchedulePayment>,
+        computation_offset: u64,
+        schedule_id: u64,
+        amount_ciphertext: [u8; 32],
+        amount_nonce: u128,
+        escrow_nonce: u128,
+        sender_new_nonce: u128,
+        release_ts: i64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let sender = &ctx.accounts.sender_account;
+        let receiver = &ctx.accounts.receiver_account;
+        require!(
+            sender.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(sender.key() != receiver.key(), ErrorCode::SelfTransfer);
+        require!(
+            sender.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            receiver.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            receiver.base_currency_id == sender.base_currency_id,
+            ErrorCode::CurrencyNotSupported
+        );
+        require!(
+            !sender.has_daily_limit && sender.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(!receiver.require_acceptance, ErrorCode::UnsupportedAccountSettings);
+        let now = Clock::get()?.unix_timestamp;
+        require!(release_ts > now, ErrorCode::InvalidReleaseTime);
+
+        let scheduled = &mut ctx.accounts.scheduled_payment;
+        scheduled.schedule_id = schedule_id;
+        scheduled.sender = sender.key();
+        scheduled.receiver = receiver.key();
+        scheduled.encrypted_amount = [0; 32];
+        scheduled.amount_nonce = 0;
+        scheduled.release_ts = release_ts;
+        scheduled.status = ScheduledPaymentStatus::Funding;
+        scheduled.bump = ctx.bumps.scheduled_payment;
+
+        let mut args = vec![
+            Argument::PlaintextU128(sender.mxe_balance_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(amount_nonce),
+            Argument::EncryptedU64(amount_ciphertext),
+        ];
+        args.extend(sender.min_reserve_arguments(sender.key(), sender.base_currency_id));
+        args.extend([
+            Argument::PlaintextU128(escrow_nonce),
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+        ]);
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SchedulePaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.scheduled_payment.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "schedule_payment")]
+    pub fn schedule_payment_callback(
+        ctx: Context<SchedulePaymentCallback>,
+        output: ComputationOutputs<SchedulePaymentOutput>,
+    ) -> Result<()> {
+        let (sender_mxe_balance, sender_balance, escrowed, is_sufficient) = match output {
+            ComputationOutputs::Success(SchedulePaymentOutput {
+                field_0: SchedulePaymentOutputStruct0 {
+                    field_0: sender_mxe_bal,
+                    field_1: sender_bal,
+                    field_2: escrowed,
+                    field_3: sufficient,
+                },
+            }) => (sender_mxe_bal, sender_bal, escrowed, sufficient),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                ctx.accounts.scheduled_payment.status = ScheduledPaymentStatus::Failed;
+                return computation_aborted(
+                    "schedule_payment",
+                    ctx.accounts.sender_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.sender_account.end_computation();
+
+        let scheduled = &mut ctx.accounts.scheduled_payment;
+        if !is_sufficient {
+            scheduled.status = ScheduledPaymentStatus::Failed;
+            emit!(ScheduledPaymentFailedEvent {
+                schedule_id: scheduled.schedule_id,
+                reason: "Insufficient balance".to_string(),
+            });
+            return Ok(());
+        }
+        scheduled.encrypted_amount = escrowed.ciphertexts[0];
+        scheduled.amount_nonce = escrowed.nonce;
+        scheduled.status = ScheduledPaymentStatus::Scheduled;
+
+        let sender = &mut ctx.accounts.sender_account;
+        sender.mxe_encrypted_balance = sender_mxe_balance.ciphertexts[0];
+        sender.mxe_balance_nonce = sender_mxe_balance.nonce;
+        sender.encrypted_balance = sender_balance.ciphertexts[0];
+        sender.balance_nonce = sender_balance.nonce;
+
+        emit!(PaymentScheduledEvent {
+            schedule_id: scheduled.schedule_id,
+            sender: scheduled.sender,
+            receiver: scheduled.receiver,
+            release_ts: scheduled.release_ts,
+        });
+        Ok(())
+    }
+
+    /// Credits a scheduled payment to its receiver. Anyone may call this once
+    /// `release_ts` has passed, unless the receiver has since started requiring
+    /// acceptance, in which case only the sender can cancel it.
+    ///
+    /// # Arguments
+    /// * `receiver_new_nonce` - Nonce for the receiver balance encrypted to their key
+    pub fn execute_scheduled_payment(
+        ctx: Context<ExecuteScheduledPayment>,
+        computation_offset: u64,
+        _schedule_id: u64,
+        receiver_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        let scheduled = &mut ctx.accounts.scheduled_payment;
+        require!(
+            scheduled.status == ScheduledPaymentStatus::Scheduled,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= scheduled.release_ts,
+            ErrorCode::ScheduledPaymentLocked
+        );
+        // The amount left the sender when it was scheduled, but freezing the
+        // sender still suspends the payment until it is unfrozen
+        require!(
+            ctx.accounts.sender_account.account_state != AccountState::Frozen,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.receiver_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            !ctx.accounts.receiver_account.require_acceptance,
+            ErrorCode::UnsupportedAccountSettings
+        );
+        scheduled.status = ScheduledPaymentStatus::Executing;
+
+        let args = release_scheduled_payment_arguments(
+            scheduled,
+            &ctx.accounts.receiver_account,
+            receiver_new_nonce,
+        );
+
+        // Both parties are counted, like every other two-party computation
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ReleaseScheduledPaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.scheduled_payment.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Returns a scheduled payment's escrowed amount to its sender. Signed by the
+    /// sender's owner before `release_ts`, or at any time once the receiver
+    /// requires acceptance and the payment can no longer execute.
+    ///
+    /// # Arguments
+    /// * `sender_new_nonce` - Nonce for the sender balance encrypted to their key
+    pub fn cancel_scheduled_payment(
+        ctx: Context<CancelScheduledPayment>,
+        computation_offset: u64,
+        _schedule_id: u64,
+        sender_new_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.sender_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        let scheduled = &mut ctx.accounts.scheduled_payment;
+        require!(
+            scheduled.status == ScheduledPaymentStatus::Scheduled,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp < scheduled.release_ts
+                || ctx.accounts.receiver_account.require_acceptance,
+            ErrorCode::ScheduledPaymentReleased
+        );
+        scheduled.status = ScheduledPaymentStatus::Cancelling;
+
+        let args = release_scheduled_payment_arguments(
+            scheduled,
+            &ctx.accounts.sender_account,
+            sender_new_nonce,
+        );
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ReleaseScheduledPaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.scheduled_payment.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "release_scheduled_payment")]
+    pub fn release_scheduled_payment_callback(
+        ctx: Context<ReleaseScheduledPaymentCallback>,
+        output: ComputationOutputs<ReleaseScheduledPaymentOutput>,
+    ) -> Result<()> {
+        let (mxe_balance, client_balance, fits) = match output {
+            ComputationOutputs::Success(ReleaseScheduledPaymentOutput {
+                field_0: ReleaseScheduledPaymentOutputStruct0 {
+                    field_0: mxe_bal,
+                    field_1: client_bal,
+                    field_2: fits,
+                },
+            }) => (mxe_bal, client_bal, fits),
+            _ => {
+                ctx.accounts.beneficiary_account.end_computation();
+                ctx.accounts.counterparty_account.end_computation();
+                // Still escrowed, so the release can be retried
+                ctx.accounts.scheduled_payment.status = ScheduledPaymentStatus::Scheduled;
+                return computation_aborted(
+                    "release_scheduled_payment",
+                    ctx.accounts.beneficiary_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.beneficiary_account.end_computation();
+        ctx.accounts.counterparty_account.end_computation();
+
+        let scheduled = &mut ctx.accounts.scheduled_payment;
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !fits {
+            // Still escrowed, so the release can be retried
+            scheduled.status = ScheduledPaymentStatus::Scheduled;
+            emit!(ScheduledPaymentFailedEvent {
+                schedule_id: scheduled.schedule_id,
+                reason: "Balance would overflow".to_string(),
+            });
+            return Ok(());
+        }
+
+        let beneficiary = &mut ctx.accounts.beneficiary_account;
+        beneficiary.mxe_encrypted_balance = mxe_balance.ciphertexts[0];
+        beneficiary.mxe_balance_nonce = mxe_balance.nonce;
+        beneficiary.encrypted_balance = client_balance.ciphertexts[0];
+        beneficiary.balance_nonce = client_balance.nonce;
+
+        if scheduled.status == ScheduledPaymentStatus::Cancelling {
+            scheduled.status = ScheduledPaymentStatus::Cancelled;
+            emit!(ScheduledPaymentCancelledEvent {
+                schedule_id: scheduled.schedule_id,
+                timestamp,
+            });
+        } else {
+            scheduled.status = ScheduledPaymentStatus::Executed;
+            emit!(ScheduledPaymentExecutedEvent {
+                schedule_id: scheduled.schedule_id,
+                timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn init_withdraw_comp_def(ctx: Context<InitWithdrawCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Withdraws a plaintext amount from the caller's encrypted balance.
+    ///
+    /// The MPC checks the balance covers `amount` plus the withdrawal fee, which
+    /// goes to the treasury, and still keeps the minimum reserve. If it does not,
+    /// the stored ciphertext is left untouched and `WithdrawalFailedEvent` is
+    /// emitted. Requires an active session. The daily spend and co-signer
+    /// threshold are not checked here, so an account with either is refused.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount to withdraw
+    /// * `balance_new_nonce` - Nonce for the new balance encrypted to the owner
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        computation_offset: u64,
+        _account_id: u64,
+        amount: u64,
+        balance_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            !ctx.accounts.user_account.has_daily_limit
+                && ctx.accounts.user_account.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+        ctx.accounts.user_account.last_activity_ts = now;
+        ctx.accounts.user_account.use_nonce(balance_new_nonce)?;
+
+        let mut args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(amount),
+        ];
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Withdrawal));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+        let user_account = &ctx.accounts.user_account;
+        args.extend(
+            user_account.min_reserve_arguments(user_account.key(), user_account.base_currency_id),
+        );
+        args.extend([
+            Argument::ArcisPubkey(user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(balance_new_nonce),
+        ]);
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![WithdrawCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.treasury.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "withdraw")]
+    pub fn withdraw_callback(
+        ctx: Context<WithdrawCallback>,
+        output: ComputationOutputs<WithdrawOutput>,
+    ) -> Result<()> {
+        let (new_balance, new_client_balance, new_treasury, is_sufficient) = match output {
+            ComputationOutputs::Success(WithdrawOutput {
+                field_0: WithdrawOutputStruct0 {
+                    field_0: balance,
+                    field_1: client_balance,
+                    field_2: treasury,
+                    field_3: sufficient,
+                },
+            }) => (balance, client_balance, treasury, sufficient),
+            _ => {
+                ctx.accounts.user_account.end_computation();
+                ctx.accounts.treasury.end_computation();
+                return computation_aborted("withdraw", ctx.accounts.user_account.account_id);
+            }
+        };
+
+        ctx.accounts.user_account.end_computation();
+        ctx.accounts.treasury.end_computation();
+
+        let user_account = &mut ctx.accounts.user_account;
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        if !is_sufficient {
+            emit!(WithdrawalFailedEvent {
+                account_id: user_account.account_id,
+                reason: "Insufficient balance".to_string(),
+                timestamp,
+            });
+            return Ok(());
+        }
+
+        user_account.mxe_encrypted_balance = new_balance.ciphertexts[0];
+        user_account.mxe_balance_nonce = new_balance.nonce;
+        user_account.encrypted_balance = new_client_balance.ciphertexts[0];
+        user_account.balance_nonce = new_client_balance.nonce;
+        ctx.accounts.treasury.store_total((new_treasury.ciphertexts[0], new_treasury.nonce));
+
+        emit!(WithdrawalEvent {
+            account_id: user_account.account_id,
+            new_balance_nonce: new_balance.nonce,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_deposit_comp_def(ctx: Context<InitDepositCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Funds the caller's encrypted balance from SPL tokens.
+    ///
+    /// `amount` tokens of the configured `deposit_mint` move from the owner's
+    /// token account into the program's vault for that mint, then the `deposit`
+    /// circuit adds the same amount to the base-currency balance. The transfer is
+    /// recorded in a `PendingDeposit` that the callback closes once the balance
+    /// is credited. If the computation aborts or the credit would overflow the
+    /// balance, the callback emits `ComputationAbortedEvent` and leaves the record
+    /// for `reclaim_deposit`, which also refunds a deposit whose computation
+    /// never called back.
+    ///
+    /// # Arguments
+    /// * `amount` - Token amount to deposit, in the mint's base units
+    /// * `balance_new_nonce` - Nonce for the new balance encrypted to the owner
+    pub fn deposit_from_token(
+        ctx: Context<DepositFromToken>,
+        computation_offset: u64,
+        _account_id: u64,
+        amount: u64,
+        balance_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.user_account.last_activity_ts = now;
+
+        token::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.token_vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let pending = &mut ctx.accounts.pending_deposit;
+        pending.user_account = ctx.accounts.user_account.key();
+        pending.owner = ctx.accounts.payer.key();
+        pending.mint = ctx.accounts.mint.key();
+        pending.amount = amount;
+        pending.queued_at = now;
+        pending.failed = false;
+        pending.bump = ctx.bumps.pending_deposit;
+
+        ctx.accounts.user_account.use_nonce(balance_new_nonce)?;
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(amount),
+            Argument::ArcisPubkey(ctx.accounts.user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(balance_new_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![DepositCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.pending_deposit.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.payer.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the tokens of a `deposit_from_token` whose credit never landed.
+    ///
+    /// The deposit must have overflowed the balance, or its computation must have
+    /// gone `IN_FLIGHT_TIMEOUT` without a successful callback. The tokens move
+    /// back from the vault to the owner's token account and the `PendingDeposit`
+    /// is closed, so a callback arriving later fails instead of crediting them
+    /// too. Signed by the owner who made the deposit.
+    pub fn reclaim_deposit(ctx: Context<ReclaimDeposit>, _computation_offset: u64) -> Result<()> {
+        let pending = &ctx.accounts.pending_deposit;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            pending.failed || now.saturating_sub(pending.queued_at) >= IN_FLIGHT_TIMEOUT,
+            ErrorCode::DepositInFlight
+        );
+
+        let mint_key = ctx.accounts.mint.key();
+        let vault_seeds: &[&[&[u8]]] =
+            &[&[TOKEN_VAULT_SEED, mint_key.as_ref(), &[ctx.bumps.token_vault]]];
+        token::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.token_vault.to_account_info(),
+                },
+                vault_seeds,
+            ),
+            pending.amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        emit!(DepositReclaimedEvent {
+            user_account: pending.user_account,
+            owner: pending.owner,
+            amount: pending.amount,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Credits a plaintext amount to an encrypted balance. Admin only.
+    ///
+    /// Nothing backs the credited amount, so this is reserved for operator
+    /// adjustments; users fund their accounts through `deposit_from_token`. The
+    /// addition happens inside the MPC; a deposit that would overflow the balance
+    /// leaves it untouched and emits `ComputationAbortedEvent` from the callback.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount to deposit
+    /// * `balance_new_nonce` - Nonce for the new balance encrypted to the owner
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        computation_offset: u64,
+        _account_id: u64,
+        amount: u64,
+        balance_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        ctx.accounts.user_account.use_nonce(balance_new_nonce)?;
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(amount),
+            Argument::ArcisPubkey(ctx.accounts.user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(balance_new_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![DepositCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "deposit")]
+    pub fn deposit_callback(
+        ctx: Context<DepositCallback>,
+        output: ComputationOutputs<DepositOutput>,
+    ) -> Result<()> {
+        let result = match output {
+            ComputationOutputs::Success(DepositOutput {
+                field_0: DepositOutputStruct0 {
+                    field_0: balance,
+                    field_1: client_balance,
+                    field_2: fits,
+                },
+            }) => Some((balance, client_balance, fits)),
+            _ => None,
+        };
+
+        ctx.accounts.user_account.end_computation();
+
+        // `deposit_from_token` passes its `PendingDeposit` and the owner it
+        // refunds; the admin's `deposit` moves no tokens and passes neither
+        let user_account_key = ctx.accounts.user_account.key();
+        let pending = match ctx.remaining_accounts {
+            [pending_info, owner_info] => {
+                let pending = load_pending_deposit(pending_info, user_account_key)?;
+                require_keys_eq!(owner_info.key(), pending.owner, ErrorCode::Unauthorized);
+                Some((pending, pending_info, owner_info))
+            }
+            _ => None,
+        };
+
+        let (new_balance, new_client_balance) = match result {
+            Some((balance, client_balance, true)) => (balance, client_balance),
+            // Aborted, or the credit would overflow the balance: the tokens stay
+            // in the vault until the owner reclaims them
+            _ => {
+                if let Some((mut pending, pending_info, _)) = pending {
+                    pending.failed = true;
+                    let mut data = pending_info.try_borrow_mut_data()?;
+                    pending.try_serialize(&mut &mut data[..])?;
+                }
+                return computation_aborted("deposit", ctx.accounts.user_account.account_id);
+            }
+        };
+
+        if let Some((_, pending_info, owner_info)) = pending {
+            close_program_account(pending_info, owner_info)?;
+        }
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.mxe_encrypted_balance = new_balance.ciphertexts[0];
+        user_account.mxe_balance_nonce = new_balance.nonce;
+        user_account.encrypted_balance = new_client_balance.ciphertexts[0];
+        user_account.balance_nonce = new_client_balance.nonce;
+
+        emit!(DepositEvent {
+            account_id: user_account.account_id,
+            new_balance_nonce: new_balance.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_check_balance_comp_def(
+        ctx: Context<InitCheckBalanceCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn check_balance(
+        ctx: Context<CheckBalance>,
+        computation_offset: u64,
+        _account_id: u64,
+        threshold: u64,
+        currency_id: u16,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state != AccountState::Frozen,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+
+        let user_account = &ctx.accounts.user_account;
+        let mut args = user_account
+            .mxe_balance_arguments(user_account.key(), currency_id)?
+            .to_vec();
+        args.push(Argument::PlaintextU64(threshold));
+        args.push(Argument::PlaintextU64(user_account.account_id));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CheckBalanceCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_balance")]
+    pub fn check_balance_callback(
+        ctx: Context<CheckBalanceCallback>,
+        output: ComputationOutputs<CheckBalanceOutput>,
+    ) -> Result<()> {
+        let is_above_threshold = match output {
+            ComputationOutputs::Success(CheckBalanceOutput {
+                field_0: CheckBalanceOutputStruct0 {
+                    field_0: result,
+                    field_1: account_id,
+                },
+            }) => {
+                require!(
+                    account_id == ctx.accounts.user_account.account_id,
+                    ErrorCode::CallbackAccountMismatch
+                );
+                result
+            }
+            _ => return computation_aborted("check_balance", ctx.accounts.user_account.account_id),
+        };
+
+        emit!(BalanceCheckEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            is_above_threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_reveal_balance_to_owner_comp_def(
+        ctx: Context<InitRevealBalanceToOwnerCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Re-encrypts the MXE-held balance to the owner's key under a fresh nonce
+    /// and emits it in `BalanceRevealedEvent`. Owner only.
+    ///
+    /// Unlike the stored `encrypted_balance`, which only some operations refresh,
+    /// this always reflects the balance the circuits work with. Nothing is stored.
+    /// Requires an active session.
+    ///
+    /// # Arguments
+    /// * `client_nonce` - Fresh nonce for the returned ciphertext
+    pub fn reveal_balance_to_owner(
+        ctx: Context<RevealBalanceToOwner>,
+        computation_offset: u64,
+        _account_id: u64,
+        client_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+        ctx.accounts.user_account.last_activity_ts = now;
+
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(
+                ctx.accounts.user_account.key(),
+                USER_ACCOUNT_MXE_BALANCE_OFFSET,
+                32,
+            ),
+            Argument::ArcisPubkey(ctx.accounts.user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(client_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealBalanceToOwnerCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_balance_to_owner")]
+    pub fn reveal_balance_to_owner_callback(
+        ctx: Context<RevealBalanceToOwnerCallback>,
+        output: ComputationOutputs<RevealBalanceToOwnerOutput>,
+    ) -> Result<()> {
+        let balance = match output {
+            ComputationOutputs::Success(RevealBalanceToOwnerOutput { field_0: balance }) => {
+                balance
+            }
+            _ => {
+                return computation_aborted(
+                    "reveal_balance_to_owner",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        emit!(BalanceRevealedEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            encrypted_balance: balance.ciphertexts[0],
+            balance_nonce: balance.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_check_balance_range_comp_def(
+        ctx: Context<InitCheckBalanceRangeCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Places an account's balance in a compliance bracket. Admin only.
+    ///
+    /// Only the bracket is revealed, through `BalanceRangeEvent`: 0 below `low`,
+    /// 1 between `low` and `high` (inclusive), 2 above `high`.
+    ///
+    /// # Arguments
+    /// * `low` - Lower bracket bound
+    /// * `high` - Upper bracket bound, at least `low`
+    pub fn check_balance_range(
+        ctx: Context<CheckBalanceRange>,
+        computation_offset: u64,
+        _account_id: u64,
+        low: u64,
+        high: u64,
+    ) -> Result<()> {
+        require!(low <= high, ErrorCode::InvalidBalanceRange);
+        require!(
+            ctx.accounts.user_account.account_state != AccountState::Frozen,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(low),
+            Argument::PlaintextU64(high),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CheckBalanceRangeCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: false,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_balance_range")]
+    pub fn check_balance_range_callback(
+        ctx: Context<CheckBalanceRangeCallback>,
+        output: ComputationOutputs<CheckBalanceRangeOutput>,
+    ) -> Result<()> {
+        let bracket = match output {
+            ComputationOutputs::Success(CheckBalanceRangeOutput { field_0: bracket }) => bracket,
+            _ => {
+                return computation_aborted(
+                    "check_balance_range",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        emit!(BalanceRangeEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            bracket,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_compare_balances_comp_def(
+        ctx: Context<InitCompareBalancesCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Compares the balances of two accounts for reconciliation. Admin only.
+    ///
+    /// Only the ordering is revealed, through `BalanceComparisonEvent`: 0 when the
+    /// first balance is lower, 1 when they are equal, 2 when it is higher.
+    pub fn compare_balances(
+        ctx: Context<CompareBalances>,
+        computation_offset: u64,
+        _first_account_id: u64,
+        _second_account_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.first_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.second_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let first = &ctx.accounts.first_account;
+        let second = &ctx.accounts.second_account;
+        let args = vec![
+            Argument::PlaintextU128(first.mxe_balance_nonce),
+            Argument::Account(first.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU128(second.mxe_balance_nonce),
+            Argument::Account(second.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CompareBalancesCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.first_account.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.second_account.key(),
+                    is_writable: false,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "compare_balances")]
+    pub fn compare_balances_callback(
+        ctx: Context<CompareBalancesCallback>,
+        output: ComputationOutputs<CompareBalancesOutput>,
+    ) -> Result<()> {
+        let ordering = match output {
+            ComputationOutputs::Success(CompareBalancesOutput { field_0: ordering }) => ordering,
+            _ => {
+                return computation_aborted(
+                    "compare_balances",
+                    ctx.accounts.first_account.account_id,
+                )
+            }
+        };
+
+        emit!(BalanceComparisonEvent {
+            first_account_id: ctx.accounts.first_account.account_id,
+            second_account_id: ctx.accounts.second_account.account_id,
+            ordering,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_reveal_treasury_comp_def(ctx: Context<InitRevealTreasuryCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Re-encrypts the fees collected in the treasury to the admin's key and
+    /// emits them in `TreasuryRevealedEvent`. Admin only; nothing is stored.
+    ///
+    /// # Arguments
+    /// * `admin_enc_pubkey` - Encryption key the total is encrypted to
+    /// * `admin_nonce` - Fresh nonce for the returned ciphertext
+    pub fn reveal_treasury(
+        ctx: Context<RevealTreasury>,
+        computation_offset: u64,
+        admin_enc_pubkey: [u8; 32],
+        admin_nonce: u128,
+    ) -> Result<()> {
+        let treasury = &ctx.accounts.treasury;
+        let args = vec![
+            Argument::PlaintextU128(treasury.total_nonce),
+            Argument::Account(treasury.key(), 8, 32),
+            Argument::PlaintextBool(treasury.initialized),
+            Argument::ArcisPubkey(admin_enc_pubkey),
+            Argument::PlaintextU128(admin_nonce),
+        ];
+
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealTreasuryCallback::callback_ix(&[])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_treasury")]
+    pub fn reveal_treasury_callback(
+        ctx: Context<RevealTreasuryCallback>,
+        output: ComputationOutputs<RevealTreasuryOutput>,
+    ) -> Result<()> {
+        let total = match output {
+            ComputationOutputs::Success(RevealTreasuryOutput { field_0: total }) => total,
+            // The treasury belongs to no account
+            _ => return computation_aborted("reveal_treasury", 0),
+        };
+
+        emit!(TreasuryRevealedEvent {
+            encrypted_total: total.ciphertexts[0],
+            total_nonce: total.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_aggregate_balances_comp_def(
+        ctx: Context<InitAggregateBalancesCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Totals the balances of a set of accounts for an auditor. Admin only.
+    ///
+    /// The `UserAccount`s are passed as remaining accounts, at most
+    /// `MAX_AGGREGATE_ACCOUNTS` and each only once. The total is encrypted to the
+    /// auditor's key; individual balances are never revealed.
+    ///
+    /// # Arguments
+    /// * `auditor_enc_pubkey` - Encryption key the total is encrypted to
+    /// * `auditor_nonce` - Nonce for the encrypted total
+    pub fn aggregate_balances(
+        ctx: Context<AggregateBalances>,
+        computation_offset: u64,
+        auditor_enc_pubkey: [u8; 32],
+        auditor_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            !ctx.remaining_accounts.is_empty()
+                && ctx.remaining_accounts.len() <= MAX_AGGREGATE_ACCOUNTS,
+            ErrorCode::InvalidAccountCount
+        );
+
+        let mut accounts: Vec<(Pubkey, u128)> = Vec::with_capacity(MAX_AGGREGATE_ACCOUNTS);
+        for info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+            let account = UserAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require!(
+                account.account_state == AccountState::Active,
+                ErrorCode::InvalidAccountState
+            );
+            require!(
+                accounts.iter().all(|(key, _)| *key != info.key()),
+                ErrorCode::DuplicateAccount
+            );
+            accounts.push((info.key(), account.mxe_balance_nonce));
+        }
+        let account_count = accounts.len() as u8;
+
+        let mut args = Vec::with_capacity(MAX_AGGREGATE_ACCOUNTS * 2 + 3);
+        // Padding slots read the first account and are ignored by the circuit
+        for slot in 0..MAX_AGGREGATE_ACCOUNTS {
+            let (key, nonce) = accounts.get(slot).copied().unwrap_or(accounts[0]);
+            args.push(Argument::PlaintextU128(nonce));
+            args.push(Argument::Account(key, USER_ACCOUNT_MXE_BALANCE_OFFSET, 32));
+        }
+        args.push(Argument::PlaintextU8(account_count));
+        args.push(Argument::ArcisPubkey(auditor_enc_pubkey));
+        args.push(Argument::PlaintextU128(auditor_nonce));
+
+        let callback_accounts: Vec<CallbackAccount> = accounts
+            .iter()
+            .map(|(key, _)| CallbackAccount {
+                pubkey: *key,
+                is_writable: false,
+            })
+            .collect();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AggregateBalancesCallback::callback_ix(&callback_accounts)],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "aggregate_balances")]
+    pub fn aggregate_balances_callback(
+        ctx: Context<AggregateBalancesCallback>,
+        output: ComputationOutputs<AggregateBalancesOutput>,
+    ) -> Result<()> {
+        let (total, overflow) = match output {
+            ComputationOutputs::Success(AggregateBalancesOutput {
+                field_0: AggregateBalancesOutputStruct0 { field_0: total, field_1: overflow },
+            }) => (total, overflow),
+            // Spans several accounts, so no single account id to report
+            _ => return computation_aborted("aggregate_balances", 0),
+        };
+
+        emit!(AggregateComputedEvent {
+            account_count: ctx.remaining_accounts.len() as u8,
+            total: total.ciphertexts[0],
+            total_nonce: total.nonce,
+            overflow,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_calculate_rewards_comp_def(
+        ctx: Context<InitCalculateRewardsCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn calculate_rewards(
+        ctx: Context<CalculateRewards>,
+        computation_offset: u64,
+        _account_id: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state != AccountState::Frozen,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+
+        // Tiers compare against the balance in the account's own base units
+        let reward_config = ctx.accounts.program_config.reward_config;
+        let decimals = ctx.accounts.user_account.decimals;
+        let [silver, gold, premium] = [
+            reward_config.silver_threshold,
+            reward_config.gold_threshold,
+            reward_config.premium_threshold,
+        ]
+        .map(|threshold| reward_config.scale_threshold(threshold, decimals));
+        let args = vec![
+            Argument::PlaintextU64(ctx.accounts.user_account.transaction_count),
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(reward_config.points_per_tx),
+            Argument::PlaintextU64(silver),
+            Argument::PlaintextU64(gold),
+            Argument::PlaintextU64(premium),
+            Argument::PlaintextU64(ctx.accounts.user_account.account_id),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CalculateRewardsCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.program_config.key(),
+                    is_writable: false,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "calculate_rewards")]
+    pub fn calculate_rewards_callback(
+        ctx: Context<CalculateRewardsCallback>,
+        output: ComputationOutputs<CalculateRewardsOutput>,
+    ) -> Result<()> {
+        let (reward_points, tier) = match output {
+            ComputationOutputs::Success(CalculateRewardsOutput {
+                field_0: CalculateRewardsOutputStruct0 {
+                    field_0: points,
+                    field_1: tier,
+                    field_2: account_id,
+                },
+            }) => {
+                require!(
+                    account_id == ctx.accounts.user_account.account_id,
+                    ErrorCode::CallbackAccountMismatch
+                );
+                (points, tier)
+            }
+            _ => {
+                return computation_aborted(
+                    "calculate_rewards",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        // Points left unspent since the previous expiry window are forfeited
+        // before the new ones are added
+        let now = Clock::get()?.unix_timestamp;
+        let user_account = &mut ctx.accounts.user_account;
+        if user_account.reward_points > 0 && now >= user_account.points_expiry_ts {
+            emit!(RewardsExpiredEvent {
+                account_id: user_account.account_id,
+                expired_points: user_account.reward_points,
+            });
+            user_account.reward_points = 0;
+        }
+        user_account.points_updated_ts = now;
+        user_account.points_expiry_ts =
+            now.saturating_add(ctx.accounts.program_config.points_expiry_period);
+
+        // A total past u64::MAX is kept at the cap rather than failing the callback,
+        // which would also discard the points that still fit
+        match user_account.reward_points.checked_add(reward_points) {
+            Some(total) => user_account.reward_points = total,
+            None => {
+                let discarded_points = reward_points - (u64::MAX - user_account.reward_points);
+                user_account.reward_points = u64::MAX;
+                emit!(RewardsOverflowEvent {
+                    account_id: user_account.account_id,
+                    discarded_points,
+                });
+            }
+        }
+
+        emit!(RewardsCalculatedEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            reward_points,
+            total_rewards: ctx.accounts.user_account.reward_points,
+            tier,
+        });
+        Ok(())
+    }
+
+    pub fn init_redeem_rewards_comp_def(
+        ctx: Context<InitRedeemRewardsCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Converts reward points into encrypted balance.
+    ///
+    /// `points` are spent when the computation is queued and credited at the
+    /// configured `points_per_unit` rate; remainder points below one unit are
+    /// rejected rather than rounded away.
+    /// Points past their `points_expiry_ts` can no longer be redeemed.
+    ///
+    /// # Arguments
+    /// * `points` - Reward points to redeem, a multiple of `points_per_unit`
+    pub fn redeem_rewards(
+        ctx: Context<RedeemRewards>,
+        computation_offset: u64,
+        _account_id: u64,
+        points: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let points_per_unit = ctx.accounts.program_config.points_per_unit;
+        require!(
+            points > 0 && points % points_per_unit == 0,
+            ErrorCode::InvalidRedemptionAmount
+        );
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.reward_points = user_account
+            .reward_points
+            .checked_sub(points)
+            .ok_or(ErrorCode::InsufficientRewards)?;
+        require!(
+            Clock::get()?.unix_timestamp < user_account.points_expiry_ts,
+            ErrorCode::RewardsExpired
+        );
+
+        let args = vec![
+            Argument::PlaintextU128(user_account.mxe_balance_nonce),
+            Argument::Account(user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(points / points_per_unit),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RedeemRewardsCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "redeem_rewards")]
+    pub fn redeem_rewards_callback(
+        ctx: Context<RedeemRewardsCallback>,
+        output: ComputationOutputs<RedeemRewardsOutput>,
+    ) -> Result<()> {
+        let (new_balance, fits) = match output {
+            ComputationOutputs::Success(RedeemRewardsOutput {
+                field_0: RedeemRewardsOutputStruct0 {
+                    field_0: balance,
+                    field_1: fits,
+                },
+            }) => (balance, fits),
+            _ => {
+                ctx.accounts.user_account.end_computation();
+                return computation_aborted("redeem_rewards", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        ctx.accounts.user_account.end_computation();
+
+        require!(fits, ErrorCode::Overflow);
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.mxe_encrypted_balance = new_balance.ciphertexts[0];
+        user_account.mxe_balance_nonce = new_balance.nonce;
+
+        emit!(RewardsRedeemedEvent {
+            account_id: user_account.account_id,
+            remaining_points: user_account.reward_points,
+            new_balance_nonce: new_balance.nonce,
+        });
+        Ok(())
+    }
+
+    pub fn init_accrue_interest_comp_def(
+        ctx: Context<InitAccrueInterestCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Compounds interest on an account's encrypted balance. Admin only.
+    ///
+    /// Every whole `interest_period` since `last_accrual_ts` adds `rate_bps` of
+    /// the balance, up to `MAX_INTEREST_PERIODS` per call; the rest carries over
+    /// to the next call. The interest accrual fee is taken out of the interest and
+    /// collected into the treasury.
+    ///
+    /// # Arguments
+    /// * `rate_bps` - Interest per period in basis points
+    pub fn accrue_interest(
+        ctx: Context<AccrueInterest>,
+        computation_offset: u64,
+        _account_id: u64,
+        rate_bps: u16,
+    ) -> Result<()> {
+        require!(rate_bps <= 10_000, ErrorCode::InvalidInterestRate);
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let interest_period = ctx.accounts.program_config.interest_period;
+        require!(interest_period > 0, ErrorCode::InvalidInterestPeriod);
+
+        // Advanced now rather than in the callback, so a second accrual queued
+        // before this one lands cannot count the same periods again
+        let now = Clock::get()?.unix_timestamp;
+        let user_account = &mut ctx.accounts.user_account;
+        let elapsed = now.saturating_sub(user_account.last_accrual_ts).max(0);
+        let periods = (elapsed / interest_period).min(MAX_INTEREST_PERIODS);
+        user_account.last_accrual_ts += periods * interest_period;
+
+        let mut args = vec![
+            Argument::PlaintextU128(user_account.mxe_balance_nonce),
+            Argument::Account(user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(rate_bps as u64),
+            Argument::PlaintextU8(periods as u8),
+        ];
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::InterestAccrual));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AccrueInterestCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.treasury.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "accrue_interest")]
+    pub fn accrue_interest_callback(
+        ctx: Context<AccrueInterestCallback>,
+        output: ComputationOutputs<AccrueInterestOutput>,
+    ) -> Result<()> {
+        let (new_balance, new_treasury, periods) = match output {
+            ComputationOutputs::Success(AccrueInterestOutput {
+                field_0: AccrueInterestOutputStruct0 {
+                    field_0: balance,
+                    field_1: treasury,
+                    field_2: periods,
+                },
+            }) => (balance, treasury, periods),
+            _ => {
+                ctx.accounts.user_account.end_computation();
+                ctx.accounts.treasury.end_computation();
+                return computation_aborted("accrue_interest", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        ctx.accounts.user_account.end_computation();
+        ctx.accounts.treasury.end_computation();
+
+        ctx.accounts.treasury.store_total((new_treasury.ciphertexts[0], new_treasury.nonce));
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.mxe_encrypted_balance = new_balance.ciphertexts[0];
+        user_account.mxe_balance_nonce = new_balance.nonce;
+
+        emit!(InterestAccruedEvent {
+            account_id: user_account.account_id,
+            periods,
+        });
+        Ok(())
+    }
+
+    pub fn init_check_premium_eligibility_comp_def(
+        ctx: Context<InitCheckPremiumEligibilityCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Checks privately whether an account meets all premium tier criteria.
+    ///
+    /// Only the combined result is revealed, through `PremiumEligibilityEvent`.
+    /// Owner only; requires an active session.
+    ///
+    /// # Arguments
+    /// * `min_balance` - Minimum balance
+    /// * `min_transaction_count` - Minimum number of transactions
+    /// * `min_account_age` - Minimum account age in seconds
+    pub fn check_premium_eligibility(
+        ctx: Context<CheckPremiumEligibility>,
+        computation_offset: u64,
+        _account_id: u64,
+        min_balance: u64,
+        min_transaction_count: u64,
+        min_account_age: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+        let account_age = now.saturating_sub(ctx.accounts.user_account.created_at).max(0) as u64;
+
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::PlaintextU64(ctx.accounts.user_account.transaction_count),
+            Argument::PlaintextU64(account_age),
+            Argument::PlaintextU64(min_balance),
+            Argument::PlaintextU64(min_transaction_count),
+            Argument::PlaintextU64(min_account_age),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CheckPremiumEligibilityCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: false,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_premium_eligibility")]
+    pub fn check_premium_eligibility_callback(
+        ctx: Context<CheckPremiumEligibilityCallback>,
+        output: ComputationOutputs<CheckPremiumEligibilityOutput>,
+    ) -> Result<()> {
+        let eligible = match output {
+            ComputationOutputs::Success(CheckPremiumEligibilityOutput { field_0: eligible }) => {
+                eligible
+            }
+            _ => {
+                return computation_aborted(
+                    "check_premium_eligibility",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        emit!(PremiumEligibilityEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            eligible,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_compute_aggregate_loyalty_comp_def(
+        ctx: Context<InitComputeAggregateLoyaltyCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Computes one loyalty tier across several accounts held by the same owner.
+    ///
+    /// `user_account` is the primary account; up to four more are passed as remaining
+    /// accounts. Every account must belong to the signer and appear only once. Unused
+    /// circuit slots are padded with the primary account and masked out in the MPC.
+    /// Requires an active session on the primary account.
+    pub fn compute_aggregate_loyalty(
+        ctx: Context<ComputeAggregateLoyalty>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let primary = &ctx.accounts.user_account;
+        require!(
+            primary.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            primary.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+        require!(
+            ctx.remaining_accounts.len() < MAX_LOYALTY_ACCOUNTS,
+            ErrorCode::InvalidAccountCount
+        );
+
+        let mut accounts = vec![(primary.key(), primary.mxe_balance_nonce)];
+        let mut total_transaction_count = primary.transaction_count;
+        for info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(*info.owner, crate::ID, ErrorCode::Unauthorized);
+            let extra = UserAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require!(
+                extra.owner_pubkey == ctx.accounts.payer.key(),
+                ErrorCode::Unauthorized
+            );
+            require!(
+                extra.account_state == AccountState::Active,
+                ErrorCode::InvalidAccountState
+            );
+            require!(
+                accounts.iter().all(|(key, _)| *key != info.key()),
+                ErrorCode::DuplicateAccount
+            );
+            require!(extra.decimals == primary.decimals, ErrorCode::DecimalsMismatch);
+            total_transaction_count = total_transaction_count
+                .checked_add(extra.transaction_count)
+                .ok_or(ErrorCode::Overflow)?;
+            accounts.push((info.key(), extra.mxe_balance_nonce));
+        }
+        let account_count = accounts.len() as u8;
+
+        // One set of thresholds serves every slot, hence the shared decimals
+        let reward_config = ctx.accounts.program_config.reward_config;
+        let [silver, gold, premium] = [
+            reward_config.silver_threshold,
+            reward_config.gold_threshold,
+            reward_config.premium_threshold,
+        ]
+        .map(|threshold| reward_config.scale_threshold(threshold, primary.decimals));
+
+        let mut args = Vec::with_capacity(MAX_LOYALTY_ACCOUNTS * 2 + 7);
+        for slot in 0..MAX_LOYALTY_ACCOUNTS {
+            let (key, nonce) = accounts.get(slot).copied().unwrap_or(accounts[0]);
+            args.push(Argument::PlaintextU128(nonce));
+            args.push(Argument::Account(key, USER_ACCOUNT_MXE_BALANCE_OFFSET, 32));
+        }
+        args.push(Argument::PlaintextU64(total_transaction_count));
+        args.push(Argument::PlaintextU8(account_count));
+        args.push(Argument::PlaintextU64(reward_config.points_per_tx));
+        args.push(Argument::PlaintextU64(silver));
+        args.push(Argument::PlaintextU64(gold));
+        args.push(Argument::PlaintextU64(premium));
+        args.push(Argument::PlaintextU64(primary.account_id));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ComputeAggregateLoyaltyCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: false,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "compute_aggregate_loyalty")]
+    pub fn compute_aggregate_loyalty_callback(
+        ctx: Context<ComputeAggregateLoyaltyCallback>,
+        output: ComputationOutputs<ComputeAggregateLoyaltyOutput>,
+    ) -> Result<()> {
+        let loyalty_tier = match output {
+            ComputationOutputs::Success(ComputeAggregateLoyaltyOutput {
+                field_0: ComputeAggregateLoyaltyOutputStruct0 {
+                    field_0: tier,
+                    field_1: account_id,
+                },
+            }) => {
+                require!(
+                    account_id == ctx.accounts.user_account.account_id,
+                    ErrorCode::CallbackAccountMismatch
+                );
+                tier
+            }
+            _ => {
+                return computation_aborted(
+                    "compute_aggregate_loyalty",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        emit!(AggregateLoyaltyEvent {
+            owner: ctx.accounts.user_account.owner_pubkey,
+            primary_account_id: ctx.accounts.user_account.account_id,
+            loyalty_tier,
+        });
+        Ok(())
+    }
+
+    pub fn init_check_aml_velocity_comp_def(
+        ctx: Context<InitCheckAmlVelocityCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Adds a completed transaction to one party's AML throughput and checks the velocity.
+    ///
+    /// The party's encrypted accumulator grows by the transaction amount whether they
+    /// sent or received it. When the transaction was made at least the configured
+    /// `aml_window` after the window opened, the accumulator restarts from it; a
+    /// transaction older than the open window still counts towards it. The window
+    /// and the `aml_threshold` come from the program config. Each party of a
+    /// transaction can be counted only once. Admin only; reveals a single
+    /// `flagged` bool.
+    pub fn check_aml_velocity(
+        ctx: Context<CheckAmlVelocity>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.transaction.status == TransactionStatus::Completed,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        let party = ctx.accounts.user_account.key();
+        let role_flag = if party == ctx.accounts.transaction.sender {
+            AML_RECORDED_SENDER
+        } else if party == ctx.accounts.transaction.receiver {
+            AML_RECORDED_RECEIVER
+        } else {
+            return Err(ErrorCode::NotTransactionParty.into());
+        };
+        require!(
+            ctx.accounts.transaction.aml_recorded & role_flag == 0,
+            ErrorCode::AmlAlreadyRecorded
+        );
+        ctx.accounts.transaction.aml_recorded |= role_flag;
+
+        // Windows follow when the transactions happened, not when they are checked
+        let made_at = ctx.accounts.transaction.timestamp;
+        let window = ctx.accounts.program_config.aml_window;
+        let threshold = ctx.accounts.program_config.aml_threshold;
+        let tracker = &mut ctx.accounts.aml_tracker;
+        let reset_window = tracker.user_account == Pubkey::default()
+            || made_at.saturating_sub(tracker.window_start_ts) >= window;
+        if reset_window {
+            tracker.bump = ctx.bumps.aml_tracker;
+            tracker.user_account = party;
+            tracker.window_start_ts = made_at;
+        }
+
+        let args = vec![
+            Argument::PlaintextU128(tracker.throughput_nonce),
+            Argument::Account(tracker.key(), 8 + 32, 32),
+            Argument::ArcisPubkey(ctx.accounts.sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(ctx.accounts.transaction.amount_nonce),
+            Argument::Account(ctx.accounts.transaction.key(), 8 + 8 + 32 + 32, 32),
+            Argument::PlaintextBool(reset_window),
+            Argument::PlaintextU64(threshold),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CheckAmlVelocityCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.aml_tracker.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: false,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_aml_velocity")]
+    pub fn check_aml_velocity_callback(
+        ctx: Context<CheckAmlVelocityCallback>,
+        output: ComputationOutputs<CheckAmlVelocityOutput>,
+    ) -> Result<()> {
+        let (throughput, flagged) = match output {
+            ComputationOutputs::Success(CheckAmlVelocityOutput {
+                field_0: CheckAmlVelocityOutputStruct0 {
+                    field_0: throughput,
+                    field_1: flagged,
+                },
+            }) => (throughput, flagged),
+            _ => {
+                return computation_aborted(
+                    "check_aml_velocity",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        let tracker = &mut ctx.accounts.aml_tracker;
+        tracker.encrypted_throughput = throughput.ciphertexts[0];
+        tracker.throughput_nonce = throughput.nonce;
+
+        emit!(AmlVelocityEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            flagged,
+            window_start_ts: tracker.window_start_ts,
+        });
+        Ok(())
+    }
+
+    pub fn init_prove_payment_received_comp_def(
+        ctx: Context<InitProvePaymentReceivedCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Produces a receipt that a completed payment was at least `threshold`.
+    ///
+    /// Either party's owner may request it, with an active session on that
+    /// party's account passed as `session_account`. The amount stays encrypted; only
+    /// whether it meets the threshold is revealed in `PaymentReceiptEvent`,
+    /// which the MXE cluster's signed callback attests to.
+    ///
+    /// # Arguments
+    /// * `threshold` - Minimum amount the receipt vouches for
+    pub fn prove_payment_received(
+        ctx: Context<ProvePaymentReceived>,
+        computation_offset: u64,
+        _transaction_id: u64,
+        threshold: u64,
+    ) -> Result<()> {
+        let payer = ctx.accounts.payer.key();
+        let party_key = if payer == ctx.accounts.sender_account.owner_pubkey {
+            ctx.accounts.sender_account.key()
+        } else if payer == ctx.accounts.receiver_account.owner_pubkey {
+            ctx.accounts.receiver_account.key()
+        } else {
+            return Err(ErrorCode::Unauthorized.into());
+        };
+        require_keys_eq!(
+            ctx.accounts.session_account.user_account,
+            party_key,
+            ErrorCode::Unauthorized
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+        require!(
+            ctx.accounts.transaction.status == TransactionStatus::Completed,
+            ErrorCode::InvalidTransactionStatus
+        );
+        ctx.accounts.transaction.computation_offset = computation_offset;
+
+        let args = vec![
+            Argument::ArcisPubkey(ctx.accounts.sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(ctx.accounts.transaction.amount_nonce),
+            Argument::Account(ctx.accounts.transaction.key(), 8 + 8 + 32 + 32, 32),
+            Argument::PlaintextU64(threshold),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProvePaymentReceivedCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.transaction.key(),
+                is_writable: false,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "prove_payment_received")]
+    pub fn prove_payment_received_callback(
+        ctx: Context<ProvePaymentReceivedCallback>,
+        output: ComputationOutputs<ProvePaymentReceivedOutput>,
+    ) -> Result<()> {
+        let meets_threshold = match output {
+            ComputationOutputs::Success(ProvePaymentReceivedOutput { field_0 }) => field_0,
+            _ => return computation_aborted("prove_payment_received", 0),
+        };
+
+        emit!(PaymentReceiptEvent {
+            transaction_id: ctx.accounts.transaction.transaction_id,
+            meets_threshold,
+            computation_offset: ctx.accounts.transaction.computation_offset,
+        });
+        Ok(())
+    }
+
+    pub fn init_reveal_transaction_amount_comp_def(
+        ctx: Context<InitRevealTransactionAmountCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Re-encrypts a payment's amount to the calling party's key and emits it in
+    /// `TransactionAmountRevealedEvent`.
+    ///
+    /// Signed by the sender's or the receiver's owner; the amount is encrypted
+    /// to that account's `owner_enc_pubkey`. Requires an active session on that
+    /// account, passed as `session_account`. Nothing is stored.
+    ///
+    /// # Arguments
+    /// * `client_nonce` - Fresh nonce for the returned ciphertext
+    pub fn reveal_transaction_amount(
+        ctx: Context<RevealTransactionAmount>,
+        computation_offset: u64,
+        _transaction_id: u64,
+        client_nonce: u128,
+    ) -> Result<()> {
+        let payer = ctx.accounts.payer.key();
+        let party = if payer == ctx.accounts.sender_account.owner_pubkey {
+            &ctx.accounts.sender_account
+        } else if payer == ctx.accounts.receiver_account.owner_pubkey {
+            &ctx.accounts.receiver_account
+        } else {
+            return Err(ErrorCode::Unauthorized.into());
+        };
+        // The amount is only stored once the payment settles or awaits approval
+        require!(
+            ctx.accounts.transaction.amount_nonce != 0,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        let party_key = party.key();
+        require_keys_eq!(
+            ctx.accounts.session_account.user_account,
+            party_key,
+            ErrorCode::Unauthorized
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.session_account.touch(now)?;
+
+        let args = vec![
+            Argument::ArcisPubkey(ctx.accounts.sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(ctx.accounts.transaction.amount_nonce),
+            Argument::Account(ctx.accounts.transaction.key(), 8 + 8 + 32 + 32, 32),
+            Argument::ArcisPubkey(party.owner_enc_pubkey),
+            Argument::PlaintextU128(client_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealTransactionAmountCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.transaction.key(),
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: party_key,
+                    is_writable: false,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_transaction_amount")]
+    pub fn reveal_transaction_amount_callback(
+        ctx: Context<RevealTransactionAmountCallback>,
+        output: ComputationOutputs<RevealTransactionAmountOutput>,
+    ) -> Result<()> {
+        let amount = match output {
+            ComputationOutputs::Success(RevealTransactionAmountOutput { field_0: amount }) => {
+                amount
+            }
+            _ => {
+                return computation_aborted(
+                    "reveal_transaction_amount",
+                    ctx.accounts.party_account.account_id,
+                )
+            }
+        };
+
+        emit!(TransactionAmountRevealedEvent {
+            transaction_id: ctx.accounts.transaction.transaction_id,
+            account_id: ctx.accounts.party_account.account_id,
+            encrypted_amount: amount.ciphertexts[0],
+            amount_nonce: amount.nonce,
+        });
+        Ok(())
+    }
+
+    pub fn init_ping_comp_def(ctx: Context<InitPingCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Health check for operators: runs a trivial circuit through the full
+    /// queue, compute and callback loop and emits `PingEvent`.
+    ///
+    /// Touches no user state and is allowed while the program is paused.
+    ///
+    /// # Arguments
+    /// * `mxe_nonce` - Nonce for encrypting the probe value under the MXE key
+    pub fn ping(ctx: Context<Ping>, computation_offset: u64, mxe_nonce: u128) -> Result<()> {
+        let args = vec![Argument::PlaintextU128(mxe_nonce)];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![PingCallback::callback_ix(&[])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "ping")]
+    pub fn ping_callback(
+        ctx: Context<PingCallback>,
+        output: ComputationOutputs<PingOutput>,
+    ) -> Result<()> {
+        let success = match output {
+            ComputationOutputs::Success(PingOutput { field_0: success }) => success,
+            _ => return computation_aborted("ping", 0),
+        };
+
+        emit!(PingEvent { success });
+        Ok(())
+    }
+
+    pub fn init_recover_account_comp_def(
+        ctx: Context<InitRecoverAccountCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Sets the guardians allowed to recover an account and the approval quorum.
+    ///
+    /// Replacing the guardian set discards any recovery approvals in progress. Fails
+    /// with `RecoveryInProgress` while a queued recovery has not called back.
+    ///
+    /// # Arguments
+    /// * `guardians` - Distinct guardian pubkeys (at most `MAX_GUARDIANS`)
+    /// * `quorum` - Approvals needed to recover the account
+    pub fn set_guardians(
+        ctx: Context<SetGuardians>,
+        guardians: Vec<Pubkey>,
+        quorum: u8,
+    ) -> Result<()> {
+        require!(
+            !guardians.is_empty() && guardians.len() <= MAX_GUARDIANS,
+            ErrorCode::InvalidGuardianSet
+        );
+        require!(
+            quorum > 0 && quorum as usize <= guardians.len(),
+            ErrorCode::InvalidGuardianSet
+        );
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(
+                !guardians[..i].contains(guardian),
+                ErrorCode::InvalidGuardianSet
+            );
+        }
+
+        let record = &mut ctx.accounts.guardians;
+        require!(
+            !record.recovery_in_flight(Clock::get()?.unix_timestamp),
+            ErrorCode::RecoveryInProgress
+        );
+        record.bump = ctx.bumps.guardians;
+        record.user_account = ctx.accounts.user_account.key();
+        record.guardians = guardians;
+        record.quorum = quorum;
+        record.clear_recovery();
+        Ok(())
+    }
+
+    /// Records a guardian's approval to hand the account to a new owner.
+    ///
+    /// Approvals only count towards the same proposed owner and encryption key; a
+    /// guardian proposing different keys restarts the approval round. Fails with
+    /// `RecoveryInProgress` while a queued recovery has not called back.
+    pub fn approve_recovery(
+        ctx: Context<ApproveRecovery>,
+        new_owner: Pubkey,
+        new_enc_pubkey: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            new_enc_pubkey != [0u8; 32],
+            ErrorCode::InvalidEncryptionPubkey
+        );
+
+        let record = &mut ctx.accounts.guardians;
+        require!(
+            !record.recovery_in_flight(Clock::get()?.unix_timestamp),
+            ErrorCode::RecoveryInProgress
+        );
+        let index = record
+            .guardians
+            .iter()
+            .position(|g| *g == ctx.accounts.guardian.key())
+            .ok_or(ErrorCode::Unauthorized)?;
+
+        if record.pending_owner != new_owner || record.pending_enc_pubkey != new_enc_pubkey {
+            record.clear_recovery();
+            record.pending_owner = new_owner;
+            record.pending_enc_pubkey = new_enc_pubkey;
+        }
+        record.approvals |= 1 << index;
+
+        emit!(RecoveryApprovedEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            guardian: ctx.accounts.guardian.key(),
+            approvals: record.approvals.count_ones() as u8,
+            quorum: record.quorum,
+        });
+        Ok(())
+    }
+
+    /// Rotates the account's owner keys once a guardian quorum approved it.
+    ///
+    /// Queues a re-encryption of the balance to the new encryption key; the owner
+    /// keys only change in the callback, together with the re-encrypted balance.
+    /// The guardian set and the approvals are frozen until then.
+    ///
+    /// # Arguments
+    /// * `new_balance_nonce` - Nonce for the balance encrypted to the new key
+    pub fn recover_account(
+        ctx: Context<RecoverAccount>,
+        computation_offset: u64,
+        _account_id: u64,
+        new_balance_nonce: u128,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let record = &mut ctx.accounts.guardians;
+        require!(!record.recovery_in_flight(now), ErrorCode::RecoveryInProgress);
+        require!(
+            record.pending_owner != Pubkey::default()
+                && record.approvals.count_ones() >= record.quorum as u32,
+            ErrorCode::RecoveryQuorumNotMet
+        );
+        record.recovery_queued_at = now;
+        let record = &ctx.accounts.guardians;
+
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(ctx.accounts.user_account.key(), USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            Argument::ArcisPubkey(record.pending_enc_pubkey),
+            Argument::PlaintextU128(new_balance_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RecoverAccountCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.user_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.guardians.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "recover_account")]
+    pub fn recover_account_callback(
+        ctx: Context<RecoverAccountCallback>,
+        output: ComputationOutputs<RecoverAccountOutput>,
+    ) -> Result<()> {
+        let balance = match output {
+            ComputationOutputs::Success(RecoverAccountOutput { field_0: balance }) => balance,
+            _ => {
+                ctx.accounts.user_account.end_computation();
+                // The guardians may approve the same recovery again
+                ctx.accounts.guardians.recovery_queued_at = 0;
+                return computation_aborted("recover_account", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        ctx.accounts.user_account.end_computation();
+
+        let record = &mut ctx.accounts.guardians;
+        require!(
+            record.pending_owner != Pubkey::default(),
+            ErrorCode::RecoveryQuorumNotMet
+        );
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner_pubkey = record.pending_owner;
+        user_account.owner_enc_pubkey = record.pending_enc_pubkey;
+        user_account.encrypted_balance = balance.ciphertexts[0];
+        user_account.balance_nonce = balance.nonce;
+        record.clear_recovery();
+
+        emit!(AccountRecoveredEvent {
+            account_id: user_account.account_id,
+            new_owner: user_account.owner_pubkey,
+            encrypted_balance: balance.ciphertexts[0],
+            balance_nonce: balance.nonce,
+        });
+        Ok(())
+    }
+
+    pub fn init_rotate_encryption_key_comp_def(
+        ctx: Context<InitRotateEncryptionKeyCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Moves the account to a new client encryption key, e.g. after a suspected
+    /// key compromise. Owner only.
+    ///
+    /// The MPC re-encrypts the balance to `client_pubkey`; the callback then
+    /// replaces `owner_enc_pubkey` and the owner-readable balance with it.
+    ///
+    /// # Arguments
+    /// * `client_pubkey` - New Arcium encryption public key
+    /// * `client_nonce` - Nonce for the balance encrypted to the new key
+    pub fn rotate_encryption_key(
+        ctx: Context<RotateEncryptionKey>,
+        computation_offset: u64,
+        _account_id: u64,
+        client_pubkey: [u8; 32],
+        client_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(client_pubkey != [0; 32], ErrorCode::InvalidEncryptionPubkey);
+
+        let args = vec![
+            Argument::PlaintextU128(ctx.accounts.user_account.mxe_balance_nonce),
+            Argument::Account(
+                ctx.accounts.user_account.key(),
+                USER_ACCOUNT_MXE_BALANCE_OFFSET,
+                32,
+            ),
+            Argument::ArcisPubkey(client_pubkey),
+            Argument::PlaintextU128(client_nonce),
+        ];
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.user_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RotateEncryptionKeyCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "rotate_encryption_key")]
+    pub fn rotate_encryption_key_callback(
+        ctx: Context<RotateEncryptionKeyCallback>,
+        output: ComputationOutputs<RotateEncryptionKeyOutput>,
+    ) -> Result<()> {
+        let balance = match output {
+            ComputationOutputs::Success(RotateEncryptionKeyOutput { field_0: balance }) => balance,
+            _ => {
+                ctx.accounts.user_account.end_computation();
+                return computation_aborted(
+                    "rotate_encryption_key",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.user_account.end_computation();
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner_enc_pubkey = balance.encryption_key;
+        user_account.encrypted_balance = balance.ciphertexts[0];
+        user_account.balance_nonce = balance.nonce;
+
+        emit!(EncryptionKeyRotatedEvent {
+            account_id: user_account.account_id,
+            balance_nonce: balance.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_set_daily_limit_comp_def(ctx: Context<InitSetDailyLimitCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Sets the owner's daily spend limit for `process_payment`. Owner only.
+    ///
+    /// The limit arrives encrypted to the owner's key and is stored MXE-encrypted,
+    /// so neither it nor the amount spent so far is visible on-chain. Payments
+    /// that would take the day's total above it are rejected inside the MPC.
+    ///
+    /// # Arguments
+    /// * `limit_ciphertext` - Limit encrypted to the owner's key
+    /// * `limit_nonce` - Nonce used for `limit_ciphertext`
+    /// * `mxe_nonce` - Nonce for the stored MXE-encrypted limit
+    pub fn set_daily_limit(
+        ctx: Context<SetDailyLimit>,
+        computation_offset: u64,
+        _account_id: u64,
+        limit_ciphertext: [u8; 32],
+        limit_nonce: u128,
+        mxe_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let args = vec![
+            Argument::ArcisPubkey(ctx.accounts.user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(limit_nonce),
+            Argument::EncryptedU64(limit_ciphertext),
+            Argument::PlaintextU128(mxe_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SetDailyLimitCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "set_daily_limit")]
+    pub fn set_daily_limit_callback(
+        ctx: Context<SetDailyLimitCallback>,
+        output: ComputationOutputs<SetDailyLimitOutput>,
+    ) -> Result<()> {
+        let limit = match output {
+            ComputationOutputs::Success(SetDailyLimitOutput { field_0: limit }) => limit,
+            _ => {
+                return computation_aborted("set_daily_limit", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.encrypted_daily_limit = limit.ciphertexts[0];
+        user_account.daily_limit_nonce = limit.nonce;
+        user_account.has_daily_limit = true;
+
+        emit!(DailyLimitSetEvent {
+            account_id: user_account.account_id,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Removes the owner's daily spend limit. Owner only.
+    ///
+    /// The debits that do not track daily spend refuse an account with a limit,
+    /// so this is how the owner gets them back. The stored ciphertexts are left
+    /// in place and ignored until a new limit is set.
+    pub fn clear_daily_limit(ctx: Context<ClearDailyLimit>, _account_id: u64) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.has_daily_limit = false;
+
+        emit!(DailyLimitClearedEvent {
+            account_id: user_account.account_id,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_set_min_reserve_comp_def(ctx: Context<InitSetMinReserveCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Sets a base-currency balance that `process_payment` may never spend.
+    /// Owner only.
+    ///
+    /// The reserve arrives encrypted to the owner's key and is stored
+    /// MXE-encrypted. A payment leaving less than it in the account fails as
+    /// insufficient, inside the MPC.
+    ///
+    /// # Arguments
+    /// * `reserve_ciphertext` - Reserve encrypted to the owner's key
+    /// * `reserve_nonce` - Nonce used for `reserve_ciphertext`
+    /// * `mxe_nonce` - Nonce for the stored MXE-encrypted reserve
+    pub fn set_min_reserve(
+        ctx: Context<SetMinReserve>,
+        computation_offset: u64,
+        _account_id: u64,
+        reserve_ciphertext: [u8; 32],
+        reserve_nonce: u128,
+        mxe_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let args = vec![
+            Argument::ArcisPubkey(ctx.accounts.user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(reserve_nonce),
+            Argument::EncryptedU64(reserve_ciphertext),
+            Argument::PlaintextU128(mxe_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SetMinReserveCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "set_min_reserve")]
+    pub fn set_min_reserve_callback(
+        ctx: Context<SetMinReserveCallback>,
+        output: ComputationOutputs<SetMinReserveOutput>,
+    ) -> Result<()> {
+        let reserve = match output {
+            ComputationOutputs::Success(SetMinReserveOutput { field_0: reserve }) => reserve,
+            _ => {
+                return computation_aborted("set_min_reserve", ctx.accounts.user_account.account_id)
+            }
+        };
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.encrypted_min_reserve = reserve.ciphertexts[0];
+        user_account.min_reserve_nonce = reserve.nonce;
+        user_account.has_min_reserve = true;
+
+        emit!(MinReserveSetEvent {
+            account_id: user_account.account_id,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn init_set_large_transfer_threshold_comp_def(
+        ctx: Context<InitSetLargeTransferThresholdCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Names a co-signer who must approve payments above an encrypted threshold.
+    /// Owner only.
+    ///
+    /// The threshold arrives encrypted to the owner's key and is stored
+    /// MXE-encrypted. Large transfers are only held back once the callback has
+    /// stored it.
+    ///
+    /// # Arguments
+    /// * `co_signer` - Signer of `approve_large_transfer`
+    /// * `threshold_ciphertext` - Threshold encrypted to the owner's key
+    /// * `threshold_nonce` - Nonce used for `threshold_ciphertext`
+    /// * `mxe_nonce` - Nonce for the stored MXE-encrypted threshold
+    pub fn set_co_signer(
+        ctx: Context<SetCoSigner>,
+        computation_offset: u64,
+        _account_id: u64,
+        co_signer: Pubkey,
+        threshold_ciphertext: [u8; 32],
+        threshold_nonce: u128,
+        mxe_nonce: u128,
+    ) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        user_account.co_signer = Some(co_signer);
+
+        let args = vec![
+            Argument::ArcisPubkey(user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(threshold_nonce),
+            Argument::EncryptedU64(threshold_ciphertext),
+            Argument::PlaintextU128(mxe_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SetLargeTransferThresholdCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.user_account.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "set_large_transfer_threshold")]
+    pub fn set_large_transfer_threshold_callback(
+        ctx: Context<SetLargeTransferThresholdCallback>,
+        output: ComputationOutputs<SetLargeTransferThresholdOutput>,
+    ) -> Result<()> {
+        let threshold = match output {
+            ComputationOutputs::Success(SetLargeTransferThresholdOutput {
+                field_0: threshold,
+            }) => threshold,
+            _ => {
+                return computation_aborted(
+                    "set_large_transfer_threshold",
+                    ctx.accounts.user_account.account_id,
+                )
+            }
+        };
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.encrypted_large_transfer_threshold = threshold.ciphertexts[0];
+        user_account.large_transfer_threshold_nonce = threshold.nonce;
+        user_account.has_large_transfer_threshold = true;
+
+        emit!(CoSignerSetEvent {
+            account_id: user_account.account_id,
+            co_signer: user_account.co_signer.unwrap_or_default(),
+        });
+        Ok(())
+    }
+
+    /// Removes the account's co-signer. Signed by both the owner and the
+    /// co-signer, so the owner alone cannot drop the approval requirement.
+    ///
+    /// The debits that skip the large-transfer threshold refuse an account with
+    /// a co-signer, so this is how the owner gets them back. Transfers still in
+    /// `PendingApproval` can no longer be approved.
+    pub fn remove_co_signer(ctx: Context<RemoveCoSigner>, _account_id: u64) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.co_signer = None;
+        user_account.has_large_transfer_threshold = false;
+
+        emit!(CoSignerRemovedEvent {
+            account_id: user_account.account_id,
+            co_signer: ctx.accounts.co_signer.key(),
+        });
+        Ok(())
+    }
+
+    pub fn init_approve_large_transfer_comp_def(
+        ctx: Context<InitApproveLargeTransferCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Completes a payment held back in `PendingApproval`. Signed by the sender's
+    /// co-signer.
+    ///
+    /// The stored amount moves with the current payment fee, which goes to the
+    /// treasury; the original tip is not charged. Held payments skip the daily
+    /// limit when sent, so the amount is counted against it on the sender's day
+    /// of approval; over the limit the payment stays held for a later day. The
+    /// minimum reserve is checked again against the sender's current balance.
+    /// A payment to a receiver requiring acceptance is escrowed rather than
+    /// credited and moves on to `PendingAcceptance`.
+    ///
+    /// # Arguments
+    /// * `sender_new_nonce` - Nonce for the sender balance encrypted to their key
+    /// * `receiver_new_nonce` - Nonce for the receiver balance encrypted to their key
+    /// * `escrow_nonce` - Nonce for the MXE-encrypted escrowed amount
+    pub fn approve_large_transfer(
+        ctx: Context<ApproveLargeTransfer>,
+        computation_offset: u64,
+        _transaction_id: u64,
+        sender_new_nonce: u128,
+        receiver_new_nonce: u128,
+        escrow_nonce: u128,
+    ) -> Result<()> {
+        let sender = &ctx.accounts.sender_account;
+        let receiver = &ctx.accounts.receiver_account;
+        require!(
+            sender.co_signer == Some(ctx.accounts.payer.key()),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            sender.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            receiver.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.status == TransactionStatus::PendingApproval,
+            ErrorCode::InvalidTransactionStatus
+        );
+        transaction.status = TransactionStatus::Processing;
+        transaction.computation_offset = computation_offset;
+        let currency_id = transaction.currency_id;
+
+        let now = Clock::get()?.unix_timestamp;
+        let utc_offset = sender.utc_offset_seconds;
+        let reset_spent_today =
+            spend_day(sender.last_spend_reset, utc_offset) != spend_day(now, utc_offset);
+
+        let mut args = Vec::new();
+        args.extend(sender.mxe_balance_arguments(sender.key(), currency_id)?);
+        args.extend(receiver.mxe_balance_arguments(receiver.key(), currency_id)?);
+        args.extend([
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(transaction.amount_nonce),
+            Argument::Account(transaction.key(), 8 + 8 + 32 + 32, 32),
+        ]);
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Payment));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+        args.extend(sender.min_reserve_arguments(sender.key(), currency_id));
+        args.extend([
+            Argument::PlaintextU128(sender.daily_limit_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_DAILY_LIMIT_OFFSET, 32),
+            Argument::PlaintextBool(sender.has_daily_limit),
+            Argument::PlaintextU128(sender.spent_today_nonce),
+            Argument::Account(sender.key(), USER_ACCOUNT_SPENT_TODAY_OFFSET, 32),
+            Argument::PlaintextBool(reset_spent_today),
+            Argument::PlaintextBool(transaction.requires_acceptance),
+            Argument::PlaintextU128(escrow_nonce),
+            Argument::ArcisPubkey(sender.owner_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+            Argument::ArcisPubkey(receiver.owner_enc_pubkey),
+            Argument::PlaintextU128(receiver_new_nonce),
+        ]);
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ApproveLargeTransferCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.transaction.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.treasury.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "approve_large_transfer")]
+    pub fn approve_large_transfer_callback(
+        ctx: Context<ApproveLargeTransferCallback>,
+        output: ComputationOutputs<ApproveLargeTransferOutput>,
+    ) -> Result<()> {
+        let (
+            sender_mxe_balance,
+            sender_balance,
+            receiver_mxe_balance,
+            receiver_balance,
+            treasury,
+            new_spent_today,
+            escrowed_amount,
+            sufficient,
+            limit_exceeded,
+        ) = match output {
+            ComputationOutputs::Success(ApproveLargeTransferOutput {
+                field_0: ApproveLargeTransferOutputStruct0 {
+                    field_0: sender_mxe_bal,
+                    field_1: sender_bal,
+                    field_2: receiver_mxe_bal,
+                    field_3: receiver_bal,
+                    field_4: treasury,
+                    field_5: spent_today,
+                    field_6: escrowed,
+                    field_7: sufficient,
+                    field_8: limit_exceeded,
+                },
+            }) => (
+                sender_mxe_bal,
+                sender_bal,
+                receiver_mxe_bal,
+                receiver_bal,
+                treasury,
+                spent_today,
+                escrowed,
+                sufficient,
+                limit_exceeded,
+            ),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                ctx.accounts.receiver_account.end_computation();
+                ctx.accounts.treasury.end_computation();
+                // Still held, so the co-signer can approve again
+                ctx.accounts.transaction.status = TransactionStatus::PendingApproval;
+                return computation_aborted(
+                    "approve_large_transfer",
+                    ctx.accounts.sender_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.sender_account.end_computation();
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.treasury.end_computation();
+
+        if !sufficient {
+            ctx.accounts.transaction.status = TransactionStatus::Failed;
+            emit!(PaymentFailedEvent {
+                transaction_id: ctx.accounts.transaction.transaction_id,
+                computation_offset: ctx.accounts.transaction.computation_offset,
+                reason: "Insufficient balance".to_string(),
+            });
+            return Ok(());
+        }
+
+        // As in process_payment, the spend counter is stored even over the limit
+        // so a new day's reset sticks
+        let now = Clock::get()?.unix_timestamp;
+        let sender_account = &mut ctx.accounts.sender_account;
+        sender_account.encrypted_spent_today = new_spent_today.ciphertexts[0];
+        sender_account.spent_today_nonce = new_spent_today.nonce;
+        let utc_offset = sender_account.utc_offset_seconds;
+        if spend_day(sender_account.last_spend_reset, utc_offset) != spend_day(now, utc_offset) {
+            sender_account.last_spend_reset = now;
+        }
+
+        if limit_exceeded {
+            // Still held, so the co-signer can approve it on a later day
+            ctx.accounts.transaction.status = TransactionStatus::PendingApproval;
+            emit!(SpendLimitExceededEvent {
+                transaction_id: ctx.accounts.transaction.transaction_id,
+                computation_offset: ctx.accounts.transaction.computation_offset,
+                account_id: ctx.accounts.sender_account.account_id,
+                timestamp: now,
+            });
+            return Ok(());
+        }
+
+        ctx.accounts.treasury.store_total((treasury.ciphertexts[0], treasury.nonce));
+
+        let currency_id = ctx.accounts.transaction.currency_id;
+        let sender_account = &mut ctx.accounts.sender_account;
+        sender_account.store_balance(
+            currency_id,
+            (sender_mxe_balance.ciphertexts[0], sender_mxe_balance.nonce),
+            (sender_balance.ciphertexts[0], sender_balance.nonce),
+        )?;
+        sender_account.transaction_count = sender_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // A receiver requiring acceptance is credited by `accept_payment` instead
+        let transaction = &mut ctx.accounts.transaction;
+        if transaction.requires_acceptance {
+            transaction.encrypted_escrow = escrowed_amount.ciphertexts[0];
+            transaction.escrow_nonce = escrowed_amount.nonce;
+            transaction.status = TransactionStatus::PendingAcceptance;
+            emit!(PaymentPendingAcceptanceEvent {
+                transaction_id: transaction.transaction_id,
+                computation_offset: transaction.computation_offset,
+                sender: transaction.sender,
+                receiver: transaction.receiver,
+                timestamp: now,
+            });
+            return Ok(());
+        }
+
+        let receiver_account = &mut ctx.accounts.receiver_account;
+        receiver_account.store_balance(
+            currency_id,
+            (receiver_mxe_balance.ciphertexts[0], receiver_mxe_balance.nonce),
+            (receiver_balance.ciphertexts[0], receiver_balance.nonce),
+        )?;
+        receiver_account.transaction_count = receiver_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.status = TransactionStatus::Completed;
+
+        emit!(LargeTransferApprovedEvent {
+            transaction_id: transaction.transaction_id,
+            computation_offset: transaction.computation_offset,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    pub fn init_set_spending_cap_comp_def(
+        ctx: Context<InitSetSpendingCapCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_delegated_payment_comp_def(
+        ctx: Context<InitDelegatedPaymentCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Lets `delegate` spend up to an encrypted cap from the owner's account.
+    ///
+    /// The cap is encrypted to the owner's key client-side and re-encrypted for the
+    /// MXE; the delegation only becomes usable once the callback stores it. Granting
+    /// again to the same delegate replaces the remaining cap.
+    ///
+    /// # Arguments
+    /// * `delegate` - Pubkey allowed to sign delegated payments
+    /// * `cap_ciphertext` - Spending cap encrypted to the owner's key
+    /// * `cap_nonce` - Nonce used to encrypt `cap_ciphertext`
+    /// * `mxe_nonce` - Nonce for the MXE-encrypted cap
+    pub fn grant_spending_delegate(
+        ctx: Context<GrantSpendingDelegate>,
+        computation_offset: u64,
+        _account_id: u64,
+        delegate: Pubkey,
+        cap_ciphertext: [u8; 32],
+        cap_nonce: u128,
+        mxe_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.user_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+
+        let spending_delegate = &mut ctx.accounts.spending_delegate;
+        spending_delegate.bump = ctx.bumps.spending_delegate;
+        spending_delegate.user_account = ctx.accounts.user_account.key();
+        spending_delegate.delegate = delegate;
+        spending_delegate.active = false;
+
+        let args = vec![
+            Argument::ArcisPubkey(ctx.accounts.user_account.owner_enc_pubkey),
+            Argument::PlaintextU128(cap_nonce),
+            Argument::EncryptedU64(cap_ciphertext),
+            Argument::PlaintextU128(mxe_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SetSpendingCapCallback::callback_ix(&[CallbackAccount {
+                pubkey: ctx.accounts.spending_delegate.key(),
+                is_writable: true,
+            }])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "set_spending_cap")]
+    pub fn set_spending_cap_callback(
+        ctx: Context<SetSpendingCapCallback>,
+        output: ComputationOutputs<SetSpendingCapOutput>,
+    ) -> Result<()> {
+        let cap = match output {
+            ComputationOutputs::Success(SetSpendingCapOutput { field_0: cap }) => cap,
+            _ => return computation_aborted("set_spending_cap", 0),
+        };
+
+        let spending_delegate = &mut ctx.accounts.spending_delegate;
+        spending_delegate.encrypted_cap = cap.ciphertexts[0];
+        spending_delegate.cap_nonce = cap.nonce;
+        spending_delegate.active = true;
+
+        emit!(SpendingDelegateGrantedEvent {
+            user_account: spending_delegate.user_account,
+            delegate: spending_delegate.delegate,
+        });
+        Ok(())
+    }
+
+    /// Revokes a spending delegation, closing its account.
+    ///
+    /// A delegated payment still queued when this lands fails in its callback.
+    pub fn revoke_spending_delegate(ctx: Context<RevokeSpendingDelegate>) -> Result<()> {
+        emit!(SpendingDelegateRevokedEvent {
+            user_account: ctx.accounts.user_account.key(),
+            delegate: ctx.accounts.spending_delegate.delegate,
+        });
+        Ok(())
+    }
+
+    /// Pays from the owner's account on behalf of the signing delegate.
+    ///
+    /// Works like `process_payment`, but the debit must also fit the delegate's
+    /// remaining encrypted cap, which is decremented by it in the same computation.
+    /// The payment fee goes to the treasury.
+    /// Both accounts must share a base currency, and a sender with a minimum
+    /// reserve, a daily limit or a co-signer is refused, as is a receiver that
+    /// requires acceptance.
+    pub fn delegated_payment(
+        ctx: Context<DelegatedPayment>,
+        computation_offset: u64,
+        transaction_id: u64,
+        amount: u64,
+        receiver_new_nonce: u128,
+        amount_nonce: u128,
+        sender_new_nonce: u128,
+    ) -> Result<()> {
+        require!(!ctx.accounts.program_config.paused, ErrorCode::ProgramPaused);
+        require!(
+            ctx.accounts.sender_account.key() != ctx.accounts.receiver_account.key(),
+            ErrorCode::SelfTransfer
+        );
+        require!(
+            ctx.accounts.spending_delegate.active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.sender_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.receiver_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            ctx.accounts.receiver_account.base_currency_id
+                == ctx.accounts.sender_account.base_currency_id,
+            ErrorCode::CurrencyNotSupported
+        );
+        require!(
+            !ctx.accounts.sender_account.has_min_reserve
+                && !ctx.accounts.sender_account.has_daily_limit
+                && ctx.accounts.sender_account.co_signer.is_none(),
+            ErrorCode::UnsupportedAccountSettings
+        );
+        require!(
+            !ctx.accounts.receiver_account.require_acceptance,
+            ErrorCode::UnsupportedAccountSettings
+        );
+
+        // A freshly created transaction PDA is zeroed; a used one records its sender
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.sender == Pubkey::default(),
+            ErrorCode::DuplicateTransactionId
+        );
+        transaction.bump = ctx.bumps.transaction;
+        transaction.transaction_id = transaction_id;
+        transaction.sender = ctx.accounts.sender_account.key();
+        transaction.receiver = ctx.accounts.receiver_account.key();
+        transaction.encrypted_amount = [0; 32];
+        transaction.amount_nonce = 0;
+        transaction.timestamp = Clock::get()?.unix_timestamp;
+        transaction.status = TransactionStatus::Processing;
+        transaction.aml_recorded = 0;
+        transaction.currency_id = ctx.accounts.sender_account.base_currency_id;
+        transaction.computation_offset = computation_offset;
+
+        let mut args = vec![
+            Argument::PlaintextU128(ctx.accounts.sender_account.mxe_balance_nonce),
+            Argument::Account(
+                ctx.accounts.sender_account.key(),
+                USER_ACCOUNT_MXE_BALANCE_OFFSET,
+                32,
+            ),
+            Argument::PlaintextU128(ctx.accounts.receiver_account.mxe_balance_nonce),
+            Argument::Account(
+                ctx.accounts.receiver_account.key(),
+                USER_ACCOUNT_MXE_BALANCE_OFFSET,
+                32,
+            ),
+            Argument::PlaintextU128(ctx.accounts.spending_delegate.cap_nonce),
+            Argument::Account(ctx.accounts.spending_delegate.key(), 8 + 32 + 32, 32),
+            Argument::PlaintextU64(amount),
+        ];
+        args.extend(fee_arguments(&ctx.accounts.program_config, FeeOperation::Payment));
+        args.extend(ctx.accounts.treasury.total_arguments(ctx.accounts.treasury.key()));
+        args.extend([
+            Argument::ArcisPubkey(ctx.accounts.receiver_account.owner_enc_pubkey),
+            Argument::PlaintextU128(receiver_new_nonce),
+            Argument::ArcisPubkey(ctx.accounts.sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(amount_nonce),
+            Argument::ArcisPubkey(ctx.accounts.sender_account.owner_enc_pubkey),
+            Argument::PlaintextU128(sender_new_nonce),
+        ]);
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        ctx.accounts.treasury.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![DelegatedPaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.transaction.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.spending_delegate.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.treasury.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "delegated_payment")]
+    pub fn delegated_payment_callback(
+        ctx: Context<DelegatedPaymentCallback>,
+        output: ComputationOutputs<DelegatedPaymentOutput>,
+    ) -> Result<()> {
+        let (
+            new_sender_balance,
+            new_sender_client_balance,
+            new_receiver_mxe_balance,
+            new_receiver_balance,
+            encrypted_amount,
+            new_cap,
+            new_treasury,
+            is_sufficient,
+            within_cap,
+        ) = match output {
+            ComputationOutputs::Success(DelegatedPaymentOutput {
+                field_0: DelegatedPaymentOutputStruct0 {
+                    field_0: sender_bal,
+                    field_1: sender_client_bal,
+                    field_2: receiver_mxe_bal,
+                    field_3: receiver_bal,
+                    field_4: amount,
+                    field_5: cap,
+                    field_6: treasury,
+                    field_7: sufficient,
+                    field_8: within_cap,
+                },
+            }) => (
+                sender_bal,
+                sender_client_bal,
+                receiver_mxe_bal,
+                receiver_bal,
+                amount,
+                cap,
+                treasury,
+                sufficient,
+                within_cap,
+            ),
+            _ => {
+                ctx.accounts.sender_account.end_computation();
+                ctx.accounts.receiver_account.end_computation();
+                ctx.accounts.treasury.end_computation();
+                ctx.accounts.transaction.status = TransactionStatus::Failed;
+                return computation_aborted(
+                    "delegated_payment",
+                    ctx.accounts.sender_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.sender_account.end_computation();
+        ctx.accounts.receiver_account.end_computation();
+        ctx.accounts.treasury.end_computation();
+
+        if !within_cap {
+            ctx.accounts.transaction.status = TransactionStatus::Failed;
+            emit!(PaymentFailedEvent {
+                transaction_id: ctx.accounts.transaction.transaction_id,
+                computation_offset: ctx.accounts.transaction.computation_offset,
+                reason: "Spending cap exceeded".to_string(),
+            });
+            return Ok(());
+        }
+        if !is_sufficient {
+            ctx.accounts.transaction.status = TransactionStatus::Failed;
+            emit!(PaymentFailedEvent {
+                transaction_id: ctx.accounts.transaction.transaction_id,
+                computation_offset: ctx.accounts.transaction.computation_offset,
+                reason: "Insufficient balance".to_string(),
+            });
+            return Ok(());
+        }
+
+        let sender_account = &mut ctx.accounts.sender_account;
+        let prev_balance_nonce = sender_account.balance_nonce;
+        sender_account.mxe_encrypted_balance = new_sender_balance.ciphertexts[0];
+        sender_account.mxe_balance_nonce = new_sender_balance.nonce;
+        sender_account.encrypted_balance = new_sender_client_balance.ciphertexts[0];
+        sender_account.balance_nonce = new_sender_client_balance.nonce;
+        sender_account.transaction_count = sender_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let receiver_account = &mut ctx.accounts.receiver_account;
+        receiver_account.mxe_encrypted_balance = new_receiver_mxe_balance.ciphertexts[0];
+        receiver_account.mxe_balance_nonce = new_receiver_mxe_balance.nonce;
+        receiver_account.encrypted_balance = new_receiver_balance.ciphertexts[0];
+        receiver_account.balance_nonce = new_receiver_balance.nonce;
+        receiver_account.transaction_count = receiver_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let spending_delegate = &mut ctx.accounts.spending_delegate;
+        spending_delegate.encrypted_cap = new_cap.ciphertexts[0];
+        spending_delegate.cap_nonce = new_cap.nonce;
+
+        ctx.accounts.treasury.store_total((new_treasury.ciphertexts[0], new_treasury.nonce));
+
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.encrypted_amount = encrypted_amount.ciphertexts[0];
+        transaction.amount_nonce = encrypted_amount.nonce;
+        transaction.status = TransactionStatus::Completed;
+
+        emit!(PaymentProcessedEvent {
+            transaction_id: ctx.accounts.transaction.transaction_id,
+            computation_offset: ctx.accounts.transaction.computation_offset,
+            sender: ctx.accounts.transaction.sender,
+            receiver: ctx.accounts.transaction.receiver,
+            timestamp: ctx.accounts.transaction.timestamp,
+            sender_new_balance: new_sender_client_balance.ciphertexts[0],
+            sender_new_balance_nonce: new_sender_client_balance.nonce,
+            prev_balance_nonce,
+        });
+        Ok(())
+    }
+
+    /// Creates the program-wide configuration, making the payer its admin.
+    ///
+    /// Only the program's upgrade authority may call it, so nobody can claim the
+    /// admin role ahead of the deployer.
+    ///
+    /// # Arguments
+    /// * `fee_schedule` - Fees charged per operation type (all zero for free operations)
+    pub fn init_program_config(
+        ctx: Context<InitProgramConfig>,
+        fee_schedule: FeeSchedule,
+    ) -> Result<()> {
+        fee_schedule.validate()?;
+
+        let config = &mut ctx.accounts.program_config;
+        config.bump = ctx.bumps.program_config;
+        config.admin = ctx.accounts.admin.key();
+        config.fee_schedule = fee_schedule;
+        config.points_per_unit = DEFAULT_POINTS_PER_UNIT;
+        config.reward_config = RewardConfig::default();
+        config.max_initial_balance = 0;
+        config.interest_period = SECONDS_PER_DAY;
+        config.paused = false;
+        config.seizure_authority = ctx.accounts.admin.key();
+        config.dormancy_threshold = DEFAULT_DORMANCY_THRESHOLD;
+        config.points_expiry_period = DEFAULT_POINTS_EXPIRY_PERIOD;
+        config.max_in_flight = DEFAULT_MAX_IN_FLIGHT;
+        config.deposit_mint = Pubkey::default();
+        config.aml_window = DEFAULT_AML_WINDOW;
+        config.aml_threshold = DEFAULT_AML_THRESHOLD;
+        Ok(())
+    }
+
+    /// Initializes the program config and a batch of computation definitions.
+    ///
+    /// The comp def accounts are passed as remaining accounts, in the same order
+    /// as `comp_def_offsets`, at most `MAX_BOOTSTRAP_COMP_DEFS` per call so that
+    /// each call fits a legacy transaction and the default compute budget; a
+    /// deployment sends as many calls as it takes to cover every circuit.
+    /// Idempotent: pieces that already exist are skipped, so re-running it on a
+    /// partially initialized deployment completes the rest. An existing config is
+    /// left untouched. Comp defs created here still need their Arcium finalize
+    /// transaction, as with the individual `init_*_comp_def` instructions.
+    /// Creating the config makes the payer admin, which only the program's
+    /// upgrade authority may become.
+    ///
+    /// # Arguments
+    /// * `comp_def_offsets` - Offset of each circuit whose comp def is passed
+    pub fn bootstrap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Bootstrap<'info>>,
+        comp_def_offsets: Vec<u32>,
+    ) -> Result<()> {
+        require!(
+            comp_def_offsets.len() <= MAX_BOOTSTRAP_COMP_DEFS
+                && comp_def_offsets.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidAccountCount
+        );
+        let config = &mut ctx.accounts.program_config;
+        if config.admin == Pubkey::default() {
+            require!(
+                ctx.accounts.program_data.upgrade_authority_address
+                    == Some(ctx.accounts.payer.key()),
+                ErrorCode::Unauthorized
+            );
+            config.bump = ctx.bumps.program_config;
+            config.admin = ctx.accounts.payer.key();
+            config.fee_schedule = FeeSchedule::default();
+            config.points_per_unit = DEFAULT_POINTS_PER_UNIT;
+            config.reward_config = RewardConfig::default();
+            config.max_initial_balance = 0;
+            config.interest_period = SECONDS_PER_DAY;
+            config.paused = false;
+            config.seizure_authority = ctx.accounts.payer.key();
+            config.dormancy_threshold = DEFAULT_DORMANCY_THRESHOLD;
+            config.points_expiry_period = DEFAULT_POINTS_EXPIRY_PERIOD;
+            config.max_in_flight = DEFAULT_MAX_IN_FLIGHT;
+            config.deposit_mint = Pubkey::default();
+            config.aml_window = DEFAULT_AML_WINDOW;
+            config.aml_threshold = DEFAULT_AML_THRESHOLD;
+        }
+
+        let accounts = &mut ctx.accounts;
+        for (offset, info) in comp_def_offsets.iter().zip(ctx.remaining_accounts.iter()) {
+            require_keys_eq!(
+                info.key(),
+                derive_comp_def_pda!(*offset),
+                ErrorCode::InvalidAccountCount
+            );
+            // Skipped when a previous run already created it
+            if !info.data_is_empty() {
+                continue;
+            }
+            match *offset {
+                COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS => {
+                    bootstrap_comp_def!(accounts, info, InitInitializeAccountsCompDef)
+                }
+                COMP_DEF_OFFSET_PROCESS_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitProcessPaymentCompDef)
+                }
+                COMP_DEF_OFFSET_CHECK_BALANCE => {
+                    bootstrap_comp_def!(accounts, info, InitCheckBalanceCompDef)
+                }
+                COMP_DEF_OFFSET_REVEAL_BALANCE_TO_OWNER => {
+                    bootstrap_comp_def!(accounts, info, InitRevealBalanceToOwnerCompDef)
+                }
+                COMP_DEF_OFFSET_CHECK_BALANCE_RANGE => {
+                    bootstrap_comp_def!(accounts, info, InitCheckBalanceRangeCompDef)
+                }
+                COMP_DEF_OFFSET_CALCULATE_REWARDS => {
+                    bootstrap_comp_def!(accounts, info, InitCalculateRewardsCompDef)
+                }
+                COMP_DEF_OFFSET_CHECK_PREMIUM_ELIGIBILITY => {
+                    bootstrap_comp_def!(accounts, info, InitCheckPremiumEligibilityCompDef)
+                }
+                COMP_DEF_OFFSET_COMPUTE_AGGREGATE_LOYALTY => {
+                    bootstrap_comp_def!(accounts, info, InitComputeAggregateLoyaltyCompDef)
+                }
+                COMP_DEF_OFFSET_CHECK_AML_VELOCITY => {
+                    bootstrap_comp_def!(accounts, info, InitCheckAmlVelocityCompDef)
+                }
+                COMP_DEF_OFFSET_WITHDRAW => {
+                    bootstrap_comp_def!(accounts, info, InitWithdrawCompDef)
+                }
+                COMP_DEF_OFFSET_DEPOSIT => bootstrap_comp_def!(accounts, info, InitDepositCompDef),
+                COMP_DEF_OFFSET_REDEEM_REWARDS => {
+                    bootstrap_comp_def!(accounts, info, InitRedeemRewardsCompDef)
+                }
+                COMP_DEF_OFFSET_ACCRUE_INTEREST => {
+                    bootstrap_comp_def!(accounts, info, InitAccrueInterestCompDef)
+                }
+                COMP_DEF_OFFSET_SET_SPENDING_CAP => {
+                    bootstrap_comp_def!(accounts, info, InitSetSpendingCapCompDef)
+                }
+                COMP_DEF_OFFSET_DELEGATED_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitDelegatedPaymentCompDef)
+                }
+                COMP_DEF_OFFSET_BATCH_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitBatchPaymentCompDef)
+                }
+                COMP_DEF_OFFSET_PROCESS_PAYMENT_WITH_FEE => {
+                    bootstrap_comp_def!(accounts, info, InitProcessPaymentWithFeeCompDef)
+                }
+                COMP_DEF_OFFSET_OPEN_CREDIT_LINE => {
+                    bootstrap_comp_def!(accounts, info, InitOpenCreditLineCompDef)
+                }
+                COMP_DEF_OFFSET_PROCESS_PAYMENT_SIGNED => {
+                    bootstrap_comp_def!(accounts, info, InitProcessPaymentSignedCompDef)
+                }
+                COMP_DEF_OFFSET_REVEAL_AVAILABLE_CREDIT => {
+                    bootstrap_comp_def!(accounts, info, InitRevealAvailableCreditCompDef)
+                }
+                COMP_DEF_OFFSET_OPEN_CURRENCY_BALANCE => {
+                    bootstrap_comp_def!(accounts, info, InitOpenCurrencyBalanceCompDef)
+                }
+                COMP_DEF_OFFSET_REFUND_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitRefundPaymentCompDef)
+                }
+                COMP_DEF_OFFSET_COMPARE_BALANCES => {
+                    bootstrap_comp_def!(accounts, info, InitCompareBalancesCompDef)
+                }
+                COMP_DEF_OFFSET_SWEEP_BALANCE => {
+                    bootstrap_comp_def!(accounts, info, InitSweepBalanceCompDef)
+                }
+                COMP_DEF_OFFSET_SCHEDULE_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitSchedulePaymentCompDef)
+                }
+                COMP_DEF_OFFSET_RELEASE_SCHEDULED_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitReleaseScheduledPaymentCompDef)
+                }
+                COMP_DEF_OFFSET_SET_LARGE_TRANSFER_THRESHOLD => {
+                    bootstrap_comp_def!(accounts, info, InitSetLargeTransferThresholdCompDef)
+                }
+                COMP_DEF_OFFSET_APPROVE_LARGE_TRANSFER => {
+                    bootstrap_comp_def!(accounts, info, InitApproveLargeTransferCompDef)
+                }
+                COMP_DEF_OFFSET_AGGREGATE_BALANCES => {
+                    bootstrap_comp_def!(accounts, info, InitAggregateBalancesCompDef)
+                }
+                COMP_DEF_OFFSET_REVEAL_TREASURY => {
+                    bootstrap_comp_def!(accounts, info, InitRevealTreasuryCompDef)
+                }
+                COMP_DEF_OFFSET_SEIZE_FUNDS => {
+                    bootstrap_comp_def!(accounts, info, InitSeizeFundsCompDef)
+                }
+                COMP_DEF_OFFSET_SET_MIN_RESERVE => {
+                    bootstrap_comp_def!(accounts, info, InitSetMinReserveCompDef)
+                }
+                COMP_DEF_OFFSET_RELEASE_ESCROWED_PAYMENT => {
+                    bootstrap_comp_def!(accounts, info, InitReleaseEscrowedPaymentCompDef)
+                }
+                COMP_DEF_OFFSET_PROCESS_PAYMENT_MULTI_SOURCE => {
+                    bootstrap_comp_def!(accounts, info, InitProcessPaymentMultiSourceCompDef)
+                }
+                COMP_DEF_OFFSET_PROVE_PAYMENT_RECEIVED => {
+                    bootstrap_comp_def!(accounts, info, InitProvePaymentReceivedCompDef)
+                }
+                COMP_DEF_OFFSET_REVEAL_TRANSACTION_AMOUNT => {
+                    bootstrap_comp_def!(accounts, info, InitRevealTransactionAmountCompDef)
+                }
+                COMP_DEF_OFFSET_PING => bootstrap_comp_def!(accounts, info, InitPingCompDef),
+                COMP_DEF_OFFSET_RECOVER_ACCOUNT => {
+                    bootstrap_comp_def!(accounts, info, InitRecoverAccountCompDef)
+                }
+                COMP_DEF_OFFSET_ROTATE_ENCRYPTION_KEY => {
+                    bootstrap_comp_def!(accounts, info, InitRotateEncryptionKeyCompDef)
+                }
+                COMP_DEF_OFFSET_SET_DAILY_LIMIT => {
+                    bootstrap_comp_def!(accounts, info, InitSetDailyLimitCompDef)
+                }
+                COMP_DEF_OFFSET_CLOSE_ACCOUNT => {
+                    bootstrap_comp_def!(accounts, info, InitCloseAccountCompDef)
+                }
+                _ => return err!(ErrorCode::UnknownComputationDefinition),
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the fee schedule. Admin only.
+    pub fn update_fee_schedule(
+        ctx: Context<UpdateProgramConfig>,
+        fee_schedule: FeeSchedule,
+    ) -> Result<()> {
+        fee_schedule.validate()?;
+        ctx.accounts.program_config.fee_schedule = fee_schedule;
+
+        emit!(FeeScheduleUpdatedEvent { fee_schedule });
+        Ok(())
+    }
+
+    /// Sets how many reward points redeem for one unit of balance. Admin only.
+    pub fn update_redemption_rate(
+        ctx: Context<UpdateProgramConfig>,
+        points_per_unit: u64,
+    ) -> Result<()> {
+        require!(points_per_unit > 0, ErrorCode::InvalidRedemptionAmount);
+        ctx.accounts.program_config.points_per_unit = points_per_unit;
+        Ok(())
+    }
+
+    /// Replaces the reward rate and balance tier thresholds used by
+    /// `calculate_rewards`. Admin only.
+    pub fn update_reward_config(
+        ctx: Context<UpdateProgramConfig>,
+        reward_config: RewardConfig,
+    ) -> Result<()> {
+        reward_config.validate()?;
+        ctx.accounts.program_config.reward_config = reward_config;
+
+        emit!(RewardConfigUpdatedEvent { reward_config });
+        Ok(())
+    }
+
+    /// Sets the largest balance an account may be opened with. Zero means new
+    /// accounts start empty and are funded through deposits. Admin only.
+    pub fn update_max_initial_balance(
+        ctx: Context<UpdateProgramConfig>,
+        max_initial_balance: u64,
+    ) -> Result<()> {
+        ctx.accounts.program_config.max_initial_balance = max_initial_balance;
+        Ok(())
+    }
+
+    /// Sets who may sign `seize_funds`. Admin only.
+    pub fn update_seizure_authority(
+        ctx: Context<UpdateProgramConfig>,
+        seizure_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.program_config.seizure_authority = seizure_authority;
+        Ok(())
+    }
+
+    /// Sets or clears the emergency stop. Admin only.
+    ///
+    /// Only queuing is refused while paused: computations already queued
+    /// still reach their callbacks and settle normally.
+    pub fn set_paused(ctx: Context<UpdateProgramConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.program_config.paused = paused;
+        emit!(ProgramPausedEvent {
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets the token mint `deposit_from_token` accepts. Admin only.
+    pub fn update_deposit_mint(
+        ctx: Context<UpdateProgramConfig>,
+        deposit_mint: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.program_config.deposit_mint = deposit_mint;
+        Ok(())
+    }
+
+    /// Sets how many seconds an account must go without activity before
+    /// `mark_dormant` can freeze it. Admin only.
+    pub fn update_dormancy_threshold(
+        ctx: Context<UpdateProgramConfig>,
+        dormancy_threshold: i64,
+    ) -> Result<()> {
+        require!(dormancy_threshold > 0, ErrorCode::InvalidDormancyThreshold);
+        ctx.accounts.program_config.dormancy_threshold = dormancy_threshold;
+        Ok(())
+    }
+
+    /// Sets the AML velocity window and the gross throughput within it above which
+    /// `check_aml_velocity` flags an account. Admin only.
+    pub fn update_aml_settings(
+        ctx: Context<UpdateProgramConfig>,
+        aml_window: i64,
+        aml_threshold: u64,
+    ) -> Result<()> {
+        require!(aml_window > 0, ErrorCode::InvalidAmlWindow);
+        let config = &mut ctx.accounts.program_config;
+        config.aml_window = aml_window;
+        config.aml_threshold = aml_threshold;
+        Ok(())
+    }
+
+    /// Sets how many seconds reward points stay redeemable after they were last
+    /// added to. Admin only.
+    pub fn update_points_expiry_period(
+        ctx: Context<UpdateProgramConfig>,
+        points_expiry_period: i64,
+    ) -> Result<()> {
+        require!(points_expiry_period > 0, ErrorCode::InvalidPointsExpiryPeriod);
+        ctx.accounts.program_config.points_expiry_period = points_expiry_period;
+        Ok(())
+    }
+
+    /// Sets how many balance-rewriting computations an account may have queued
+    /// at once. Admin only.
+    ///
+    /// The treasury and validator reward pools are held to the same limit, and
+    /// every fee-collecting computation rewrites them, so above one a callback
+    /// can overwrite fees collected by another.
+    pub fn update_max_in_flight(
+        ctx: Context<UpdateProgramConfig>,
+        max_in_flight: u8,
+    ) -> Result<()> {
+        require!(max_in_flight > 0, ErrorCode::InvalidMaxInFlight);
+        ctx.accounts.program_config.max_in_flight = max_in_flight;
+        Ok(())
+    }
+
+    /// Sets the length in seconds of one `accrue_interest` period. Admin only.
+    pub fn update_interest_period(
+        ctx: Context<UpdateProgramConfig>,
+        interest_period: i64,
+    ) -> Result<()> {
+        require!(interest_period > 0, ErrorCode::InvalidInterestPeriod);
+        ctx.accounts.program_config.interest_period = interest_period;
+        Ok(())
+    }
+
+    /// Opens a session for a user account, or re-opens one that has lapsed.
+    ///
+    /// Sensitive operations require an active session: one whose `last_active_ts`
+    /// is no older than `idle_timeout` seconds. Each gated operation refreshes the
+    /// session; once it lapses only `open_session` brings it back.
+    ///
+    /// # Arguments
+    /// * `idle_timeout` - Seconds of inactivity after which the session expires
+    pub fn open_session(ctx: Context<OpenSession>, idle_timeout: i64) -> Result<()> {
+        require!(idle_timeout > 0, ErrorCode::InvalidIdleTimeout);
+
+        let session = &mut ctx.accounts.session_account;
+        session.bump = ctx.bumps.session_account;
+        session.user_account = ctx.accounts.user_account.key();
+        session.idle_timeout = idle_timeout;
+        session.last_active_ts = Clock::get()?.unix_timestamp;
+
+        emit!(SessionRefreshedEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            last_active_ts: session.last_active_ts,
+        });
+        Ok(())
+    }
+
+    /// Extends a session that is still active, signed by the account owner.
+    /// An expired session is refused with `SessionExpired`.
+    pub fn refresh_session(ctx: Context<RefreshSession>) -> Result<()> {
+        let session = &mut ctx.accounts.session_account;
+        session.touch(Clock::get()?.unix_timestamp)?;
+
+        emit!(SessionRefreshedEvent {
+            account_id: ctx.accounts.user_account.account_id,
+            last_active_ts: session.last_active_ts,
+        });
+        Ok(())
+    }
+
+    /// Freezes an active account, blocking payments, balance checks and rewards.
+    ///
+    /// Outgoing authorizations are suspended with it: scheduled payments from the
+    /// account cannot execute, and its spending delegate and co-signer cannot
+    /// move funds, until the account is unfrozen.
+    ///
+    /// Signed by the account owner or the program admin.
+    pub fn freeze_account(ctx: Context<SetAccountFrozen>, _account_id: u64) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.set_state(AccountState::Frozen)?;
+
+        emit!(AccountFrozenEvent {
+            account_id: user_account.account_id,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Returns a frozen account to `Active`.
+    ///
+    /// Signed by the account owner or the program admin.
+    pub fn unfreeze_account(ctx: Context<SetAccountFrozen>, _account_id: u64) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.set_state(AccountState::Active)?;
+        // Otherwise a dormant account would be frozen again straight away
+        user_account.last_activity_ts = Clock::get()?.unix_timestamp;
+
+        emit!(AccountUnfrozenEvent {
+            account_id: user_account.account_id,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Freezes an active account that has seen no payment, deposit or withdrawal
+    /// for at least `dormancy_threshold` seconds. Admin only.
+    ///
+    /// The owner or admin brings it back with `unfreeze_account`.
+    pub fn mark_dormant(ctx: Context<MarkDormant>, _account_id: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        require!(
+            now.saturating_sub(user_account.last_activity_ts)
+                >= ctx.accounts.program_config.dormancy_threshold,
+            ErrorCode::AccountNotDormant
+        );
+        user_account.set_state(AccountState::Frozen)?;
+
+        emit!(AccountDormantEvent {
+            account_id: user_account.account_id,
+            last_activity_ts: user_account.last_activity_ts,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Emits an account's public state. Anyone may call this; it reads no
+    /// encrypted data.
+    pub fn get_account_status(ctx: Context<GetAccountStatus>, _account_id: u64) -> Result<()> {
+        let user_account = &ctx.accounts.user_account;
+        emit!(AccountStatusEvent {
+            account_id: user_account.account_id,
+            state: user_account.account_state,
+            transaction_count: user_account.transaction_count,
+            reward_points: user_account.reward_points,
+        });
+        Ok(())
+    }
+
+    /// Sets the offset from UTC at which the account's day starts.
+    ///
+    /// The daily spend counter resets at local midnight, so a payment made after
+    /// it counts against a fresh limit. Signed by the account owner.
+    ///
+    /// # Arguments
+    /// * `utc_offset_seconds` - Seconds east of UTC, within ±14 hours
+    pub fn set_utc_offset(
+        ctx: Context<SetUtcOffset>,
+        _account_id: u64,
+        utc_offset_seconds: i64,
+    ) -> Result<()> {
+        require!(
+            utc_offset_seconds.abs() <= MAX_UTC_OFFSET_SECONDS,
+            ErrorCode::InvalidUtcOffset
+        );
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.utc_offset_seconds = utc_offset_seconds;
+
+        emit!(UtcOffsetSetEvent {
+            account_id: user_account.account_id,
+            utc_offset_seconds,
+        });
+        Ok(())
+    }
+
+    /// Replaces the account's encrypted label. Signed by the account owner.
+    ///
+    /// # Arguments
+    /// * `label_ciphertext` - New label encrypted by the owner's client
+    /// * `label_nonce` - Nonce used for `label_ciphertext`
+    pub fn set_label(
+        ctx: Context<SetLabel>,
+        _account_id: u64,
+        label_ciphertext: [u8; 32],
+        label_nonce: u128,
+    ) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.encrypted_label = label_ciphertext;
+        user_account.label_nonce = label_nonce;
+
+        emit!(LabelSetEvent {
+            account_id: user_account.account_id,
+            label_nonce,
+        });
+        Ok(())
+    }
+
+    /// Sets whether incoming `process_payment` transfers wait in escrow until
+    /// this account accepts them. Signed by the account owner.
+    pub fn set_require_acceptance(
+        ctx: Context<SetRequireAcceptance>,
+        _account_id: u64,
+        require_acceptance: bool,
+    ) -> Result<()> {
+        ctx.accounts.user_account.require_acceptance = require_acceptance;
+        Ok(())
+    }
+
+    pub fn init_release_escrowed_payment_comp_def(
+        ctx: Context<InitReleaseEscrowedPaymentCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Credits a payment in `PendingAcceptance` to the receiver. Signed by the
+    /// receiver's owner.
+    ///
+    /// # Arguments
+    /// * `receiver_new_nonce` - Nonce for the receiver balance encrypted to their key
+    pub fn accept_payment(
+        ctx: Context<SettlePendingPayment>,
+        computation_offset: u64,
+        _transaction_id: u64,
+        receiver_new_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.receiver_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.receiver_account.account_state == AccountState::Active,
+            ErrorCode::InvalidAccountState
+        );
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.status == TransactionStatus::PendingAcceptance,
+            ErrorCode::InvalidTransactionStatus
+        );
+        transaction.status = TransactionStatus::Processing;
+        transaction.computation_offset = computation_offset;
+
+        let args = release_escrowed_payment_arguments(
+            transaction,
+            &ctx.accounts.receiver_account,
+            receiver_new_nonce,
+        )?;
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.receiver_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ReleaseEscrowedPaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.transaction.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.receiver_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    /// Returns a payment in `PendingAcceptance` to its sender. Signed by the
+    /// receiver's owner.
+    ///
+    /// The escrowed amount goes back in full; the fee and any tip were collected
+    /// when the payment was sent and are kept.
+    ///
+    /// # Arguments
+    /// * `sender_new_nonce` - Nonce for the sender balance encrypted to their key
+    pub fn reject_payment(
+        ctx: Context<SettlePendingPayment>,
+        computation_offset: u64,
+        _transaction_id: u64,
+        sender_new_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.receiver_account.owner_pubkey == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.status == TransactionStatus::PendingAcceptance,
+            ErrorCode::InvalidTransactionStatus
+        );
+        transaction.status = TransactionStatus::Processing;
+        transaction.computation_offset = computation_offset;
+
+        let args = release_escrowed_payment_arguments(
+            transaction,
+            &ctx.accounts.sender_account,
+            sender_new_nonce,
+        )?;
+
+        let max_in_flight = ctx.accounts.program_config.max_in_flight;
+        ctx.accounts.sender_account.begin_computation(max_in_flight)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ReleaseEscrowedPaymentCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.transaction.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.sender_account.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "release_escrowed_payment")]
+    pub fn release_escrowed_payment_callback(
+        ctx: Context<ReleaseEscrowedPaymentCallback>,
+        output: ComputationOutputs<ReleaseEscrowedPaymentOutput>,
+    ) -> Result<()> {
+        let (mxe_balance, client_balance, fits) = match output {
+            ComputationOutputs::Success(ReleaseEscrowedPaymentOutput {
+                field_0: ReleaseEscrowedPaymentOutputStruct0 {
+                    field_0: mxe_bal,
+                    field_1: client_bal,
+                    field_2: fits,
+                },
+            }) => (mxe_bal, client_bal, fits),
+            _ => {
+                ctx.accounts.beneficiary_account.end_computation();
+                // Still escrowed, so the receiver can decide again
+                ctx.accounts.transaction.status = TransactionStatus::PendingAcceptance;
+                return computation_aborted(
+                    "release_escrowed_payment",
+                    ctx.accounts.beneficiary_account.account_id,
+                )
+            }
+        };
+
+        ctx.accounts.beneficiary_account.end_computation();
+
+        // `accept_payment` credits the receiver, `reject_payment` the sender
+        let accepted = ctx.accounts.beneficiary_account.key() == ctx.accounts.transaction.receiver;
+        let transaction = &mut ctx.accounts.transaction;
+        if !fits {
+            // Still escrowed, so the receiver can decide again
+            transaction.status = TransactionStatus::PendingAcceptance;
+            emit!(PaymentFailedEvent {
+                transaction_id: transaction.transaction_id,
+                computation_offset: transaction.computation_offset,
+                reason: "Balance would overflow".to_string(),
+            });
+            return Ok(());
+        }
+
+        let beneficiary = &mut ctx.accounts.beneficiary_account;
+        beneficiary.store_balance(
+            transaction.currency_id,
+            (mxe_balance.ciphertexts[0], mxe_balance.nonce),
+            (client_balance.ciphertexts[0], client_balance.nonce),
+        )?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        if accepted {
+            beneficiary.transaction_count = beneficiary
+                .transaction_count
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+            transaction.status = TransactionStatus::Completed;
+            emit!(PaymentAcceptedEvent {
+                transaction_id: transaction.transaction_id,
+                computation_offset: transaction.computation_offset,
+                timestamp,
+            });
+        } else {
+            transaction.status = TransactionStatus::Rejected;
+            emit!(PaymentRejectedEvent {
+                transaction_id: transaction.transaction_id,
+                computation_offset: transaction.computation_offset,
+                timestamp,
+            });
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Initialize Accounts
+// ============================================================================
+
+#[queue_computation_accounts("initialize_accounts", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct InitializeUserAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserTxIndex::INIT_SPACE,
+        seeds = [USER_TX_INDEX_SEED, payer.key().as_ref()],
+        bump,
+    )]
+    pub tx_index: Account<'info, UserTxIndex>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("initialize_accounts")]
+#[derive(Accounts)]
+pub struct InitializeAccountsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[queue_computation_accounts("initialize_accounts", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct RetryInitialization<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("initialize_accounts", payer)]
+#[derive(Accounts)]
+pub struct InitInitializeAccountsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Process Payment
+// ============================================================================
+
+#[queue_computation_accounts("process_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64)]
+pub struct ProcessPayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Trusted only once `Account` has checked it is owned by this program and
+    /// the seeds below show it is the canonical PDA its `account_id` names.
+    /// Spending also requires `sender_enc_pubkey` to match the stored key.
+    #[account(
+        mut,
+        seeds = [b"user_account", sender_account.account_id.to_le_bytes().as_ref()],
+        bump = sender_account.bump,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    /// Owned by this program and at its canonical PDA, like `sender_account`,
+    /// so a client cannot substitute an account whose data it wrote itself.
+    #[account(
+        mut,
+        seeds = [b"user_account", receiver_account.account_id.to_le_bytes().as_ref()],
+        bump = receiver_account.bump,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [USER_TX_INDEX_SEED, sender_account.owner_pubkey.as_ref()],
+        bump = sender_tx_index.bump,
+    )]
+    pub sender_tx_index: Account<'info, UserTxIndex>,
+    #[account(
+        mut,
+        seeds = [USER_TX_INDEX_SEED, receiver_account.owner_pubkey.as_ref()],
+        bump = receiver_tx_index.bump,
+    )]
+    pub receiver_tx_index: Account<'info, UserTxIndex>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Transaction::INIT_SPACE,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ValidatorRewards::INIT_SPACE,
+        seeds = [VALIDATOR_REWARDS_SEED],
+        bump,
+    )]
+    pub validator_rewards: Account<'info, ValidatorRewards>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_payment")]
+#[derive(Accounts)]
+pub struct ProcessPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction.transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        address = transaction.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [VALIDATOR_REWARDS_SEED],
+        bump = validator_rewards.bump,
+    )]
+    pub validator_rewards: Account<'info, ValidatorRewards>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("process_payment", payer)]
+#[derive(Accounts)]
+pub struct InitProcessPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Batch Payment
+// ============================================================================
+
+#[queue_computation_accounts("batch_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct BatchPayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_BATCH_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(mut)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("batch_payment")]
+#[derive(Accounts)]
+pub struct BatchPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_BATCH_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", sender_account.account_id.to_le_bytes().as_ref()],
+        bump = sender_account.bump,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("batch_payment", payer)]
+#[derive(Accounts)]
+pub struct InitBatchPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Multi-Source Payment
+// ============================================================================
+
+#[queue_computation_accounts("process_payment_multi_source", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ProcessPaymentMultiSource<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT_MULTI_SOURCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", receiver_account.account_id.to_le_bytes().as_ref()],
+        bump = receiver_account.bump,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("process_payment_multi_source")]
+#[derive(Accounts)]
+pub struct ProcessPaymentMultiSourceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT_MULTI_SOURCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", receiver_account.account_id.to_le_bytes().as_ref()],
+        bump = receiver_account.bump,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("process_payment_multi_source", payer)]
+#[derive(Accounts)]
+pub struct InitProcessPaymentMultiSourceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Payment With Fee
+// ============================================================================
+
+#[queue_computation_accounts("process_payment_with_fee", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ProcessPaymentWithFee<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT_WITH_FEE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(mut)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub fee_collector: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("process_payment_with_fee")]
+#[derive(Accounts)]
+pub struct ProcessPaymentWithFeeCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT_WITH_FEE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", sender_account.account_id.to_le_bytes().as_ref()],
+        bump = sender_account.bump,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", receiver_account.account_id.to_le_bytes().as_ref()],
+        bump = receiver_account.bump,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", fee_collector.account_id.to_le_bytes().as_ref()],
+        bump = fee_collector.bump,
+    )]
+    pub fee_collector: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("process_payment_with_fee", payer)]
+#[derive(Accounts)]
+pub struct InitProcessPaymentWithFeeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Credit Line
+// ============================================================================
+
+#[queue_computation_accounts("open_credit_line", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct OpenCreditLine<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_OPEN_CREDIT_LINE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("open_credit_line")]
+#[derive(Accounts)]
+pub struct OpenCreditLineCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_OPEN_CREDIT_LINE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("open_credit_line", payer)]
+#[derive(Accounts)]
+pub struct InitOpenCreditLineCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("process_payment_signed", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ProcessPaymentSigned<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT_SIGNED)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(mut)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("process_payment_signed")]
+#[derive(Accounts)]
+pub struct ProcessPaymentSignedCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT_SIGNED)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", sender_account.account_id.to_le_bytes().as_ref()],
+        bump = sender_account.bump,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", receiver_account.account_id.to_le_bytes().as_ref()],
+        bump = receiver_account.bump,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("process_payment_signed", payer)]
+#[derive(Accounts)]
+pub struct InitProcessPaymentSignedCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("reveal_available_credit", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct RevealAvailableCredit<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_AVAILABLE_CREDIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("reveal_available_credit")]
+#[derive(Accounts)]
+pub struct RevealAvailableCreditCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_AVAILABLE_CREDIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("reveal_available_credit", payer)]
+#[derive(Accounts)]
+pub struct InitRevealAvailableCreditCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Currencies
+// ============================================================================
+
+#[queue_computation_accounts("open_currency_balance", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct AddCurrency<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_OPEN_CURRENCY_BALANCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("open_currency_balance")]
+#[derive(Accounts)]
+pub struct OpenCurrencyBalanceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_OPEN_CURRENCY_BALANCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("open_currency_balance", payer)]
+#[derive(Accounts)]
+pub struct InitOpenCurrencyBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Refunds
+// ============================================================================
+
+#[queue_computation_accounts("refund_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64, refund_transaction_id: u64)]
+pub struct RefundTransaction<'info> {
+    /// The original payment's receiver, who sends the refund, or the admin
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = original_transaction.bump,
+    )]
+    pub original_transaction: Account<'info, Transaction>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Transaction::INIT_SPACE,
+        seeds = [b"transaction", refund_transaction_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub refund_transaction: Account<'info, Transaction>,
+    /// Receiver of the original payment
+    #[account(
+        mut,
+        address = original_transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    /// Sender of the original payment
+    #[account(
+        mut,
+        address = original_transaction.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [USER_TX_INDEX_SEED, receiver_account.owner_pubkey.as_ref()],
+        bump = receiver_tx_index.bump,
+    )]
+    pub receiver_tx_index: Account<'info, UserTxIndex>,
+    #[account(
+        mut,
+        seeds = [USER_TX_INDEX_SEED, sender_account.owner_pubkey.as_ref()],
+        bump = sender_tx_index.bump,
+    )]
+    pub sender_tx_index: Account<'info, UserTxIndex>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REFUND_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("refund_payment")]
+#[derive(Accounts)]
+pub struct RefundPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REFUND_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"transaction", original_transaction.transaction_id.to_le_bytes().as_ref()],
+        bump = original_transaction.bump,
+    )]
+    pub original_transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        seeds = [b"transaction", refund_transaction.transaction_id.to_le_bytes().as_ref()],
+        bump = refund_transaction.bump,
+        constraint = refund_transaction.refunded_transaction_id
+            == Some(original_transaction.transaction_id),
+    )]
+    pub refund_transaction: Account<'info, Transaction>,
+    /// Receiver of the original payment, sending the refund
+    #[account(
+        mut,
+        address = refund_transaction.sender,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = refund_transaction.receiver,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("refund_payment", payer)]
+#[derive(Accounts)]
+pub struct InitRefundPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Disputes
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(transaction_id: u64)]
+pub struct FlagTransaction<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        address = transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(transaction_id: u64)]
+pub struct ResolveDispute<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Seizure
+// ============================================================================
+
+#[queue_computation_accounts("seize_funds", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct SeizeFunds<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SEIZE_FUNDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.seizure_authority == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("seize_funds")]
+#[derive(Accounts)]
+pub struct SeizeFundsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SEIZE_FUNDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("seize_funds", payer)]
+#[derive(Accounts)]
+pub struct InitSeizeFundsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Sweep
+// ============================================================================
+
+#[queue_computation_accounts("sweep_balance", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SweepBalance<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SWEEP_BALANCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", source_account.account_id.to_le_bytes().as_ref()],
+        bump = source_account.bump,
+    )]
+    pub source_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", destination_account.account_id.to_le_bytes().as_ref()],
+        bump = destination_account.bump,
+    )]
+    pub destination_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("sweep_balance")]
+#[derive(Accounts)]
+pub struct SweepBalanceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SWEEP_BALANCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", source_account.account_id.to_le_bytes().as_ref()],
+        bump = source_account.bump,
+    )]
+    pub source_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", destination_account.account_id.to_le_bytes().as_ref()],
+        bump = destination_account.bump,
+    )]
+    pub destination_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("sweep_balance", payer)]
+#[derive(Accounts)]
+pub struct InitSweepBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Close Account
+// ============================================================================
+
+#[queue_computation_accounts("close_account", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CloseAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLOSE_ACCOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", source_account.account_id.to_le_bytes().as_ref()],
+        bump = source_account.bump,
+    )]
+    pub source_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", destination_account.account_id.to_le_bytes().as_ref()],
+        bump = destination_account.bump,
+    )]
+    pub destination_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("close_account")]
+#[derive(Accounts)]
+pub struct CloseAccountCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLOSE_ACCOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", source_account.account_id.to_le_bytes().as_ref()],
+        bump = source_account.bump,
+    )]
+    pub source_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_account", destination_account.account_id.to_le_bytes().as_ref()],
+        bump = destination_account.bump,
+    )]
+    pub destination_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("close_account", payer)]
+#[derive(Accounts)]
+pub struct InitCloseAccountCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Scheduled Payments
+// ============================================================================
+
+#[queue_computation_accounts("schedule_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, schedule_id: u64)]
+pub struct SchedulePayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SCHEDULE_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ScheduledPayment::INIT_SPACE,
+        seeds = [SCHEDULED_PAYMENT_SEED, schedule_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub scheduled_payment: Account<'info, ScheduledPayment>,
+    #[account(
+        mut,
+        seeds = [b"user_account", sender_account.account_id.to_le_bytes().as_ref()],
+        bump = sender_account.bump,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [b"user_account", receiver_account.account_id.to_le_bytes().as_ref()],
+        bump = receiver_account.bump,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("schedule_payment")]
+#[derive(Accounts)]
+pub struct SchedulePaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SCHEDULE_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [SCHEDULED_PAYMENT_SEED, scheduled_payment.schedule_id.to_le_bytes().as_ref()],
+        bump = scheduled_payment.bump,
+    )]
+    pub scheduled_payment: Account<'info, ScheduledPayment>,
+    #[account(
+        mut,
+        address = scheduled_payment.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+}
+
+#[queue_computation_accounts("release_scheduled_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, schedule_id: u64)]
+pub struct ExecuteScheduledPayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RELEASE_SCHEDULED_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [SCHEDULED_PAYMENT_SEED, schedule_id.to_le_bytes().as_ref()],
+        bump = scheduled_payment.bump,
+    )]
+    pub scheduled_payment: Account<'info, ScheduledPayment>,
+    #[account(
+        mut,
+        address = scheduled_payment.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = scheduled_payment.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[queue_computation_accounts("release_scheduled_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, schedule_id: u64)]
+pub struct CancelScheduledPayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RELEASE_SCHEDULED_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [SCHEDULED_PAYMENT_SEED, schedule_id.to_le_bytes().as_ref()],
+        bump = scheduled_payment.bump,
+    )]
+    pub scheduled_payment: Account<'info, ScheduledPayment>,
+    #[account(
+        mut,
+        address = scheduled_payment.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = scheduled_payment.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("release_scheduled_payment")]
+#[derive(Accounts)]
+pub struct ReleaseScheduledPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RELEASE_SCHEDULED_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [SCHEDULED_PAYMENT_SEED, scheduled_payment.schedule_id.to_le_bytes().as_ref()],
+        bump = scheduled_payment.bump,
+    )]
+    pub scheduled_payment: Account<'info, ScheduledPayment>,
+    /// Receiver when executing, sender when cancelling
+    #[account(
+        mut,
+        address = scheduled_payment.beneficiary(),
+    )]
+    pub beneficiary_account: Account<'info, UserAccount>,
+    /// Sender when executing, receiver when cancelling
+    #[account(
+        mut,
+        address = scheduled_payment.counterparty(),
+    )]
+    pub counterparty_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("schedule_payment", payer)]
+#[derive(Accounts)]
+pub struct InitSchedulePaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("release_scheduled_payment", payer)]
+#[derive(Accounts)]
+pub struct InitReleaseScheduledPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Withdraw
+// ============================================================================
+
+#[queue_computation_accounts("withdraw", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_WITHDRAW)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("withdraw")]
+#[derive(Accounts)]
+pub struct WithdrawCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_WITHDRAW)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("withdraw", payer)]
+#[derive(Accounts)]
+pub struct InitWithdrawCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Deposit
+// ============================================================================
+
+#[queue_computation_accounts("deposit", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[queue_computation_accounts("deposit", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct DepositFromToken<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.deposit_mint == mint.key() @ ErrorCode::InvalidMint,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = payer,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    /// Holds every deposited token of `mint`; owned by its own PDA
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [TOKEN_VAULT_SEED, mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = token_vault,
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PendingDeposit::INIT_SPACE,
+        seeds = [PENDING_DEPOSIT_SEED, computation_offset.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub pending_deposit: Account<'info, PendingDeposit>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ReclaimDeposit<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        close = owner,
+        seeds = [PENDING_DEPOSIT_SEED, computation_offset.to_le_bytes().as_ref()],
+        bump = pending_deposit.bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        has_one = mint @ ErrorCode::InvalidMint,
+    )]
+    pub pending_deposit: Account<'info, PendingDeposit>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = owner,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, mint.key().as_ref()],
+        bump,
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[callback_accounts("deposit")]
+#[derive(Accounts)]
+pub struct DepositCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("deposit", payer)]
+#[derive(Accounts)]
+pub struct InitDepositCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Check Balance
+// ============================================================================
+
+#[queue_computation_accounts("check_balance", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct CheckBalance<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_BALANCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("check_balance")]
+#[derive(Accounts)]
+pub struct CheckBalanceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_BALANCE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    /// Checked against the account id passed back in the output
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("check_balance", payer)]
+#[derive(Accounts)]
+pub struct InitCheckBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Reveal Balance
+// ============================================================================
+
+#[queue_computation_accounts("reveal_balance_to_owner", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct RevealBalanceToOwner<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_BALANCE_TO_OWNER)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("reveal_balance_to_owner")]
+#[derive(Accounts)]
+pub struct RevealBalanceToOwnerCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_BALANCE_TO_OWNER)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("reveal_balance_to_owner", payer)]
+#[derive(Accounts)]
+pub struct InitRevealBalanceToOwnerCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Balance Range
+// ============================================================================
+
+#[queue_computation_accounts("check_balance_range", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct CheckBalanceRange<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_BALANCE_RANGE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("check_balance_range")]
+#[derive(Accounts)]
+pub struct CheckBalanceRangeCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_BALANCE_RANGE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("check_balance_range", payer)]
+#[derive(Accounts)]
+pub struct InitCheckBalanceRangeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Balance Comparison
+// ============================================================================
+
+#[queue_computation_accounts("compare_balances", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, first_account_id: u64, second_account_id: u64)]
+pub struct CompareBalances<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPARE_BALANCES)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        seeds = [b"user_account", first_account_id.to_le_bytes().as_ref()],
+        bump = first_account.bump,
+    )]
+    pub first_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [b"user_account", second_account_id.to_le_bytes().as_ref()],
+        bump = second_account.bump,
+    )]
+    pub second_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("compare_balances")]
+#[derive(Accounts)]
+pub struct CompareBalancesCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPARE_BALANCES)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        seeds = [b"user_account", first_account.account_id.to_le_bytes().as_ref()],
+        bump = first_account.bump,
+    )]
+    pub first_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [b"user_account", second_account.account_id.to_le_bytes().as_ref()],
+        bump = second_account.bump,
+    )]
+    pub second_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("compare_balances", payer)]
+#[derive(Accounts)]
+pub struct InitCompareBalancesCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Treasury
+// ============================================================================
+
+#[queue_computation_accounts("reveal_treasury", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RevealTreasury<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_TREASURY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    /// Created empty if no fee has been collected yet
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("reveal_treasury")]
+#[derive(Accounts)]
+pub struct RevealTreasuryCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_TREASURY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[init_computation_definition_accounts("reveal_treasury", payer)]
+#[derive(Accounts)]
+pub struct InitRevealTreasuryCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Balance Aggregation
+// ============================================================================
+
+#[queue_computation_accounts("aggregate_balances", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct AggregateBalances<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_AGGREGATE_BALANCES)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("aggregate_balances")]
+#[derive(Accounts)]
+pub struct AggregateBalancesCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_AGGREGATE_BALANCES)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[init_computation_definition_accounts("aggregate_balances", payer)]
+#[derive(Accounts)]
+pub struct InitAggregateBalancesCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Calculate Rewards
+// ============================================================================
+
+#[queue_computation_accounts("calculate_rewards", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct CalculateRewards<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_REWARDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("calculate_rewards")]
+#[derive(Accounts)]
+pub struct CalculateRewardsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_REWARDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    /// Checked against the account id passed back in the output
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[init_computation_definition_accounts("calculate_rewards", payer)]
+#[derive(Accounts)]
+pub struct InitCalculateRewardsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Redeem Rewards
+// ============================================================================
+
+#[queue_computation_accounts("redeem_rewards", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct RedeemRewards<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REDEEM_REWARDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("redeem_rewards")]
+#[derive(Accounts)]
+pub struct RedeemRewardsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REDEEM_REWARDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("redeem_rewards", payer)]
+#[derive(Accounts)]
+pub struct InitRedeemRewardsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Interest Accrual
+// ============================================================================
+
+#[queue_computation_accounts("accrue_interest", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct AccrueInterest<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ACCRUE_INTEREST)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("accrue_interest")]
+#[derive(Accounts)]
+pub struct AccrueInterestCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ACCRUE_INTEREST)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("accrue_interest", payer)]
+#[derive(Accounts)]
+pub struct InitAccrueInterestCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Premium Eligibility
+// ============================================================================
+
+#[queue_computation_accounts("check_premium_eligibility", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct CheckPremiumEligibility<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_PREMIUM_ELIGIBILITY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+}
+
+#[callback_accounts("check_premium_eligibility")]
+#[derive(Accounts)]
+pub struct CheckPremiumEligibilityCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_PREMIUM_ELIGIBILITY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("check_premium_eligibility", payer)]
+#[derive(Accounts)]
+pub struct InitCheckPremiumEligibilityCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Aggregate Loyalty
+// ============================================================================
+
+#[queue_computation_accounts("compute_aggregate_loyalty", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ComputeAggregateLoyalty<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_AGGREGATE_LOYALTY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("compute_aggregate_loyalty")]
+#[derive(Accounts)]
+pub struct ComputeAggregateLoyaltyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_AGGREGATE_LOYALTY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    /// Checked against the account id passed back in the output
+    #[account(
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("compute_aggregate_loyalty", payer)]
+#[derive(Accounts)]
+pub struct InitComputeAggregateLoyaltyCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - AML Velocity
+// ============================================================================
+
+#[queue_computation_accounts("check_aml_velocity", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CheckAmlVelocity<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_AML_VELOCITY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        constraint = program_config.admin == payer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub transaction: Account<'info, Transaction>,
+    #[account(address = transaction.sender)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AmlTracker::INIT_SPACE,
+        seeds = [b"aml_tracker", user_account.key().as_ref()],
+        bump,
+    )]
+    pub aml_tracker: Account<'info, AmlTracker>,
+}
+
+#[callback_accounts("check_aml_velocity")]
+#[derive(Accounts)]
+pub struct CheckAmlVelocityCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_AML_VELOCITY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"aml_tracker", user_account.key().as_ref()],
+        bump = aml_tracker.bump,
+    )]
+    pub aml_tracker: Account<'info, AmlTracker>,
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("check_aml_velocity", payer)]
+#[derive(Accounts)]
+pub struct InitCheckAmlVelocityCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Payment Receipts
+// ============================================================================
+
+#[queue_computation_accounts("prove_payment_received", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64)]
+pub struct ProvePaymentReceived<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROVE_PAYMENT_RECEIVED)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(address = transaction.sender)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(address = transaction.receiver)]
+    pub receiver_account: Account<'info, UserAccount>,
+    /// Session of whichever party signs; checked in the handler
+    #[account(mut)]
+    pub session_account: Account<'info, SessionAccount>,
+}
+
+#[callback_accounts("prove_payment_received")]
+#[derive(Accounts)]
+pub struct ProvePaymentReceivedCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROVE_PAYMENT_RECEIVED)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub transaction: Account<'info, Transaction>,
+}
+
+#[init_computation_definition_accounts("prove_payment_received", payer)]
+#[derive(Accounts)]
+pub struct InitProvePaymentReceivedCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Transaction Amount Reveal
+// ============================================================================
+
+#[queue_computation_accounts("reveal_transaction_amount", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64)]
+pub struct RevealTransactionAmount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_TRANSACTION_AMOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(address = transaction.sender)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(address = transaction.receiver)]
+    pub receiver_account: Account<'info, UserAccount>,
+    /// Session of whichever party signs; checked in the handler
+    #[account(mut)]
+    pub session_account: Account<'info, SessionAccount>,
+}
+
+#[callback_accounts("reveal_transaction_amount")]
+#[derive(Accounts)]
+pub struct RevealTransactionAmountCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_TRANSACTION_AMOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub transaction: Account<'info, Transaction>,
+    /// Sender or receiver the amount was re-encrypted to
+    pub party_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("reveal_transaction_amount", payer)]
+#[derive(Accounts)]
+pub struct InitRevealTransactionAmountCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Health Check
+// ============================================================================
+
+#[queue_computation_accounts("ping", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct Ping<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PING)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("ping")]
+#[derive(Accounts)]
+pub struct PingCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PING)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[init_computation_definition_accounts("ping", payer)]
+#[derive(Accounts)]
+pub struct InitPingCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Guardian Recovery
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        constraint = user_account.owner_pubkey == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Guardians::INIT_SPACE,
+        seeds = [b"guardians", user_account.key().as_ref()],
+        bump,
+    )]
+    pub guardians: Account<'info, Guardians>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    pub guardian: Signer<'info>,
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"guardians", user_account.key().as_ref()],
+        bump = guardians.bump,
+    )]
+    pub guardians: Account<'info, Guardians>,
+}
+
+#[queue_computation_accounts("recover_account", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct RecoverAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECOVER_ACCOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"guardians", user_account.key().as_ref()],
+        bump = guardians.bump,
+    )]
+    pub guardians: Account<'info, Guardians>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("recover_account")]
+#[derive(Accounts)]
+pub struct RecoverAccountCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECOVER_ACCOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"guardians", user_account.key().as_ref()],
+        bump = guardians.bump,
+    )]
+    pub guardians: Account<'info, Guardians>,
+}
+
+#[init_computation_definition_accounts("recover_account", payer)]
+#[derive(Accounts)]
+pub struct InitRecoverAccountCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Key Rotation
+// ============================================================================
+
+#[queue_computation_accounts("rotate_encryption_key", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct RotateEncryptionKey<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ROTATE_ENCRYPTION_KEY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("rotate_encryption_key")]
+#[derive(Accounts)]
+pub struct RotateEncryptionKeyCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ROTATE_ENCRYPTION_KEY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("rotate_encryption_key", payer)]
+#[derive(Accounts)]
+pub struct InitRotateEncryptionKeyCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Daily Spend Limit
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct ClearDailyLimit<'info> {
+    #[account(
+        constraint = owner.key() == user_account.owner_pubkey @ ErrorCode::Unauthorized,
+    )]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[queue_computation_accounts("set_daily_limit", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct SetDailyLimit<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_DAILY_LIMIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("set_daily_limit")]
+#[derive(Accounts)]
+pub struct SetDailyLimitCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_DAILY_LIMIT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("set_daily_limit", payer)]
+#[derive(Accounts)]
+pub struct InitSetDailyLimitCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Minimum Reserve
+// ============================================================================
+
+#[queue_computation_accounts("set_min_reserve", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct SetMinReserve<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_MIN_RESERVE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("set_min_reserve")]
+#[derive(Accounts)]
+pub struct SetMinReserveCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_MIN_RESERVE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("set_min_reserve", payer)]
+#[derive(Accounts)]
+pub struct InitSetMinReserveCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Large Transfer Approval
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct RemoveCoSigner<'info> {
+    #[account(
+        constraint = owner.key() == user_account.owner_pubkey @ ErrorCode::Unauthorized,
+    )]
+    pub owner: Signer<'info>,
+    #[account(
+        constraint = user_account.co_signer == Some(co_signer.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub co_signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[queue_computation_accounts("set_large_transfer_threshold", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64)]
+pub struct SetCoSigner<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_LARGE_TRANSFER_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[callback_accounts("set_large_transfer_threshold")]
+#[derive(Accounts)]
+pub struct SetLargeTransferThresholdCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_LARGE_TRANSFER_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", user_account.account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("set_large_transfer_threshold", payer)]
+#[derive(Accounts)]
+pub struct InitSetLargeTransferThresholdCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("approve_large_transfer", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64)]
+pub struct ApproveLargeTransfer<'info> {
+    /// The sender's co-signer
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_APPROVE_LARGE_TRANSFER)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        address = transaction.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("approve_large_transfer")]
+#[derive(Accounts)]
+pub struct ApproveLargeTransferCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_APPROVE_LARGE_TRANSFER)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction.transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        address = transaction.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("approve_large_transfer", payer)]
+#[derive(Accounts)]
+pub struct InitApproveLargeTransferCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Spending Delegation
+// ============================================================================
+
+#[queue_computation_accounts("set_spending_cap", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, account_id: u64, delegate: Pubkey)]
+pub struct GrantSpendingDelegate<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_SPENDING_CAP)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + SpendingDelegate::INIT_SPACE,
+        seeds = [b"spending_delegate", user_account.key().as_ref(), delegate.as_ref()],
+        bump,
+    )]
+    pub spending_delegate: Account<'info, SpendingDelegate>,
+}
+
+#[callback_accounts("set_spending_cap")]
+#[derive(Accounts)]
+pub struct SetSpendingCapCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_SPENDING_CAP)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"spending_delegate",
+            spending_delegate.user_account.as_ref(),
+            spending_delegate.delegate.as_ref(),
+        ],
+        bump = spending_delegate.bump,
+    )]
+    pub spending_delegate: Account<'info, SpendingDelegate>,
+}
+
+#[init_computation_definition_accounts("set_spending_cap", payer)]
+#[derive(Accounts)]
+pub struct InitSetSpendingCapCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSpendingDelegate<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        constraint = user_account.owner_pubkey == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"spending_delegate",
+            user_account.key().as_ref(),
+            spending_delegate.delegate.as_ref(),
+        ],
+        bump = spending_delegate.bump,
+    )]
+    pub spending_delegate: Account<'info, SpendingDelegate>,
+}
+
+#[queue_computation_accounts("delegated_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64)]
+pub struct DelegatedPayment<'info> {
+    /// The delegate spending from `sender_account`
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [b"spending_delegate", sender_account.key().as_ref(), payer.key().as_ref()],
+        bump = spending_delegate.bump,
+    )]
+    pub spending_delegate: Account<'info, SpendingDelegate>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Transaction::INIT_SPACE,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DELEGATED_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[callback_accounts("delegated_payment")]
+#[derive(Accounts)]
+pub struct DelegatedPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_DELEGATED_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction.transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        address = transaction.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [
+            b"spending_delegate",
+            sender_account.key().as_ref(),
+            spending_delegate.delegate.as_ref(),
+        ],
+        bump = spending_delegate.bump,
+    )]
+    pub spending_delegate: Account<'info, SpendingDelegate>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[init_computation_definition_accounts("delegated_payment", payer)]
+#[derive(Accounts)]
+pub struct InitDelegatedPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Program Config
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct InitProgramConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Ibank>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Bootstrap<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Ibank>,
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProgramConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Sessions
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct OpenSession<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        constraint = user_account.owner_pubkey == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SessionAccount::INIT_SPACE,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshSession<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        constraint = user_account.owner_pubkey == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"session", user_account.key().as_ref()],
+        bump = session_account.bump,
+    )]
+    pub session_account: Account<'info, SessionAccount>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Account Freezing
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct SetAccountFrozen<'info> {
+    #[account(
+        constraint = authority.key() == user_account.owner_pubkey
+            || authority.key() == program_config.admin @ ErrorCode::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Dormancy
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct MarkDormant<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Account Status
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct GetAccountStatus<'info> {
+    #[account(
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Timezone
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct SetUtcOffset<'info> {
+    #[account(
+        constraint = owner.key() == user_account.owner_pubkey @ ErrorCode::Unauthorized,
+    )]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Labels
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct SetLabel<'info> {
+    #[account(
+        constraint = owner.key() == user_account.owner_pubkey @ ErrorCode::Unauthorized,
+    )]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+// ============================================================================
+// ACCOUNT CONTEXTS - Payment Acceptance
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(account_id: u64)]
+pub struct SetRequireAcceptance<'info> {
+    #[account(
+        constraint = owner.key() == user_account.owner_pubkey @ ErrorCode::Unauthorized,
+    )]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[queue_computation_accounts("release_escrowed_payment", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, transaction_id: u64)]
+pub struct SettlePendingPayment<'info> {
+    /// The receiver's owner
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = SIGN_PDA_SPACE,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
     pub mxe_account: Account<'info, MXEAccount>,
     #[account(
         mut,
@@ -368,7 +12267,7 @@ pub struct InitializeUserAccount<'info> {
     /// CHECK: computation_account, checked by the arcium program.
     pub computation_account: UncheckedAccount<'info>,
     #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS)
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RELEASE_ESCROWED_PAYMENT)
     )]
     pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
     #[account(
@@ -388,450 +12287,1429 @@ pub struct InitializeUserAccount<'info> {
     pub system_program: Program<'info, System>,
     pub arcium_program: Program<'info, Arcium>,
     #[account(
-        init,
-        payer = payer,
-        space = 8 + UserAccount::INIT_SPACE,
-        seeds = [b"user_account", account_id.to_le_bytes().as_ref()],
-        bump,
+        mut,
+        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub transaction: Account<'info, Transaction>,
+    #[account(
+        mut,
+        address = transaction.sender,
+    )]
+    pub sender_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        address = transaction.receiver,
+    )]
+    pub receiver_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = program_config.bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[callback_accounts("release_escrowed_payment")]
+#[derive(Accounts)]
+pub struct ReleaseEscrowedPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RELEASE_ESCROWED_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"transaction", transaction.transaction_id.to_le_bytes().as_ref()],
+        bump = transaction.bump,
+    )]
+    pub transaction: Account<'info, Transaction>,
+    /// The receiver when accepting, the sender when rejecting
+    #[account(
+        mut,
+        constraint = beneficiary_account.key() == transaction.receiver
+            || beneficiary_account.key() == transaction.sender,
+    )]
+    pub beneficiary_account: Account<'info, UserAccount>,
+}
+
+#[init_computation_definition_accounts("release_escrowed_payment", payer)]
+#[derive(Accounts)]
+pub struct InitReleaseEscrowedPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(mut)]
+    /// CHECK: Checked by Arcium program
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============================================================================
+// DATA STRUCTURES
+// ============================================================================
+#[account]
+#[derive(InitSpace)]
+pub struct UserAccount {
+    /// Unique account identifier
+    pub account_id: u64,
+    /// Owner's Solana public key
+    pub owner_pubkey: Pubkey,
+    /// Balance encrypted to the owner's key (32 bytes ciphertext), for the client
+    pub encrypted_balance: [u8; 32],
+    /// Nonce for balance encryption
+    pub balance_nonce: u128,
+    /// Balance encrypted under the MXE key, read by every balance circuit.
+    /// Located at `USER_ACCOUNT_MXE_BALANCE_OFFSET`.
+    pub mxe_encrypted_balance: [u8; 32],
+    /// Nonce for the MXE-encrypted balance
+    pub mxe_balance_nonce: u128,
+    /// Daily spend limit (MXE-encrypted), at `USER_ACCOUNT_DAILY_LIMIT_OFFSET`
+    pub encrypted_daily_limit: [u8; 32],
+    /// Nonce for the daily limit encryption
+    pub daily_limit_nonce: u128,
+    /// Amount sent on the day of `last_spend_reset` (MXE-encrypted), at
+    /// `USER_ACCOUNT_SPENT_TODAY_OFFSET`
+    pub encrypted_spent_today: [u8; 32],
+    /// Nonce for the spent-today encryption
+    pub spent_today_nonce: u128,
+    /// Overdraft allowance used by `process_payment_signed`
+    pub credit_line: CreditLine,
+    /// Balances in currencies other than `base_currency_id`, at
+    /// `USER_ACCOUNT_CURRENCIES_OFFSET`; the first `currency_count` are in use
+    pub currencies: [CurrencyBalance; MAX_CURRENCIES],
+    /// Amount above which a payment waits for `co_signer` (MXE-encrypted), at
+    /// `USER_ACCOUNT_LARGE_TRANSFER_THRESHOLD_OFFSET`
+    pub encrypted_large_transfer_threshold: [u8; 32],
+    /// Nonce for the large-transfer threshold encryption
+    pub large_transfer_threshold_nonce: u128,
+    /// Base-currency balance payments may not spend (MXE-encrypted), at
+    /// `USER_ACCOUNT_MIN_RESERVE_OFFSET`
+    pub encrypted_min_reserve: [u8; 32],
+    /// Nonce for the minimum reserve encryption
+    pub min_reserve_nonce: u128,
+    /// Number of `currencies` slots added by `add_currency`
+    pub currency_count: u8,
+    /// Currency of the primary balance fields above
+    pub base_currency_id: u16,
+    /// Decimal places for displaying amounts; not used in any computation
+    pub decimals: u8,
+    /// Human-readable label, encrypted by the owner's client under their
+    /// `owner_enc_pubkey` shared secret; never read by the program
+    pub encrypted_label: [u8; 32],
+    /// Nonce for the label encryption
+    pub label_nonce: u128,
+    /// Whether `set_daily_limit` has stored a limit
+    pub has_daily_limit: bool,
+    /// Second signer who approves large transfers, set by `set_co_signer`
+    pub co_signer: Option<Pubkey>,
+    /// Whether `set_co_signer`'s threshold has been stored
+    pub has_large_transfer_threshold: bool,
+    /// Whether `set_min_reserve` has stored a reserve
+    pub has_min_reserve: bool,
+    /// Whether incoming `process_payment` transfers wait for `accept_payment`;
+    /// payment paths without an escrow refuse such a receiver
+    pub require_acceptance: bool,
+    /// Unix timestamp of the payment that last reset `encrypted_spent_today`
+    pub last_spend_reset: i64,
+    /// Offset from UTC of the owner's day, set by `set_utc_offset`
+    pub utc_offset_seconds: i64,
+    /// Total number of transactions
+    pub transaction_count: u64,
+    /// Number of payments queued by `process_payment`, which must be passed
+    /// back as `expected_sequence` by the next one
+    pub sequence: u64,
+    /// Accumulated reward points
+    pub reward_points: u64,
+    /// Unix timestamp `calculate_rewards` last added points at
+    pub points_updated_ts: i64,
+    /// Unix timestamp after which `reward_points` are forfeited; zero until the
+    /// first points are earned
+    pub points_expiry_ts: i64,
+    /// Owner's Arcium encryption public key
+    pub owner_enc_pubkey: [u8; 32],
+    /// Current account state
+    pub account_state: AccountState,
+    /// Unix timestamp the account was opened at
+    pub created_at: i64,
+    /// End of the last interest period credited by `accrue_interest`
+    pub last_accrual_ts: i64,
+    /// Unix timestamp of the owner's last payment, deposit or withdrawal
+    pub last_activity_ts: i64,
+    /// Client-supplied nonces most recently used for this account's outputs
+    pub recent_nonces: [u128; RECENT_NONCE_COUNT],
+    /// Slot of `recent_nonces` the next nonce is written to
+    pub recent_nonce_cursor: u8,
+    /// Queued computations that will rewrite this account's balance
+    pub in_flight_count: u8,
+    /// Unix timestamp the last of those computations was queued at
+    pub in_flight_since: i64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl UserAccount {
+    /// Records a queued computation that will rewrite this account's balance.
+    ///
+    /// Each one reads the balance ciphertext as it was when queued, so a second
+    /// queued before the first callback lands would overwrite its result. Counts
+    /// older than `IN_FLIGHT_TIMEOUT` are dropped first.
+    pub fn begin_computation(&mut self, max_in_flight: u8) -> Result<()> {
+        begin_in_flight(&mut self.in_flight_count, &mut self.in_flight_since, max_in_flight)
+    }
+
+    /// Releases one computation recorded by `begin_computation`, from its callback.
+    pub fn end_computation(&mut self) {
+        self.in_flight_count = self.in_flight_count.saturating_sub(1);
+    }
+
+    /// Moves the account to `state`, failing with `InvalidStateTransition` unless
+    /// `AccountState::can_transition` allows it.
+    pub fn set_state(&mut self, state: AccountState) -> Result<()> {
+        require!(
+            AccountState::can_transition(self.account_state, state),
+            ErrorCode::InvalidStateTransition
+        );
+        self.account_state = state;
+        Ok(())
+    }
+
+    /// Records a client-supplied nonce for one of this account's ciphertexts.
+    ///
+    /// Encrypting two values under the same key and nonce leaks their relation, so
+    /// zero (an unset nonce) and any of the last `RECENT_NONCE_COUNT` are refused.
+    pub fn use_nonce(&mut self, nonce: u128) -> Result<()> {
+        require!(nonce != 0, ErrorCode::InvalidNonce);
+        require!(!self.recent_nonces.contains(&nonce), ErrorCode::NonceReused);
+        self.recent_nonces[self.recent_nonce_cursor as usize] = nonce;
+        self.recent_nonce_cursor =
+            ((self.recent_nonce_cursor as usize + 1) % RECENT_NONCE_COUNT) as u8;
+        Ok(())
+    }
+
+    /// Slot of `currencies` holding `currency_id`, or `None` for the base currency.
+    pub fn currency_slot(&self, currency_id: u16) -> Result<Option<usize>> {
+        if currency_id == self.base_currency_id {
+            return Ok(None);
+        }
+        self.currencies[..self.currency_count as usize]
+            .iter()
+            .position(|currency| currency.initialized && currency.currency_id == currency_id)
+            .map(Some)
+            .ok_or(error!(ErrorCode::CurrencyNotSupported))
+    }
+
+    /// Circuit arguments reading this account's MXE-encrypted balance in
+    /// `currency_id`; `key` is the account's address.
+    pub fn mxe_balance_arguments(&self, key: Pubkey, currency_id: u16) -> Result<[Argument; 2]> {
+        Ok(match self.currency_slot(currency_id)? {
+            None => [
+                Argument::PlaintextU128(self.mxe_balance_nonce),
+                Argument::Account(key, USER_ACCOUNT_MXE_BALANCE_OFFSET, 32),
+            ],
+            Some(slot) => [
+                Argument::PlaintextU128(self.currencies[slot].mxe_balance_nonce),
+                Argument::Account(key, currency_mxe_balance_offset(slot), 32),
+            ],
+        })
+    }
+
+    /// Circuit arguments reading this account's minimum reserve for a debit in
+    /// `currency_id`; the reserve is held in the base currency, so it only applies
+    /// there. `key` is the account's address.
+    pub fn min_reserve_arguments(&self, key: Pubkey, currency_id: u16) -> [Argument; 3] {
+        [
+            Argument::PlaintextU128(self.min_reserve_nonce),
+            Argument::Account(key, USER_ACCOUNT_MIN_RESERVE_OFFSET, 32),
+            Argument::PlaintextBool(self.has_min_reserve && currency_id == self.base_currency_id),
+        ]
+    }
+
+    /// Nonce of the owner's copy of the balance in `currency_id`.
+    pub fn client_balance_nonce(&self, currency_id: u16) -> Result<u128> {
+        Ok(match self.currency_slot(currency_id)? {
+            None => self.balance_nonce,
+            Some(slot) => self.currencies[slot].balance_nonce,
+        })
+    }
+
+    /// Stores the `(ciphertext, nonce)` pairs of a new balance in `currency_id`:
+    /// the MXE copy and the copy encrypted to the owner.
+    pub fn store_balance(
+        &mut self,
+        currency_id: u16,
+        mxe_balance: ([u8; 32], u128),
+        client_balance: ([u8; 32], u128),
+    ) -> Result<()> {
+        match self.currency_slot(currency_id)? {
+            None => {
+                (self.mxe_encrypted_balance, self.mxe_balance_nonce) = mxe_balance;
+                (self.encrypted_balance, self.balance_nonce) = client_balance;
+            }
+            Some(slot) => {
+                let currency = &mut self.currencies[slot];
+                (currency.mxe_encrypted_balance, currency.mxe_balance_nonce) = mxe_balance;
+                (currency.encrypted_balance, currency.balance_nonce) = client_balance;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Balance of a user account in one non-base currency.
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CurrencyBalance {
+    pub currency_id: u16,
+    /// Balance encrypted under the MXE key, read by the balance circuits
+    pub mxe_encrypted_balance: [u8; 32],
+    pub mxe_balance_nonce: u128,
+    /// Balance encrypted to the owner's key
+    pub encrypted_balance: [u8; 32],
+    pub balance_nonce: u128,
+    /// Whether the balance has been stored by `add_currency`
+    pub initialized: bool,
+}
+
+/// Overdraft allowance of a user account.
+///
+/// The account's position is `balance - drawn`: the balance itself stays a u64,
+/// and whatever a signed payment takes beyond it is tracked in `encrypted_drawn`,
+/// which may not exceed the limit. Both values are MXE-encrypted.
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CreditLine {
+    /// Credit limit, at `USER_ACCOUNT_CREDIT_LIMIT_OFFSET`
+    pub encrypted_limit: [u8; 32],
+    /// Nonce for the limit encryption
+    pub limit_nonce: u128,
+    /// Credit currently drawn, at `USER_ACCOUNT_CREDIT_DRAWN_OFFSET`
+    pub encrypted_drawn: [u8; 32],
+    /// Nonce for the drawn amount encryption
+    pub drawn_nonce: u128,
+    /// Whether `open_credit_line` has stored a limit
+    pub is_open: bool,
+}
+
+/// Transaction record with encrypted amount.
+#[account]
+#[derive(InitSpace)]
+pub struct Transaction {
+    /// Unique transaction identifier
+    pub transaction_id: u64,
+    /// Sender account public key
+    pub sender: Pubkey,
+    /// Receiver account public key
+    pub receiver: Pubkey,
+    /// Transaction amount encrypted to the sender's key
+    pub encrypted_amount: [u8; 32],
+    /// Nonce for amount encryption
+    pub amount_nonce: u128,
+    /// Transaction timestamp
+    pub timestamp: i64,
+    /// Transaction status
+    pub status: TransactionStatus,
+    /// Parties whose AML throughput already includes this transaction
+    pub aml_recorded: u8,
+    /// Currency the amount is denominated in
+    pub currency_id: u16,
+    /// Client-supplied memo, encrypted with the x25519 secret shared by the
+    /// sender's and receiver's `owner_enc_pubkey`s; zero when none was given
+    pub encrypted_memo: [u8; 64],
+    /// Nonce for the memo encryption
+    pub memo_nonce: u128,
+    /// Amount held for the receiver's acceptance (MXE-encrypted), at
+    /// `TRANSACTION_ESCROW_OFFSET`; zero for other payments
+    pub encrypted_escrow: [u8; 32],
+    /// Nonce for the escrowed amount encryption
+    pub escrow_nonce: u128,
+    /// Payment this transaction refunds, if it is a refund
+    pub refunded_transaction_id: Option<u64>,
+    /// Whether the payment is held by `flag_transaction`, or was refunded out
+    /// of that hold
+    pub disputed: bool,
+    /// Offset of the computation last queued for this transaction, echoed in
+    /// its result events
+    pub computation_offset: u64,
+    /// Whether the receiver required acceptance when the payment was sent
+    pub requires_acceptance: bool,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// A payment escrowed by `schedule_payment` until its release time.
+#[account]
+#[derive(InitSpace)]
+pub struct ScheduledPayment {
+    /// Unique schedule identifier
+    pub schedule_id: u64,
+    /// Sender account public key
+    pub sender: Pubkey,
+    /// Receiver account public key
+    pub receiver: Pubkey,
+    /// Escrowed amount (MXE-encrypted), at `SCHEDULED_PAYMENT_AMOUNT_OFFSET`
+    pub encrypted_amount: [u8; 32],
+    /// Nonce for the escrowed amount encryption
+    pub amount_nonce: u128,
+    /// Unix timestamp from which the payment can execute
+    pub release_ts: i64,
+    /// Current schedule state
+    pub status: ScheduledPaymentStatus,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl ScheduledPayment {
+    /// Account credited by the release in progress: the sender when cancelling,
+    /// otherwise the receiver.
+    pub fn beneficiary(&self) -> Pubkey {
+        if self.status == ScheduledPaymentStatus::Cancelling {
+            self.sender
+        } else {
+            self.receiver
+        }
+    }
+
+    /// The other party of the release in progress, counted in flight alongside
+    /// the beneficiary.
+    pub fn counterparty(&self) -> Pubkey {
+        if self.status == ScheduledPaymentStatus::Cancelling {
+            self.receiver
+        } else {
+            self.sender
+        }
+    }
+}
+
+/// Tokens moved into the vault by `deposit_from_token` whose credit has not
+/// landed yet, keyed by the computation offset. Closed by the deposit callback
+/// on success, or by `reclaim_deposit` when the tokens are refunded.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingDeposit {
+    /// Account the deposit credits
+    pub user_account: Pubkey,
+    /// Owner whose token account the tokens came from and who may reclaim them
+    pub owner: Pubkey,
+    /// Mint of the deposited tokens
+    pub mint: Pubkey,
+    /// Deposited amount, in the mint's base units
+    pub amount: u64,
+    /// Unix timestamp the deposit was queued
+    pub queued_at: i64,
+    /// Whether the callback refused the credit because the balance would overflow
+    pub failed: bool,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Guardians who can jointly recover a user account whose owner lost their keys.
+#[account]
+#[derive(InitSpace)]
+pub struct Guardians {
+    /// User account these guardians protect
+    pub user_account: Pubkey,
+    /// Guardian pubkeys (at most `MAX_GUARDIANS`)
+    #[max_len(5)]
+    pub guardians: Vec<Pubkey>,
+    /// Approvals required to recover the account
+    pub quorum: u8,
+    /// Bitmask of guardian indices approving the pending recovery
+    pub approvals: u8,
+    /// Owner proposed by the pending recovery
+    pub pending_owner: Pubkey,
+    /// Encryption pubkey proposed by the pending recovery
+    pub pending_enc_pubkey: [u8; 32],
+    /// When `recover_account` was queued (0 when none is in flight)
+    pub recovery_queued_at: i64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl Guardians {
+    pub fn clear_recovery(&mut self) {
+        self.approvals = 0;
+        self.pending_owner = Pubkey::default();
+        self.pending_enc_pubkey = [0; 32];
+        self.recovery_queued_at = 0;
+    }
+
+    /// Whether a queued `recover_account` may still land its callback, which reads
+    /// the pending owner keys. Recoveries older than `IN_FLIGHT_TIMEOUT` are
+    /// treated as lost.
+    pub fn recovery_in_flight(&self, now: i64) -> bool {
+        self.recovery_queued_at != 0 && now.saturating_sub(self.recovery_queued_at) < IN_FLIGHT_TIMEOUT
+    }
+}
+
+/// Most recent transaction ids sent or received by the accounts of one owner,
+/// kept as a ring buffer. PDA seeded by the owner's pubkey, see `UserTxIndex::pda`.
+#[account]
+#[derive(InitSpace)]
+pub struct UserTxIndex {
+    /// Owner whose accounts the transactions involve
+    pub owner: Pubkey,
+    /// Transaction ids; once full, `next_slot` holds the oldest
+    pub transaction_ids: [u64; MAX_TX_INDEX_ENTRIES],
+    /// Slot the next transaction id is written to
+    pub next_slot: u8,
+    /// Number of slots in use
+    pub len: u8,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl UserTxIndex {
+    /// Address and bump of the index for `owner`.
+    pub fn pda(owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[USER_TX_INDEX_SEED, owner.as_ref()], &crate::ID)
+    }
+
+    /// Appends `transaction_id`, evicting the oldest entry when full.
+    pub fn record(&mut self, transaction_id: u64) {
+        self.transaction_ids[self.next_slot as usize] = transaction_id;
+        self.next_slot = ((self.next_slot as usize + 1) % MAX_TX_INDEX_ENTRIES) as u8;
+        if (self.len as usize) < MAX_TX_INDEX_ENTRIES {
+            self.len += 1;
+        }
+    }
+
+    /// Stored transaction ids, oldest first.
+    pub fn recent(&self) -> Vec<u64> {
+        let start = if (self.len as usize) < MAX_TX_INDEX_ENTRIES {
+            0
+        } else {
+            self.next_slot as usize
+        };
+        (0..self.len as usize)
+            .map(|i| self.transaction_ids[(start + i) % MAX_TX_INDEX_ENTRIES])
+            .collect()
+    }
+}
+
+/// One receiver of a `batch_payment`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BatchPaymentEntry {
+    /// Receiver's UserAccount PDA
+    pub receiver: Pubkey,
+    /// Amount to transfer
+    pub amount: u64,
+    /// Nonce for the receiver's re-encrypted balance
+    pub receiver_new_nonce: u128,
+}
+
+/// Permission for a delegate to spend from an account up to an encrypted cap.
+#[account]
+#[derive(InitSpace)]
+pub struct SpendingDelegate {
+    /// The UserAccount PDA being spent from
+    pub user_account: Pubkey,
+    /// Pubkey allowed to sign delegated payments
+    pub delegate: Pubkey,
+    /// Remaining spending cap (MXE-encrypted ciphertext)
+    pub encrypted_cap: [u8; 32],
+    /// Nonce for cap encryption
+    pub cap_nonce: u128,
+    /// Whether the cap has been stored and the delegation can be used
+    pub active: bool,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Encrypted gross-throughput accumulator for AML velocity checks.
+#[account]
+#[derive(InitSpace)]
+pub struct AmlTracker {
+    /// User account being tracked
+    pub user_account: Pubkey,
+    /// Encrypted inflow + outflow within the current window
+    pub encrypted_throughput: [u8; 32],
+    /// Nonce for throughput encryption
+    pub throughput_nonce: u128,
+    /// Time of the transaction that opened the current velocity window
+    pub window_start_ts: i64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Program-wide settings controlled by the admin.
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    /// Signer allowed to update the configuration
+    pub admin: Pubkey,
+    /// Fees charged per operation type
+    pub fee_schedule: FeeSchedule,
+    /// Reward points redeemed for one unit of balance
+    pub points_per_unit: u64,
+    /// Reward points earned per transaction and balance tier thresholds
+    pub reward_config: RewardConfig,
+    /// Largest `initial_balance` accepted by `initialize_user_account`
+    pub max_initial_balance: u64,
+    /// Length in seconds of one interest accrual period
+    pub interest_period: i64,
+    /// Emergency stop flag; while set, payments, deposits, withdrawals,
+    /// balance checks and reward calculations refuse to queue
+    pub paused: bool,
+    /// Signer allowed to `seize_funds` from frozen accounts
+    pub seizure_authority: Pubkey,
+    /// Seconds without activity after which `mark_dormant` may freeze an account
+    pub dormancy_threshold: i64,
+    /// Seconds reward points stay redeemable after `calculate_rewards` last added
+    /// to them
+    pub points_expiry_period: i64,
+    /// Balance-rewriting computations an account may have queued at once
+    pub max_in_flight: u8,
+    /// Token mint `deposit_from_token` accepts, credited one base unit per
+    /// token unit; unset (the default key) disables token deposits
+    pub deposit_mint: Pubkey,
+    /// Seconds of transaction time one AML velocity window spans
+    pub aml_window: i64,
+    /// Gross throughput within the window above which an account is flagged
+    pub aml_threshold: u64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Fee charged on one operation: `fee_bps` of the amount plus `flat_fee`.
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct FeeRule {
+    pub fee_bps: u16,
+    pub flat_fee: u64,
+}
+
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct FeeSchedule {
+    pub payment: FeeRule,
+    pub withdrawal: FeeRule,
+    pub interest_accrual: FeeRule,
+}
+
+impl FeeSchedule {
+    pub fn rule(&self, operation: FeeOperation) -> FeeRule {
+        match operation {
+            FeeOperation::Payment => self.payment,
+            FeeOperation::Withdrawal => self.withdrawal,
+            FeeOperation::InterestAccrual => self.interest_accrual,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for rule in [self.payment, self.withdrawal, self.interest_accrual] {
+            require!(rule.fee_bps <= 10_000, ErrorCode::InvalidFeeSchedule);
+        }
+        Ok(())
+    }
+}
+
+/// Inputs of the `calculate_rewards` formula.
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RewardConfig {
+    /// Points earned per transaction
+    pub points_per_tx: u64,
+    /// Minimum balance of the Silver tier
+    pub silver_threshold: u64,
+    /// Minimum balance of the Gold tier
+    pub gold_threshold: u64,
+    /// Minimum balance of the Premium tier
+    pub premium_threshold: u64,
+    /// Decimals the thresholds are written in; they are rescaled to each
+    /// account's `decimals` before the tier is evaluated
+    pub threshold_decimals: u8,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            points_per_tx: 10,
+            silver_threshold: 1000,
+            gold_threshold: 5000,
+            premium_threshold: 10000,
+            threshold_decimals: 2,
+        }
+    }
+}
+
+impl RewardConfig {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.silver_threshold <= self.gold_threshold
+                && self.gold_threshold <= self.premium_threshold,
+            ErrorCode::InvalidRewardConfig
+        );
+        Ok(())
+    }
+
+    /// `threshold` converted from `threshold_decimals` to an amount with
+    /// `decimals`. Scaling up saturates; scaling down rounds toward zero.
+    pub fn scale_threshold(&self, threshold: u64, decimals: u8) -> u64 {
+        if decimals >= self.threshold_decimals {
+            let factor = 10u64
+                .checked_pow((decimals - self.threshold_decimals) as u32)
+                .unwrap_or(u64::MAX);
+            threshold.saturating_mul(factor)
+        } else {
+            10u64
+                .checked_pow((self.threshold_decimals - decimals) as u32)
+                .map_or(0, |factor| threshold / factor)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FeeOperation {
+    Payment,
+    Withdrawal,
+    InterestAccrual,
+}
+
+/// Counts a queued computation against an in-flight guard, failing with
+/// `ComputationInFlight` once `max_in_flight` are queued. A count older than
+/// `IN_FLIGHT_TIMEOUT` is dropped first, so a callback that never lands does not
+/// block the guarded account for good.
+fn begin_in_flight(count: &mut u8, since: &mut i64, max_in_flight: u8) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    if now.saturating_sub(*since) >= IN_FLIGHT_TIMEOUT {
+        *count = 0;
+    }
+    require!(*count < max_in_flight, ErrorCode::ComputationInFlight);
+    *count += 1;
+    *since = now;
+    Ok(())
+}
+
+/// Encrypted pool of priority tips paid by senders for validators.
+#[account]
+#[derive(InitSpace)]
+pub struct ValidatorRewards {
+    /// Encrypted total of all tips collected
+    pub encrypted_total: [u8; 32],
+    /// Nonce for total encryption
+    pub total_nonce: u128,
+    /// Whether `encrypted_total` holds a ciphertext yet
+    pub initialized: bool,
+    /// Queued computations that will rewrite the total
+    pub in_flight_count: u8,
+    /// Unix timestamp the last of those computations was queued at
+    pub in_flight_since: i64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl ValidatorRewards {
+    /// Records a queued computation that will rewrite the total, which it reads
+    /// as it was when queued, like `UserAccount::begin_computation`.
+    pub fn begin_computation(&mut self, max_in_flight: u8) -> Result<()> {
+        begin_in_flight(&mut self.in_flight_count, &mut self.in_flight_since, max_in_flight)
+    }
+
+    /// Releases one computation recorded by `begin_computation`, from its callback.
+    pub fn end_computation(&mut self) {
+        self.in_flight_count = self.in_flight_count.saturating_sub(1);
+    }
+}
+
+/// Encrypted pool of the fees charged under the program's fee schedule.
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    /// Encrypted total of all fees collected
+    pub encrypted_total: [u8; 32],
+    /// Nonce for total encryption
+    pub total_nonce: u128,
+    /// Whether `encrypted_total` holds a ciphertext yet
+    pub initialized: bool,
+    /// Queued computations that will rewrite the total
+    pub in_flight_count: u8,
+    /// Unix timestamp the last of those computations was queued at
+    pub in_flight_since: i64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl Treasury {
+    /// Records a queued computation that will collect a fee into the total,
+    /// which it reads as it was when queued, like
+    /// `UserAccount::begin_computation`.
+    pub fn begin_computation(&mut self, max_in_flight: u8) -> Result<()> {
+        begin_in_flight(&mut self.in_flight_count, &mut self.in_flight_since, max_in_flight)
+    }
+
+    /// Releases one computation recorded by `begin_computation`, from its callback.
+    pub fn end_computation(&mut self) {
+        self.in_flight_count = self.in_flight_count.saturating_sub(1);
+    }
+
+    /// Circuit arguments reading the collected total; `key` is the treasury's
+    /// address. A circuit reads an uninitialized treasury as zero.
+    pub fn total_arguments(&self, key: Pubkey) -> [Argument; 3] {
+        [
+            Argument::PlaintextU128(self.total_nonce),
+            Argument::Account(key, 8, 32),
+            Argument::PlaintextBool(self.initialized),
+        ]
+    }
+
+    /// Stores the total returned by a fee-collecting circuit.
+    pub fn store_total(&mut self, total: ([u8; 32], u128)) {
+        (self.encrypted_total, self.total_nonce) = total;
+        self.initialized = true;
+    }
+}
+
+/// Activity session gating sensitive operations on a user account.
+#[account]
+#[derive(InitSpace)]
+pub struct SessionAccount {
+    /// User account this session belongs to
+    pub user_account: Pubkey,
+    /// Timestamp of the last refresh or gated operation
+    pub last_active_ts: i64,
+    /// Seconds of inactivity before the session expires
+    pub idle_timeout: i64,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl SessionAccount {
+    pub fn is_active(&self, now: i64) -> bool {
+        now.saturating_sub(self.last_active_ts) <= self.idle_timeout
+    }
+
+    /// Fails with `SessionExpired` if the session has lapsed, otherwise
+    /// records `now` as the latest activity.
+    pub fn touch(&mut self, now: i64) -> Result<()> {
+        require!(self.is_active(now), ErrorCode::SessionExpired);
+        self.last_active_ts = now;
+        Ok(())
+    }
+}
+
+#[repr(u8)]
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccountState {
+    Initializing = 0,
+    Active = 1,
+    Frozen = 2,
+    Closed = 3,
+    /// The initialization computation aborted; see `retry_initialization`
+    InitFailed = 4,
+}
+
+impl AccountState {
+    /// Whether an account may move from `from` to `to`.
+    ///
+    /// Initialization either activates the account or fails and may be retried;
+    /// an active account can be frozen and unfrozen, and only an active account
+    /// can be closed. Nothing leaves `Closed`.
+    pub fn can_transition(from: AccountState, to: AccountState) -> bool {
+        use AccountState::*;
+        matches!(
+            (from, to),
+            (Initializing, Active)
+                | (Initializing, InitFailed)
+                | (InitFailed, Initializing)
+                | (Active, Frozen)
+                | (Frozen, Active)
+                | (Active, Closed)
+        )
+    }
+}
+
+#[repr(u8)]
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransactionStatus {
+    Processing = 0,
+    Completed = 1,
+    Failed = 2,
+    /// Completed, then returned to the sender by `refund_transaction`
+    Refunded = 3,
+    /// Above the sender's large-transfer threshold, waiting for
+    /// `approve_large_transfer`
+    PendingApproval = 4,
+    /// Completed, then put on hold by `flag_transaction` pending a dispute
+    Held = 5,
+    /// Debited from the sender and escrowed until the receiver calls
+    /// `accept_payment` or `reject_payment`
+    PendingAcceptance = 6,
+    /// Returned to the sender by `reject_payment`
+    Rejected = 7,
+}
+
+#[repr(u8)]
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScheduledPaymentStatus {
+    /// Escrow computation queued
+    Funding = 0,
+    /// Amount escrowed, awaiting release or cancellation
+    Scheduled = 1,
+    Executing = 2,
+    Cancelling = 3,
+    Executed = 4,
+    Cancelled = 5,
+    /// The sender's balance did not cover the amount
+    Failed = 6,
+}
+
+/// A queued computation came back without a result.
+#[event]
+pub struct ComputationAbortedEvent {
+    /// Name of the circuit whose callback received the abort
+    pub operation: String,
+    /// Account the computation was for, 0 if it was not for a single account
+    pub account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountInitializedEvent {
+    pub account_id: u64,
+    pub owner: Pubkey,
+    pub balance_nonce: u128,
+    pub decimals: u8,
+}
+
+#[event]
+pub struct AccountInitFailedEvent {
+    pub account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BalanceRevealedEvent {
+    pub account_id: u64,
+    pub encrypted_balance: [u8; 32],
+    pub balance_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentProcessedEvent {
+    pub transaction_id: u64,
+    /// Offset the payment was queued with
+    pub computation_offset: u64,
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub timestamp: i64,
+    /// Sender's new balance encrypted to their own key
+    pub sender_new_balance: [u8; 32],
+    pub sender_new_balance_nonce: u128,
+    /// Nonce of the sender's balance this payment replaced. Each payment's
+    /// `prev_balance_nonce` is the previous one's `sender_new_balance_nonce`, so
+    /// clients can order racing payments.
+    pub prev_balance_nonce: u128,
+}
+
+#[event]
+pub struct PaymentPendingAcceptanceEvent {
+    pub transaction_id: u64,
+    pub computation_offset: u64,
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentAcceptedEvent {
+    pub transaction_id: u64,
+    pub computation_offset: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentRejectedEvent {
+    pub transaction_id: u64,
+    pub computation_offset: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentFailedEvent {
+    pub transaction_id: u64,
+    pub computation_offset: u64,
+    pub reason: String,
+}
+
+#[event]
+pub struct BatchPaymentEvent {
+    pub sender: Pubkey,
+    pub receiver_count: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MultiSourcePaymentEvent {
+    pub receiver: Pubkey,
+    pub source_count: u8,
+    /// Index of the source that paid, encrypted to the sender's key
+    pub source_index: [u8; 32],
+    pub source_index_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MultiSourcePaymentFailedEvent {
+    pub receiver: Pubkey,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeePaymentEvent {
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub fee_collector: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeePaymentFailedEvent {
+    pub sender: Pubkey,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BatchPaymentFailedEvent {
+    pub sender: Pubkey,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalEvent {
+    pub account_id: u64,
+    pub new_balance_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalFailedEvent {
+    pub account_id: u64,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositEvent {
+    pub account_id: u64,
+    pub new_balance_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositReclaimedEvent {
+    pub user_account: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpendingDelegateGrantedEvent {
+    pub user_account: Pubkey,
+    pub delegate: Pubkey,
+}
+
+#[event]
+pub struct SpendingDelegateRevokedEvent {
+    pub user_account: Pubkey,
+    pub delegate: Pubkey,
+}
+
+#[event]
+pub struct AccountFrozenEvent {
+    pub account_id: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountStatusEvent {
+    pub account_id: u64,
+    pub state: AccountState,
+    pub transaction_count: u64,
+    pub reward_points: u64,
+}
+
+#[event]
+pub struct AccountDormantEvent {
+    pub account_id: u64,
+    pub last_activity_ts: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramPausedEvent {
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountUnfrozenEvent {
+    pub account_id: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BalanceRangeEvent {
+    pub account_id: u64,
+    pub bracket: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryRevealedEvent {
+    /// Fees collected so far, encrypted to the admin's key
+    pub encrypted_total: [u8; 32],
+    pub total_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AggregateComputedEvent {
+    pub account_count: u8,
+    /// Total of the balances, encrypted to the auditor's key
+    pub total: [u8; 32],
+    pub total_nonce: u128,
+    /// Whether the total exceeded u64::MAX and was capped
+    pub overflow: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BalanceComparisonEvent {
+    pub first_account_id: u64,
+    pub second_account_id: u64,
+    /// 0 = first balance lower, 1 = equal, 2 = first balance higher
+    pub ordering: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsCalculatedEvent {
+    pub account_id: u64,
+    pub reward_points: u64,
+    pub total_rewards: u64,
+    /// Balance tier reached: 0 Basic, 1 Silver, 2 Gold, 3 Premium
+    pub tier: u8,
+}
+
+/// Reward points were capped at `u64::MAX`.
+#[event]
+pub struct RewardsOverflowEvent {
+    pub account_id: u64,
+    /// Points earned beyond the cap
+    pub discarded_points: u64,
+}
+
+#[event]
+pub struct RewardsExpiredEvent {
+    pub account_id: u64,
+    /// Points forfeited because the expiry window passed
+    pub expired_points: u64,
+}
+
+#[event]
+pub struct RewardsRedeemedEvent {
+    pub account_id: u64,
+    pub remaining_points: u64,
+    pub new_balance_nonce: u128,
 }
 
-#[callback_accounts("initialize_accounts")]
-#[derive(Accounts)]
-pub struct InitializeAccountsCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INITIALIZE_ACCOUNTS)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar, checked by the account constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
+#[event]
+pub struct BalanceCheckEvent {
+    pub account_id: u64,
+    pub is_above_threshold: bool,
+    pub timestamp: i64,
 }
 
-#[init_computation_definition_accounts("initialize_accounts", payer)]
-#[derive(Accounts)]
-pub struct InitInitializeAccountsCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(mut)]
-    /// CHECK: comp_def_account, checked by arcium program.
-    /// Can't check it here as it's not initialized yet.
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PremiumEligibilityEvent {
+    pub account_id: u64,
+    pub eligible: bool,
+    pub timestamp: i64,
 }
 
-// ============================================================================
-// ACCOUNT CONTEXTS - Process Payment
-// ============================================================================
+#[event]
+pub struct AggregateLoyaltyEvent {
+    pub owner: Pubkey,
+    pub primary_account_id: u64,
+    pub loyalty_tier: u8,
+}
 
-#[queue_computation_accounts("process_payment", payer)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64, transaction_id: u64)]
-pub struct ProcessPayment<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut)]
-    pub sender_account: Account<'info, UserAccount>,
-    #[account(mut)]
-    pub receiver_account: Account<'info, UserAccount>,
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + Transaction::INIT_SPACE,
-        seeds = [b"transaction", transaction_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub transaction: Account<'info, Transaction>,
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = payer,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account, checked by the arcium program.
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool, checked by the arcium program.
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account, checked by the arcium program.
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct RecoveryApprovedEvent {
+    pub account_id: u64,
+    pub guardian: Pubkey,
+    pub approvals: u8,
+    pub quorum: u8,
 }
 
-#[callback_accounts("process_payment")]
-#[derive(Accounts)]
-pub struct ProcessPaymentCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar, checked by the account constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub transaction: Account<'info, Transaction>,
+#[event]
+pub struct AccountRecoveredEvent {
+    pub account_id: u64,
+    pub new_owner: Pubkey,
+    pub encrypted_balance: [u8; 32],
+    pub balance_nonce: u128,
 }
 
-#[init_computation_definition_accounts("process_payment", payer)]
-#[derive(Accounts)]
-pub struct InitProcessPaymentCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(mut)]
-    /// CHECK: Checked by Arcium program
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct EncryptionKeyRotatedEvent {
+    pub account_id: u64,
+    pub balance_nonce: u128,
+    pub timestamp: i64,
 }
 
-// ============================================================================
-// ACCOUNT CONTEXTS - Check Balance
-// ============================================================================
+#[event]
+pub struct DailyLimitSetEvent {
+    pub account_id: u64,
+    pub timestamp: i64,
+}
 
-#[queue_computation_accounts("check_balance", payer)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64, _account_id: u64)]
-pub struct CheckBalance<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = payer,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account, checked by the arcium program.
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool, checked by the arcium program.
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account, checked by the arcium program.
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_BALANCE)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
+#[event]
+pub struct DailyLimitClearedEvent {
+    pub account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MinReserveSetEvent {
+    pub account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CurrencyAddedEvent {
+    pub account_id: u64,
+    pub currency_id: u16,
+}
+
+#[event]
+pub struct CreditLineOpenedEvent {
+    pub account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SignedPaymentProcessedEvent {
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    /// Sender's new balance, encrypted to their key
+    pub sender_new_balance: [u8; 32],
+    pub sender_new_balance_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreditLimitExceededEvent {
+    pub account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AvailableCreditRevealedEvent {
+    pub account_id: u64,
+    /// Credit limit plus balance less the drawn amount, encrypted to the owner
+    pub encrypted_available: [u8; 32],
+    pub available_nonce: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpendLimitExceededEvent {
+    pub transaction_id: u64,
+    pub computation_offset: u64,
+    pub account_id: u64,
+    pub timestamp: i64,
 }
 
-#[callback_accounts("check_balance")]
-#[derive(Accounts)]
-pub struct CheckBalanceCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_BALANCE)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar, checked by the account constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
+#[event]
+pub struct InterestAccruedEvent {
+    pub account_id: u64,
+    pub periods: u8,
 }
 
-#[init_computation_definition_accounts("check_balance", payer)]
-#[derive(Accounts)]
-pub struct InitCheckBalanceCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(mut)]
-    /// CHECK: Checked by Arcium program
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct AmlVelocityEvent {
+    pub account_id: u64,
+    pub flagged: bool,
+    pub window_start_ts: i64,
 }
 
-// ============================================================================
-// ACCOUNT CONTEXTS - Calculate Rewards
-// ============================================================================
+#[event]
+pub struct PaymentReceiptEvent {
+    pub transaction_id: u64,
+    /// Whether the payment's amount was at least the requested threshold
+    pub meets_threshold: bool,
+    pub computation_offset: u64,
+}
 
-#[queue_computation_accounts("calculate_rewards", payer)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64, _account_id: u64)]
-pub struct CalculateRewards<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = payer,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account, checked by the arcium program.
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool, checked by the arcium program.
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account, checked by the arcium program.
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_REWARDS)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
+#[event]
+pub struct TransactionAmountRevealedEvent {
+    pub transaction_id: u64,
+    /// Party whose `owner_enc_pubkey` the amount is encrypted to
+    pub account_id: u64,
+    pub encrypted_amount: [u8; 32],
+    pub amount_nonce: u128,
 }
 
-#[callback_accounts("calculate_rewards")]
-#[derive(Accounts)]
-pub struct CalculateRewardsCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_REWARDS)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar, checked by the account constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
+#[event]
+pub struct PingEvent {
+    /// Whether the probe value survived the MXE encrypt/decrypt round trip
+    pub success: bool,
 }
 
-#[init_computation_definition_accounts("calculate_rewards", payer)]
-#[derive(Accounts)]
-pub struct InitCalculateRewardsCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(mut)]
-    /// CHECK: Checked by Arcium program
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct FeeScheduleUpdatedEvent {
+    pub fee_schedule: FeeSchedule,
 }
 
-// ============================================================================
-// DATA STRUCTURES
-// ============================================================================
-#[account]
-#[derive(InitSpace)]
-pub struct UserAccount {
-    /// Unique account identifier
+#[event]
+pub struct RewardConfigUpdatedEvent {
+    pub reward_config: RewardConfig,
+}
+
+#[event]
+pub struct SessionRefreshedEvent {
     pub account_id: u64,
-    /// Owner's Solana public key
-    pub owner_pubkey: Pubkey,
-    /// Encrypted balance (32 bytes ciphertext)
-    pub encrypted_balance: [u8; 32],
-    /// Nonce for balance encryption
-    pub balance_nonce: u128,
-    /// Total number of transactions
-    pub transaction_count: u64,
-    /// Accumulated reward points
-    pub reward_points: u64,
-    /// Owner's Arcium encryption public key
-    pub owner_enc_pubkey: [u8; 32],
-    /// Current account state
-    pub account_state: AccountState,
-    /// PDA bump seed
-    pub bump: u8,
+    pub last_active_ts: i64,
 }
 
-/// Transaction record with encrypted amount.
-#[account]
-#[derive(InitSpace)]
-pub struct Transaction {
-    /// Unique transaction identifier
+#[event]
+pub struct CoSignerSetEvent {
+    pub account_id: u64,
+    pub co_signer: Pubkey,
+}
+
+#[event]
+pub struct CoSignerRemovedEvent {
+    pub account_id: u64,
+    pub co_signer: Pubkey,
+}
+
+#[event]
+pub struct ApprovalRequiredEvent {
     pub transaction_id: u64,
-    /// Sender account public key
+    pub computation_offset: u64,
+    pub account_id: u64,
+    pub co_signer: Pubkey,
+}
+
+#[event]
+pub struct LargeTransferApprovedEvent {
+    pub transaction_id: u64,
+    pub computation_offset: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentScheduledEvent {
+    pub schedule_id: u64,
     pub sender: Pubkey,
-    /// Receiver account public key
     pub receiver: Pubkey,
-    /// Encrypted transaction amount
-    pub encrypted_amount: [u8; 32],
-    /// Nonce for amount encryption
-    pub amount_nonce: u128,
-    /// Transaction timestamp
+    pub release_ts: i64,
+}
+
+#[event]
+pub struct ScheduledPaymentExecutedEvent {
+    pub schedule_id: u64,
     pub timestamp: i64,
-    /// Transaction status
-    pub status: TransactionStatus,
-    /// PDA bump seed
-    pub bump: u8,
 }
 
-#[repr(u8)]
-#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
-pub enum AccountState {
-    Initializing = 0,
-    Active = 1,
-    Frozen = 2,
-    Closed = 3,
+#[event]
+pub struct ScheduledPaymentCancelledEvent {
+    pub schedule_id: u64,
+    pub timestamp: i64,
 }
 
-#[repr(u8)]
-#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
-pub enum TransactionStatus {
-    Processing = 0,
-    Completed = 1,
-    Failed = 2,
+#[event]
+pub struct ScheduledPaymentFailedEvent {
+    pub schedule_id: u64,
+    pub reason: String,
 }
 
 #[event]
-pub struct AccountInitializedEvent {
+pub struct FundsSeizedEvent {
     pub account_id: u64,
-    pub owner: Pubkey,
-    pub balance_nonce: u128,
+    /// Balance destroyed, encrypted to the seizure authority's key
+    pub seized_amount: [u8; 32],
+    pub seized_amount_nonce: u128,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct PaymentProcessedEvent {
+pub struct BalanceSweptEvent {
+    pub source_account_id: u64,
+    pub destination_account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SweepFailedEvent {
+    pub source_account_id: u64,
+    pub destination_account_id: u64,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountClosedEvent {
+    pub account_id: u64,
+    pub destination_account_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TransactionHeldEvent {
     pub transaction_id: u64,
-    pub sender: Pubkey,
-    pub receiver: Pubkey,
+    /// Whether the receiver's account was frozen along with the hold
+    pub receiver_frozen: bool,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct PaymentFailedEvent {
+pub struct DisputeResolvedEvent {
+    pub transaction_id: u64,
+    /// Whether the payment was refunded rather than released
+    pub refunded: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TransactionRefundedEvent {
+    pub transaction_id: u64,
+    pub refund_transaction_id: u64,
+    /// Offset the refund was queued with
+    pub computation_offset: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundFailedEvent {
     pub transaction_id: u64,
+    pub refund_transaction_id: u64,
+    /// Offset the refund was queued with
+    pub computation_offset: u64,
     pub reason: String,
 }
 
 #[event]
-pub struct RewardsCalculatedEvent {
+pub struct UtcOffsetSetEvent {
     pub account_id: u64,
-    pub reward_points: u64,
-    pub total_rewards: u64,
+    pub utc_offset_seconds: i64,
 }
 
 #[event]
-pub struct BalanceCheckEvent {
+pub struct LabelSetEvent {
     pub account_id: u64,
-    pub is_above_threshold: bool,
-    pub timestamp: i64,
+    pub label_nonce: u128,
 }
 
 #[error_code]
@@ -846,4 +13724,138 @@ pub enum ErrorCode {
     InvalidEncryptionPubkey,
     #[msg("Cluster not set")]
     ClusterNotSet,
+    #[msg("Signer is not authorized for this account")]
+    Unauthorized,
+    #[msg("Idle timeout must be positive")]
+    InvalidIdleTimeout,
+    #[msg("Session expired, refresh it first")]
+    SessionExpired,
+    #[msg("Too many accounts supplied")]
+    InvalidAccountCount,
+    #[msg("The same account was supplied more than once")]
+    DuplicateAccount,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Fee basis points must not exceed 10000")]
+    InvalidFeeSchedule,
+    #[msg("AML window must be positive")]
+    InvalidAmlWindow,
+    #[msg("Invalid transaction status")]
+    InvalidTransactionStatus,
+    #[msg("Account is not a party to this transaction")]
+    NotTransactionParty,
+    #[msg("Transaction already counted for this account")]
+    AmlAlreadyRecorded,
+    #[msg("Guardians must be distinct, at most five, with 1 <= quorum <= count")]
+    InvalidGuardianSet,
+    #[msg("Not enough guardian approvals to recover the account")]
+    RecoveryQuorumNotMet,
+    #[msg("Payment exceeds the delegate's remaining spending cap")]
+    SpendingCapExceeded,
+    #[msg("Lower bound must not exceed the upper bound")]
+    InvalidBalanceRange,
+    #[msg("Sender and receiver must be different accounts")]
+    SelfTransfer,
+    #[msg("Not enough reward points")]
+    InsufficientRewards,
+    #[msg("Points must be a positive multiple of the redemption rate")]
+    InvalidRedemptionAmount,
+    #[msg("Interest rate must not exceed 10000 basis points")]
+    InvalidInterestRate,
+    #[msg("Interest period must be positive")]
+    InvalidInterestPeriod,
+    #[msg("Tier thresholds must be in ascending order")]
+    InvalidRewardConfig,
+    #[msg("Transaction id has already been used")]
+    DuplicateTransactionId,
+    #[msg("Initial balance exceeds the configured maximum")]
+    InitialBalanceTooHigh,
+    #[msg("Account has no open credit line")]
+    NoCreditLine,
+    #[msg("Account already has a credit line")]
+    CreditLineAlreadyOpen,
+    #[msg("Account holds no balance in this currency")]
+    CurrencyNotSupported,
+    #[msg("Account already holds this currency")]
+    CurrencyAlreadyHeld,
+    #[msg("Account holds the maximum number of currencies")]
+    TooManyCurrencies,
+    #[msg("UTC offset must be within 14 hours")]
+    InvalidUtcOffset,
+    #[msg("Transaction has already been refunded")]
+    TransactionAlreadyRefunded,
+    #[msg("Release time must be in the future")]
+    InvalidReleaseTime,
+    #[msg("Scheduled payment is not released yet")]
+    ScheduledPaymentLocked,
+    #[msg("Scheduled payment has been released and can no longer be cancelled")]
+    ScheduledPaymentReleased,
+    #[msg("Decimals must be at most 18")]
+    InvalidDecimals,
+    #[msg("Payment was built against an outdated account sequence")]
+    StaleSequence,
+    #[msg("Dormancy threshold must be positive")]
+    InvalidDormancyThreshold,
+    #[msg("Account has been active within the dormancy threshold")]
+    AccountNotDormant,
+    #[msg("Spend limits, reserves, co-signers and acceptance are not supported here")]
+    UnsupportedAccountSettings,
+    #[msg("Nonce must be non-zero")]
+    InvalidNonce,
+    #[msg("Nonce was already used for this account")]
+    NonceReused,
+    #[msg("Token mint is not accepted for deposits")]
+    InvalidMint,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Account cannot move to the requested state")]
+    InvalidStateTransition,
+    #[msg("Points expiry period must be positive")]
+    InvalidPointsExpiryPeriod,
+    #[msg("Reward points have expired")]
+    RewardsExpired,
+    #[msg("Max in-flight computations must be positive")]
+    InvalidMaxInFlight,
+    #[msg("Account already has a computation in flight")]
+    ComputationInFlight,
+    #[msg("A recovery of this account is already in flight")]
+    RecoveryInProgress,
+    #[msg("Deposit is still waiting for its callback")]
+    DepositInFlight,
+    #[msg("No circuit uses this computation definition offset")]
+    UnknownComputationDefinition,
+    #[msg("Callback result was computed for a different account")]
+    CallbackAccountMismatch,
+    #[msg("Linked accounts must use the primary account's decimals")]
+    DecimalsMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_state_transitions() {
+        use AccountState::*;
+        let states = [Initializing, Active, Frozen, Closed, InitFailed];
+        let allowed = [
+            (Initializing, Active),
+            (Initializing, InitFailed),
+            (InitFailed, Initializing),
+            (Active, Frozen),
+            (Frozen, Active),
+            (Active, Closed),
+        ];
+        for from in states {
+            for to in states {
+                assert_eq!(
+                    AccountState::can_transition(from, to),
+                    allowed.contains(&(from, to)),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
 }
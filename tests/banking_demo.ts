@@ -1,6 +1,12 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { PublicKey, Keypair } from "@solana/web3.js";
+import {
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
 import { Ibank } from "../target/types/ibank";
 import { randomBytes } from "crypto";
 import {
@@ -12,6 +18,7 @@ import {
   buildFinalizeCompDefTx,
   RescueCipher,
   deserializeLE,
+  serializeLE,
   getMXEAccAddress,
   getMempoolAccAddress,
   getCompDefAccAddress,
@@ -56,6 +63,596 @@ describe("Privacy-First Banking Demo", () => {
 
   const arciumEnv = getArciumEnv();
 
+  const compDefAccount = (circuit: string) =>
+    getCompDefAccAddress(
+      program.programId,
+      Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+    );
+
+  // Accounts shared by every queue_computation instruction
+  const queueAccounts = (offset: anchor.BN, circuit: string) => ({
+    computationAccount: getComputationAccAddress(program.programId, offset),
+    clusterAccount: arciumEnv.arciumClusterPubkey,
+    mxeAccount: getMXEAccAddress(program.programId),
+    mempoolAccount: getMempoolAccAddress(program.programId),
+    executingPool: getExecutingPoolAccAddress(program.programId),
+    compDefAccount: compDefAccount(circuit),
+  });
+
+  const randomId = () => BigInt(Math.floor(Math.random() * 1000000));
+
+  const toBN = (value: bigint | number) => new anchor.BN(value.toString());
+
+  const randomNonce = () => new anchor.BN(deserializeLE(randomBytes(16)).toString());
+
+  const userAccountPda = (accountId: bigint) => {
+    const idBuffer = Buffer.alloc(8);
+    idBuffer.writeBigUInt64LE(accountId);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("user_account"), idBuffer],
+      program.programId
+    )[0];
+  };
+
+  const sessionAccountPda = (userAccount: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), userAccount.toBuffer()],
+      program.programId
+    )[0];
+
+  const userTxIndexPda = (ownerPubkey: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user_tx_index"), ownerPubkey.toBuffer()],
+      program.programId
+    )[0];
+
+  // Transaction ids recorded in an owner's index, oldest first
+  const recentTransactionIds = async (ownerPubkey: PublicKey) => {
+    const index = await program.account.userTxIndex.fetch(
+      userTxIndexPda(ownerPubkey)
+    );
+    const ids = index.transactionIds.map((id) => BigInt(id.toString()));
+    const start = index.len < ids.length ? 0 : index.nextSlot;
+    return Array.from(
+      { length: index.len },
+      (_, i) => ids[(start + i) % ids.length]
+    );
+  };
+
+  const transactionPda = (transactionId: bigint) => {
+    const idBuffer = Buffer.alloc(8);
+    idBuffer.writeBigUInt64LE(transactionId);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("transaction"), idBuffer],
+      program.programId
+    )[0];
+  };
+
+  const pendingDepositPda = (computationOffset: anchor.BN) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_deposit"), computationOffset.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const decryptBalance = async (cipher: RescueCipher, pda: PublicKey) => {
+    const account = await program.account.userAccount.fetch(pda);
+    return cipher.decrypt(
+      [account.encryptedBalance],
+      new Uint8Array(account.balanceNonce.toArray("le", 16))
+    )[0];
+  };
+
+  const validatorRewardsPda = PublicKey.findProgramAddressSync(
+    [Buffer.from("validator_rewards")],
+    program.programId
+  )[0];
+
+  const treasuryPda = PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury")],
+    program.programId
+  )[0];
+
+  // Private inputs such as amounts and tips are encrypted under the sender's key
+  const encryptU64 = (senderCipher: RescueCipher, value: number) => {
+    const nonce = randomBytes(16);
+    const [ciphertext] = senderCipher.encrypt([BigInt(value)], nonce);
+    return {
+      ciphertext: Array.from(ciphertext),
+      nonce: new anchor.BN(deserializeLE(nonce).toString()),
+    };
+  };
+
+  // A memo of up to 32 bytes, encrypted as two 16-byte chunks under `memoCipher`
+  const encryptMemo = (memoCipher: RescueCipher, memo: string) => {
+    const bytes = Buffer.alloc(32);
+    bytes.write(memo);
+    const nonce = randomBytes(16);
+    const ciphertexts = memoCipher.encrypt(
+      [deserializeLE(bytes.subarray(0, 16)), deserializeLE(bytes.subarray(16))],
+      nonce
+    );
+    return {
+      ciphertext: ciphertexts.flatMap((c) => Array.from(c)),
+      nonce: new anchor.BN(deserializeLE(nonce).toString()),
+    };
+  };
+
+  const noMemo = { ciphertext: new Array(64).fill(0), nonce: new anchor.BN(0) };
+
+  // Labels are up to 16 bytes, packed into a single field element
+  const encryptLabel = (labelCipher: RescueCipher, label: string) => {
+    const bytes = Buffer.alloc(16);
+    bytes.write(label);
+    const nonce = randomBytes(16);
+    const [ciphertext] = labelCipher.encrypt([deserializeLE(bytes)], nonce);
+    return {
+      ciphertext: Array.from(ciphertext),
+      nonce: new anchor.BN(deserializeLE(nonce).toString()),
+      plaintext: deserializeLE(bytes),
+    };
+  };
+
+  const noLabel = { ciphertext: new Array(32).fill(0), nonce: new anchor.BN(0) };
+
+  const processPaymentArgs = (
+    offset: anchor.BN,
+    transactionId: bigint,
+    amount: number,
+    senderCipher: RescueCipher,
+    senderEncPubkey: number[],
+    tip = 0,
+    currencyId = 0,
+    memo = noMemo,
+    sequence = new anchor.BN(0)
+  ) => {
+    const encryptedAmount = encryptU64(senderCipher, amount);
+    const encryptedTip = encryptU64(senderCipher, tip);
+    return [
+      offset,
+      toBN(transactionId),
+      encryptedAmount.ciphertext,
+      encryptedAmount.nonce,
+      senderEncPubkey,
+      randomNonce(),
+      encryptedTip.ciphertext,
+      encryptedTip.nonce,
+      randomNonce(),
+      randomNonce(),
+      currencyId,
+      memo.ciphertext,
+      memo.nonce,
+      sequence,
+    ] as const;
+  };
+
+  const sendPayment = async (
+    sender: PublicKey,
+    receiver: PublicKey,
+    amount: number,
+    senderCipher: RescueCipher,
+    tip = 0
+  ) => {
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    const senderState = await program.account.userAccount.fetch(sender);
+    await program.methods
+      .processPayment(
+        ...processPaymentArgs(
+          offset,
+          transactionId,
+          amount,
+          senderCipher,
+          senderState.ownerEncPubkey,
+          tip,
+          0,
+          noMemo,
+          senderState.sequence
+        )
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender,
+        receiverAccount: receiver,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    return transactionId;
+  };
+
+  const checkBalanceAbove = async (
+    account: { accountId: bigint; pda: PublicKey },
+    threshold: number,
+    currencyId = 0
+  ) => {
+    await ensureSession(account.pda);
+    const offset = new anchor.BN(randomBytes(8));
+    const balanceCheckEventPromise = awaitEvent("balanceCheckEvent");
+    await program.methods
+      .checkBalance(offset, toBN(account.accountId), toBN(threshold), currencyId)
+      .accountsPartial({
+        ...queueAccounts(offset, "check_balance"),
+        userAccount: account.pda,
+        sessionAccount: sessionAccountPda(account.pda),
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    return (await balanceCheckEventPromise).isAboveThreshold;
+  };
+
+  const calculateRewards = async (account: {
+    accountId: bigint;
+    pda: PublicKey;
+  }) => {
+    await ensureSession(account.pda);
+    const offset = new anchor.BN(randomBytes(8));
+    const rewardsEventPromise = awaitEvent("rewardsCalculatedEvent");
+    await program.methods
+      .calculateRewards(offset, toBN(account.accountId))
+      .accountsPartial({
+        ...queueAccounts(offset, "calculate_rewards"),
+        userAccount: account.pda,
+        sessionAccount: sessionAccountPda(account.pda),
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    return rewardsEventPromise;
+  };
+
+  const programConfigPda = PublicKey.findProgramAddressSync(
+    [Buffer.from("program_config")],
+    program.programId
+  )[0];
+
+  // Holds the upgrade authority, the only key allowed to create the config
+  const programDataPda = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+  )[0];
+
+  const bootstrapCircuits = [
+    "initialize_accounts",
+    "process_payment",
+    "check_balance",
+    "reveal_balance_to_owner",
+    "check_balance_range",
+    "calculate_rewards",
+    "check_premium_eligibility",
+    "compute_aggregate_loyalty",
+    "check_aml_velocity",
+    "withdraw",
+    "deposit",
+    "redeem_rewards",
+    "accrue_interest",
+    "set_spending_cap",
+    "delegated_payment",
+    "batch_payment",
+    "process_payment_with_fee",
+    "open_credit_line",
+    "process_payment_signed",
+    "reveal_available_credit",
+    "open_currency_balance",
+    "refund_payment",
+    "compare_balances",
+    "sweep_balance",
+    "schedule_payment",
+    "release_scheduled_payment",
+    "set_large_transfer_threshold",
+    "approve_large_transfer",
+    "aggregate_balances",
+    "reveal_treasury",
+    "seize_funds",
+    "set_min_reserve",
+    "release_escrowed_payment",
+    "process_payment_multi_source",
+    "prove_payment_received",
+    "reveal_transaction_amount",
+    "ping",
+    "recover_account",
+    "rotate_encryption_key",
+    "set_daily_limit",
+    "close_account",
+  ];
+
+  const isCompDefInitialized = async (circuit: string) =>
+    (await provider.connection.getAccountInfo(compDefAccount(circuit))) !== null;
+
+  // Runs `bootstrap` over every circuit in batches small enough for one legacy
+  // transaction each, then finalizes the comp defs listed in `created`
+  const bootstrap = async (created: string[]) => {
+    const batchSize = 5;
+    for (let start = 0; start < bootstrapCircuits.length; start += batchSize) {
+      const batch = bootstrapCircuits.slice(start, start + batchSize);
+      await program.methods
+        .bootstrap(
+          batch.map((circuit) =>
+            Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+          )
+        )
+        .accountsPartial({
+          payer: owner.publicKey,
+          programData: programDataPda,
+          programConfig: programConfigPda,
+          mxeAccount: getMXEAccAddress(program.programId),
+        })
+        .remainingAccounts(
+          batch.map((circuit) => ({
+            pubkey: compDefAccount(circuit),
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    }
+    for (const circuit of created) {
+      const finalizeTx = await buildFinalizeCompDefTx(
+        provider,
+        Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE(),
+        program.programId
+      );
+      const latestBlockhash = await provider.connection.getLatestBlockhash();
+      finalizeTx.recentBlockhash = latestBlockhash.blockhash;
+      finalizeTx.lastValidBlockHeight = latestBlockhash.lastValidBlockHeight;
+      finalizeTx.sign(owner);
+      await provider.sendAndConfirm(finalizeTx, [owner], {
+        commitment: "confirmed",
+      });
+    }
+  };
+
+  const setMaxInitialBalance = (maxInitialBalance: bigint) =>
+    program.methods
+      .updateMaxInitialBalance(toBN(maxInitialBalance))
+      .accountsPartial({
+        admin: owner.publicKey,
+        programConfig: programConfigPda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  const feeRule = (feeBps = 0, flatFee = 0) => ({
+    feeBps,
+    flatFee: toBN(flatFee),
+  });
+
+  const zeroFeeSchedule = () => ({
+    payment: feeRule(),
+    withdrawal: feeRule(),
+    interestAccrual: feeRule(),
+  });
+
+  const setFeeSchedule = (feeSchedule: ReturnType<typeof zeroFeeSchedule>) =>
+    program.methods
+      .updateFeeSchedule(feeSchedule)
+      .accountsPartial({
+        admin: owner.publicKey,
+        programConfig: programConfigPda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  const setMaxInFlight = (maxInFlight: number) =>
+    program.methods
+      .updateMaxInFlight(maxInFlight)
+      .accountsPartial({
+        admin: owner.publicKey,
+        programConfig: programConfigPda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  const openSession = (pda: PublicKey, idleTimeout: number, signer = owner) =>
+    program.methods
+      .openSession(toBN(idleTimeout))
+      .accountsPartial({
+        owner: signer.publicKey,
+        userAccount: pda,
+        sessionAccount: sessionAccountPda(pda),
+      })
+      .signers([signer])
+      .rpc({ commitment: "confirmed" });
+
+  // Opens a long session unless the account already has one
+  const ensureSession = async (pda: PublicKey) => {
+    if (!(await program.account.sessionAccount.fetchNullable(sessionAccountPda(pda)))) {
+      await openSession(pda, 600);
+    }
+  };
+
+  const refreshSession = (pda: PublicKey) =>
+    program.methods
+      .refreshSession()
+      .accountsPartial({
+        owner: owner.publicKey,
+        userAccount: pda,
+        sessionAccount: sessionAccountPda(pda),
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  const withdraw = async (
+    account: { accountId: bigint; pda: PublicKey },
+    amount: number
+  ) => {
+    const offset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .withdraw(offset, toBN(account.accountId), toBN(amount), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "withdraw"),
+        userAccount: account.pda,
+        sessionAccount: sessionAccountPda(account.pda),
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+  };
+
+  const revealBalance = (
+    account: { accountId: bigint; pda: PublicKey },
+    offset: anchor.BN,
+    nonce: anchor.BN
+  ) =>
+    program.methods
+      .revealBalanceToOwner(offset, toBN(account.accountId), nonce)
+      .accountsPartial({
+        ...queueAccounts(offset, "reveal_balance_to_owner"),
+        userAccount: account.pda,
+        sessionAccount: sessionAccountPda(account.pda),
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  const setAmlSettings = (window: number, threshold: number | bigint) =>
+    program.methods
+      .updateAmlSettings(toBN(window), toBN(threshold))
+      .accountsPartial({
+        admin: owner.publicKey,
+        programConfig: programConfigPda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  const checkVelocity = async (userAccount: PublicKey, transactionId: bigint) => {
+    const offset = new anchor.BN(randomBytes(8));
+    const amlEventPromise = awaitEvent("amlVelocityEvent");
+    await program.methods
+      .checkAmlVelocity(offset)
+      .accountsPartial({
+        ...queueAccounts(offset, "check_aml_velocity"),
+        programConfig: programConfigPda,
+        userAccount,
+        transaction: transactionPda(transactionId),
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    return (await amlEventPromise).flagged;
+  };
+
+  const finalize = (offset: anchor.BN) =>
+    awaitComputationFinalization(
+      provider,
+      offset,
+      program.programId,
+      "confirmed"
+    );
+
+  const sleep = (ms: number) => new Promise((res) => setTimeout(res, ms));
+
+  const setupCipher = async () => {
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const mxePublicKey = await getMXEPublicKeyWithRetry(
+      provider,
+      program.programId
+    );
+    const cipher = new RescueCipher(
+      x25519.getSharedSecret(privateKey, mxePublicKey)
+    );
+    return { privateKey, publicKey, cipher };
+  };
+
+  const createUserAccount = async (
+    initialBalance: number,
+    publicKey: Uint8Array,
+    payer: Keypair = owner,
+    currencyId = 0,
+    decimals = 2,
+    label = noLabel
+  ) => {
+    const accountId = randomId();
+    const pda = userAccountPda(accountId);
+    const offset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .initializeUserAccount(
+        offset,
+        toBN(accountId),
+        toBN(initialBalance),
+        randomNonce(),
+        Array.from(publicKey),
+        randomNonce(),
+        currencyId,
+        decimals,
+        label.ciphertext,
+        label.nonce
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "initialize_accounts"),
+        userAccount: pda,
+        programConfig: programConfigPda,
+        payer: payer.publicKey,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    return { accountId, pda };
+  };
+
+  const fundedKeypair = async () => {
+    const kp = Keypair.generate();
+    const sig = await provider.connection.requestAirdrop(
+      kp.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    return kp;
+  };
+
+  // Set by `before` when no earlier run created the program config
+  let freshDeployment = false;
+
+  before(async () => {
+    freshDeployment =
+      (await provider.connection.getAccountInfo(programConfigPda)) === null;
+    if (!freshDeployment) {
+      // New accounts start empty by default; the suite funds them at creation
+      await setMaxInitialBalance(BigInt("18446744073709551615"));
+    }
+  });
+
+  it("Should bootstrap a fresh deployment", async function () {
+    if (!freshDeployment) {
+      // A validator kept from an earlier run already holds the config
+      this.skip();
+    }
+    const missing: string[] = [];
+    for (const circuit of bootstrapCircuits) {
+      if (!(await isCompDefInitialized(circuit))) missing.push(circuit);
+    }
+
+    await bootstrap(missing);
+
+    const config = await program.account.programConfig.fetch(programConfigPda);
+    expect(config.admin.toBase58()).to.equal(owner.publicKey.toBase58());
+    expect(config.paused).to.be.false;
+    for (const rule of Object.values(config.feeSchedule)) {
+      expect(rule.feeBps).to.equal(0);
+      expect(rule.flatFee.toNumber()).to.equal(0);
+    }
+    for (const circuit of bootstrapCircuits) {
+      expect(await isCompDefInitialized(circuit)).to.be.true;
+    }
+    await setMaxInitialBalance(BigInt("18446744073709551615"));
+  });
+
   it("Should execute a complete privacy-first banking flow", async () => {
     console.log("Owner address:", owner.publicKey.toBase58());
 
@@ -137,7 +734,11 @@ describe("Privacy-First Banking Demo", () => {
         new anchor.BN(initialBalance1),
         new anchor.BN(deserializeLE(mxeNonce1).toString()),
         Array.from(publicKey),
-        new anchor.BN(deserializeLE(clientNonce1).toString())
+        new anchor.BN(deserializeLE(clientNonce1).toString()),
+        0,
+        2,
+        noLabel.ciphertext,
+        noLabel.nonce
       )
       .accountsPartial({
         computationAccount: getComputationAccAddress(
@@ -155,6 +756,7 @@ describe("Privacy-First Banking Demo", () => {
           ).readUInt32LE()
         ),
         userAccount: account1PDA,
+        programConfig: programConfigPda,
         payer: owner.publicKey,
       })
       .signers([owner])
@@ -197,7 +799,11 @@ describe("Privacy-First Banking Demo", () => {
         new anchor.BN(initialBalance2),
         new anchor.BN(deserializeLE(mxeNonce2).toString()),
         Array.from(publicKey),
-        new anchor.BN(deserializeLE(clientNonce2).toString())
+        new anchor.BN(deserializeLE(clientNonce2).toString()),
+        0,
+        2,
+        noLabel.ciphertext,
+        noLabel.nonce
       )
       .accountsPartial({
         computationAccount: getComputationAccAddress(
@@ -215,6 +821,7 @@ describe("Privacy-First Banking Demo", () => {
           ).readUInt32LE()
         ),
         userAccount: account2PDA,
+        programConfig: programConfigPda,
         payer: owner.publicKey,
       })
       .signers([owner])
@@ -247,12 +854,25 @@ describe("Privacy-First Banking Demo", () => {
 
     console.log(`Sending ${paymentAmount} units from Account 1 to Account 2...`);
     const receiverNewNonce = randomBytes(16);
+    const encryptedAmount = encryptU64(cipher, paymentAmount);
+    const encryptedTip = encryptU64(cipher, 0);
+    account1State = await program.account.userAccount.fetch(account1PDA);
     const paymentSig = await program.methods
       .processPayment(
         paymentOffset,
         new anchor.BN(transactionId.toString()),
-        new anchor.BN(paymentAmount),
-        new anchor.BN(deserializeLE(receiverNewNonce).toString())
+        encryptedAmount.ciphertext,
+        encryptedAmount.nonce,
+        Array.from(publicKey),
+        new anchor.BN(deserializeLE(receiverNewNonce).toString()),
+        encryptedTip.ciphertext,
+        encryptedTip.nonce,
+        randomNonce(),
+        randomNonce(),
+        0,
+        noMemo.ciphertext,
+        noMemo.nonce,
+        account1State.sequence
       )
       .accountsPartial({
         computationAccount: getComputationAccAddress(
@@ -269,7 +889,12 @@ describe("Privacy-First Banking Demo", () => {
         ),
         senderAccount: account1PDA,
         receiverAccount: account2PDA,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
         transaction: transactionPDA,
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
         payer: owner.publicKey,
       })
       .signers([owner])
@@ -288,6 +913,11 @@ describe("Privacy-First Banking Demo", () => {
     const paymentProcessedEvent = await paymentProcessedEventPromise;
     console.log("Received PaymentProcessedEvent.");
     console.log("Transaction ID:", paymentProcessedEvent.transactionId.toString());
+    const [senderNewBalance] = cipher.decrypt(
+      [paymentProcessedEvent.senderNewBalance],
+      new Uint8Array(paymentProcessedEvent.senderNewBalanceNonce.toArray("le", 16))
+    );
+    expect(senderNewBalance).to.equal(BigInt(initialBalance1 - paymentAmount));
 
     const transactionState = await program.account.transaction.fetch(
       transactionPDA
@@ -297,6 +927,7 @@ describe("Privacy-First Banking Demo", () => {
 
     // --- Check Balance for Account 1 ---
     console.log("\n=== Checking Balance for Account 1 ===");
+    await openSession(account1PDA, 600);
     const checkBalanceOffset = new anchor.BN(randomBytes(8));
     const balanceThreshold = 5000; // Check if balance > 5000
 
@@ -307,7 +938,8 @@ describe("Privacy-First Banking Demo", () => {
       .checkBalance(
         checkBalanceOffset,
         new anchor.BN(account1Id.toString()),
-        new anchor.BN(balanceThreshold)
+        new anchor.BN(balanceThreshold),
+        0
       )
       .accountsPartial({
         computationAccount: getComputationAccAddress(
@@ -323,6 +955,7 @@ describe("Privacy-First Banking Demo", () => {
           Buffer.from(getCompDefAccOffset("check_balance")).readUInt32LE()
         ),
         userAccount: account1PDA,
+        sessionAccount: sessionAccountPda(account1PDA),
         payer: owner.publicKey,
       })
       .signers([owner])
@@ -368,6 +1001,8 @@ describe("Privacy-First Banking Demo", () => {
           Buffer.from(getCompDefAccOffset("calculate_rewards")).readUInt32LE()
         ),
         userAccount: account1PDA,
+        sessionAccount: sessionAccountPda(account1PDA),
+        programConfig: programConfigPda,
         payer: owner.publicKey,
       })
       .signers([owner])
@@ -399,7 +1034,4568 @@ describe("Privacy-First Banking Demo", () => {
     console.log("✅ All balances remain encrypted on-chain");
   });
 
-  // --- Helper Functions for Computation Definition Initialization ---
+  it("Should open and refresh an idle-timeout session", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    const sessionPda = sessionAccountPda(account.pda);
+
+    await openSession(account.pda, 4);
+    const opened = await program.account.sessionAccount.fetch(sessionPda);
+    expect(opened.idleTimeout.toNumber()).to.equal(4);
+
+    await sleep(2000);
+
+    await refreshSession(account.pda);
+    const refreshed = await program.account.sessionAccount.fetch(sessionPda);
+    expect(refreshed.lastActiveTs.toNumber()).to.be.greaterThan(
+      opened.lastActiveTs.toNumber()
+    );
+
+    // A lapsed session cannot be refreshed, only opened again
+    await sleep(6000);
+    try {
+      await refreshSession(account.pda);
+      expect.fail("an expired session must not be refreshed");
+    } catch (e) {
+      expect(e.toString()).to.include("SessionExpired");
+    }
+    await openSession(account.pda, 4);
+    const reopened = await program.account.sessionAccount.fetch(sessionPda);
+    expect(reopened.lastActiveTs.toNumber()).to.be.greaterThan(
+      refreshed.lastActiveTs.toNumber()
+    );
+  });
+
+  it("Should aggregate loyalty across three accounts of one owner", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef(
+      "compute_aggregate_loyalty",
+      "initComputeAggregateLoyaltyCompDef"
+    );
+    const { publicKey, cipher } = await setupCipher();
+    const primary = await createUserAccount(12000, publicKey);
+    const second = await createUserAccount(6000, publicKey);
+    const third = await createUserAccount(500, publicKey);
+    // The primary's single transaction is the only one across the three
+    await sendPayment(primary.pda, third.pda, 100, cipher);
+    await openSession(primary.pda, 600);
+
+    const aggregateLoyalty = async (linked = [second.pda, third.pda]) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const loyaltyEventPromise = awaitEvent("aggregateLoyaltyEvent");
+      await program.methods
+        .computeAggregateLoyalty(offset)
+        .accountsPartial({
+          ...queueAccounts(offset, "compute_aggregate_loyalty"),
+          userAccount: primary.pda,
+          sessionAccount: sessionAccountPda(primary.pda),
+          payer: owner.publicKey,
+          programConfig: programConfigPda,
+        })
+        .remainingAccounts(
+          linked.map((pubkey) => ({
+            pubkey,
+            isSigner: false,
+            isWritable: false,
+          }))
+        )
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+
+      const loyaltyEvent = await loyaltyEventPromise;
+      expect(loyaltyEvent.primaryAccountId.toString()).to.equal(
+        primary.accountId.toString()
+      );
+      return loyaltyEvent.loyaltyTier;
+    };
+    const setRewardConfig = (
+      pointsPerTx: bigint,
+      thresholds: [number, number, number]
+    ) =>
+      program.methods
+        .updateRewardConfig({
+          pointsPerTx: toBN(pointsPerTx),
+          silverThreshold: toBN(thresholds[0]),
+          goldThreshold: toBN(thresholds[1]),
+          premiumThreshold: toBN(thresholds[2]),
+          thresholdDecimals: 2,
+        })
+        .accountsPartial({
+          admin: owner.publicKey,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    try {
+      // Defaults: 10 points + Premium (100) + Gold (50) + Basic (0) = 160 -> tier 1
+      expect(await aggregateLoyalty()).to.equal(1);
+
+      // 400 points per transaction: 400 + 150 = 550 -> tier 2
+      await setRewardConfig(BigInt(400), [1000, 5000, 10000]);
+      expect(await aggregateLoyalty()).to.equal(2);
+
+      // Raised thresholds leave only the primary in Silver: 10 + 25 -> tier 0
+      await setRewardConfig(BigInt(10), [10000, 20000, 30000]);
+      expect(await aggregateLoyalty()).to.equal(0);
+
+      // A rate near u64::MAX must not wrap the score back to a low tier
+      await setRewardConfig(BigInt("18446744073709551615"), [1000, 5000, 10000]);
+      expect(await aggregateLoyalty()).to.equal(3);
+    } finally {
+      await setRewardConfig(BigInt(10), [1000, 5000, 10000]);
+    }
+
+    // One set of thresholds is scaled for the primary, so decimals must agree
+    const sixDecimals = await createUserAccount(500, publicKey, owner, 0, 6);
+    try {
+      await aggregateLoyalty([sixDecimals.pda]);
+      expect.fail("accounts with other decimals cannot be aggregated");
+    } catch (e) {
+      expect(e.toString()).to.include("DecimalsMismatch");
+    }
+  });
+
+  it("Should reject a user account that does not match the account id", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const target = await createUserAccount(1000, publicKey);
+    const unrelated = await createUserAccount(1000, publicKey);
+
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .checkBalance(offset, toBN(target.accountId), toBN(500), 0)
+        .accountsPartial({
+          ...queueAccounts(offset, "check_balance"),
+          userAccount: unrelated.pda,
+          sessionAccount: sessionAccountPda(unrelated.pda),
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("check_balance should reject a mismatched user account");
+    } catch (e) {
+      expect(e.toString()).to.include("ConstraintSeeds");
+    }
+  });
+
+  it("Should refuse a callback result routed to a different account", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const target = await createUserAccount(1000, publicKey);
+    const unrelated = await createUserAccount(0, publicKey);
+
+    // A result computed for the target, delivered to the unrelated account
+    let emitted = false;
+    const listenerId = program.addEventListener("balanceCheckEvent", (event) => {
+      if (event.accountId.toString() === unrelated.accountId.toString()) emitted = true;
+    });
+    try {
+      await program.methods
+        .checkBalanceCallback({
+          success: {
+            0: { field0: { field0: true, field1: toBN(target.accountId) } },
+          },
+        } as any)
+        .accountsPartial({
+          arciumProgram: getArciumProgAddress(),
+          compDefAccount: compDefAccount("check_balance"),
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          userAccount: unrelated.pda,
+        })
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a result for another account must not be accepted");
+    } catch (e) {
+      expect(e.toString()).to.not.include("a result for another account");
+    }
+    await program.removeEventListener(listenerId);
+    expect(emitted).to.be.false;
+
+    // Routed through the cluster, each account still gets its own result
+    expect(await checkBalanceAbove(target, 500)).to.be.true;
+    expect(await checkBalanceAbove(unrelated, 500)).to.be.false;
+  });
+
+  it("Should initialize an account through the built circuit", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+
+    const initializedEventPromise = awaitEvent("accountInitializedEvent");
+    const account = await createUserAccount(2500, publicKey);
+    const initializedEvent = await initializedEventPromise;
+
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(state.accountState).to.deep.equal({ active: {} });
+    expect(initializedEvent.accountId.toString()).to.equal(
+      account.accountId.toString()
+    );
+    expect(state.ownerEncPubkey).to.deep.equal(Array.from(publicKey));
+  });
+
+  it("Should pay out of a balance set by initialization", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1200, publicKey);
+    const receiver = await createUserAccount(300, publicKey);
+
+    // The circuits read the MXE copy written by the init callback, so the very
+    // first payment must already see the initial balances
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 700, cipher);
+    const transaction = await program.account.transaction.fetch(
+      transactionPda(transactionId)
+    );
+    expect(transaction.status).to.deep.equal({ completed: {} });
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+    expect(await checkBalanceAbove(sender, 500)).to.be.true;
+    expect(await checkBalanceAbove(sender, 501)).to.be.false;
+  });
+
+  it("Should persist balances across sequential payments", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(2000, publicKey);
+
+    await sendPayment(sender.pda, receiver.pda, 1000, cipher);
+    await sendPayment(sender.pda, receiver.pda, 500, cipher);
+
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(3500));
+    const senderState = await program.account.userAccount.fetch(sender.pda);
+    const receiverState = await program.account.userAccount.fetch(
+      receiver.pda
+    );
+    expect(senderState.transactionCount.toNumber()).to.equal(2);
+    expect(receiverState.transactionCount.toNumber()).to.equal(2);
+  });
+
+  it("Should charge the configured payment fee", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    // 1% + 10 flat on payments; withdrawals are priced differently
+    await setFeeSchedule({
+      ...zeroFeeSchedule(),
+      payment: feeRule(100, 10),
+      withdrawal: feeRule(0, 50),
+    });
+    try {
+      await sendPayment(sender.pda, receiver.pda, 1000, cipher);
+    } finally {
+      await setFeeSchedule(zeroFeeSchedule());
+    }
+
+    // Sender debited 1000 + 20 fee, receiver credited exactly 1000
+    expect(await checkBalanceAbove(sender, 3980)).to.be.true;
+    expect(await checkBalanceAbove(sender, 3981)).to.be.false;
+    expect(await checkBalanceAbove(receiver, 1000)).to.be.true;
+    expect(await checkBalanceAbove(receiver, 1001)).to.be.false;
+  });
+
+  it("Should debit the sender's tip into the validator reward pool", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    await sendPayment(sender.pda, receiver.pda, 1000, cipher, 200);
+
+    // Sender pays 1000 + 200 tip, receiver gets only the amount
+    expect(await checkBalanceAbove(sender, 3800)).to.be.true;
+    expect(await checkBalanceAbove(sender, 3801)).to.be.false;
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+    const rewards = await program.account.validatorRewards.fetch(
+      validatorRewardsPda
+    );
+    expect(rewards.initialized).to.be.true;
+  });
+
+  it("Should reject a payment to the sending account itself", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(5000, publicKey);
+    const before = await program.account.userAccount.fetch(account.pda);
+
+    try {
+      await sendPayment(account.pda, account.pda, 1000, cipher);
+      expect.fail("a self-transfer must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("SelfTransfer");
+    }
+
+    const after = await program.account.userAccount.fetch(account.pda);
+    expect(after.mxeBalanceNonce.toString()).to.equal(
+      before.mxeBalanceNonce.toString()
+    );
+  });
+
+  it("Should refuse a payment that would overflow the receiver balance", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const maxU64 = BigInt("18446744073709551615");
+    const receiverId = randomId();
+    const receiverPda = userAccountPda(receiverId);
+    const initOffset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .initializeUserAccount(
+        initOffset,
+        toBN(receiverId),
+        toBN(maxU64 - BigInt(10)),
+        randomNonce(),
+        Array.from(publicKey),
+        randomNonce(),
+        0,
+        2,
+        noLabel.ciphertext,
+        noLabel.nonce
+      )
+      .accountsPartial({
+        ...queueAccounts(initOffset, "initialize_accounts"),
+        userAccount: receiverPda,
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(initOffset);
+    const before = await program.account.userAccount.fetch(receiverPda);
+
+    // 10 still fits exactly below u64::MAX; 11 would wrap
+    const transactionId = await sendPayment(sender.pda, receiverPda, 11, cipher);
+    const transaction = await program.account.transaction.fetch(
+      transactionPda(transactionId)
+    );
+    expect(transaction.status).to.not.deep.equal({ completed: {} });
+    const after = await program.account.userAccount.fetch(receiverPda);
+    expect(after.mxeEncryptedBalance).to.deep.equal(before.mxeEncryptedBalance);
+    expect(await checkBalanceAbove(sender, 5000)).to.be.true;
+
+    await sendPayment(sender.pda, receiverPda, 10, cipher);
+    expect(await decryptBalance(cipher, receiverPda)).to.equal(maxU64);
+  });
+
+  it("Should keep the transfer amount out of the instruction data", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(500000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const amount = 314159;
+
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    const signature = await program.methods
+      .processPayment(
+        ...processPaymentArgs(
+          offset,
+          transactionId,
+          amount,
+          cipher,
+          Array.from(publicKey)
+        )
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const plaintext = Buffer.alloc(8);
+    plaintext.writeBigUInt64LE(BigInt(amount));
+    for (const ix of tx.transaction.message.compiledInstructions) {
+      expect(Buffer.from(ix.data).includes(plaintext)).to.be.false;
+    }
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(amount));
+  });
+
+  it("Should store the transfer amount encrypted to the sender", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const transactionId = await sendPayment(
+      sender.pda,
+      receiver.pda,
+      1234,
+      cipher
+    );
+
+    const transaction = await program.account.transaction.fetch(
+      transactionPda(transactionId)
+    );
+    const [amount] = cipher.decrypt(
+      [transaction.encryptedAmount],
+      new Uint8Array(transaction.amountNonce.toArray("le", 16))
+    );
+    expect(amount).to.equal(BigInt(1234));
+  });
+
+  it("Should flag high gross AML throughput", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("check_aml_velocity", "initCheckAmlVelocityCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const alice = await createUserAccount(10000, publicKey);
+    const bob = await createUserAccount(0, publicKey);
+
+    const outgoing = await sendPayment(alice.pda, bob.pda, 3000, cipher);
+    const incoming = await sendPayment(bob.pda, alice.pda, 2500, cipher);
+
+    await setAmlSettings(3600, 5000);
+    try {
+      // 3000 out stays under the threshold; 3000 out + 2500 in crosses it
+      expect(await checkVelocity(alice.pda, outgoing)).to.be.false;
+      expect(await checkVelocity(alice.pda, incoming)).to.be.true;
+
+      try {
+        await checkVelocity(alice.pda, outgoing);
+        expect.fail("a transaction must only be counted once per party");
+      } catch (e) {
+        expect(e.toString()).to.include("AmlAlreadyRecorded");
+      }
+    } finally {
+      await setAmlSettings(86400, BigInt("18446744073709551615"));
+    }
+  });
+
+  it("Should open AML windows by transaction time, not check time", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("check_aml_velocity", "initCheckAmlVelocityCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const alice = await createUserAccount(10000, publicKey);
+    const bob = await createUserAccount(0, publicKey);
+
+    // Two payments made more than one window apart, checked back to back
+    const first = await sendPayment(alice.pda, bob.pda, 3000, cipher);
+    await sleep(3000);
+    const second = await sendPayment(alice.pda, bob.pda, 2500, cipher);
+
+    await setAmlSettings(2, 5000);
+    try {
+      expect(await checkVelocity(alice.pda, first)).to.be.false;
+      // Counted in a window of its own, so 2500 alone is not flagged
+      expect(await checkVelocity(alice.pda, second)).to.be.false;
+    } finally {
+      await setAmlSettings(86400, BigInt("18446744073709551615"));
+    }
+  });
+
+  it("Should leave the balance untouched on a failed withdrawal", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("withdraw", "initWithdrawCompDef");
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    await openSession(account.pda, 600);
+    const before = await program.account.userAccount.fetch(account.pda);
+
+    const failedEventPromise = awaitEvent("withdrawalFailedEvent");
+    await withdraw(account, 5000);
+    const failedEvent = await failedEventPromise;
+    expect(failedEvent.accountId.toString()).to.equal(
+      account.accountId.toString()
+    );
+
+    const after = await program.account.userAccount.fetch(account.pda);
+    expect(after.mxeEncryptedBalance).to.deep.equal(before.mxeEncryptedBalance);
+    expect(after.mxeBalanceNonce.toString()).to.equal(
+      before.mxeBalanceNonce.toString()
+    );
+  });
+
+  it("Should gate withdrawals on an active session and charge the withdrawal fee", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("withdraw", "initWithdrawCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    await openSession(account.pda, 2);
+
+    // Flat 50 on withdrawals, payments are priced differently
+    await setFeeSchedule({
+      ...zeroFeeSchedule(),
+      payment: feeRule(100, 10),
+      withdrawal: feeRule(0, 50),
+    });
+    try {
+      await withdraw(account, 100);
+
+      await sleep(3000);
+      try {
+        await withdraw(account, 100);
+        expect.fail("withdraw should require an active session");
+      } catch (e) {
+        expect(e.toString()).to.include("SessionExpired");
+      }
+
+      await openSession(account.pda, 600);
+      await withdraw(account, 100);
+    } finally {
+      await setFeeSchedule(zeroFeeSchedule());
+    }
+
+    // Two withdrawals of 100 + 50 fee each
+    expect(await checkBalanceAbove(account, 700)).to.be.true;
+    expect(await checkBalanceAbove(account, 701)).to.be.false;
+    expect(await decryptBalance(cipher, account.pda)).to.equal(BigInt(700));
+  });
+
+  it("Should recover an account with a 2-of-3 guardian quorum", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("recover_account", "initRecoverAccountCompDef");
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    const guardiansPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("guardians"), account.pda.toBuffer()],
+      program.programId
+    )[0];
+    const guardians = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+
+    await program.methods
+      .setGuardians(
+        guardians.map((g) => g.publicKey),
+        2
+      )
+      .accountsPartial({
+        owner: owner.publicKey,
+        userAccount: account.pda,
+        guardians: guardiansPda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    const newOwner = Keypair.generate();
+    const { publicKey: newEncPubkey, cipher: newCipher } = await setupCipher();
+    const approve = (guardian: Keypair) =>
+      program.methods
+        .approveRecovery(newOwner.publicKey, Array.from(newEncPubkey))
+        .accountsPartial({
+          guardian: guardian.publicKey,
+          userAccount: account.pda,
+          guardians: guardiansPda,
+        })
+        .signers([guardian])
+        .rpc({ commitment: "confirmed" });
+
+    const recover = async () => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .recoverAccount(offset, toBN(account.accountId), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "recover_account"),
+          userAccount: account.pda,
+          guardians: guardiansPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    await approve(guardians[0]);
+    try {
+      await recover();
+      expect.fail("one approval must not meet a quorum of two");
+    } catch (e) {
+      expect(e.toString()).to.include("RecoveryQuorumNotMet");
+    }
+
+    await approve(guardians[1]);
+    const recoveredEventPromise = awaitEvent("accountRecoveredEvent");
+    await recover();
+    const recoveredEvent = await recoveredEventPromise;
+
+    const recovered = await program.account.userAccount.fetch(account.pda);
+    expect(recovered.ownerPubkey.toBase58()).to.equal(
+      newOwner.publicKey.toBase58()
+    );
+    expect(recovered.ownerEncPubkey).to.deep.equal(Array.from(newEncPubkey));
+    const [balance] = newCipher.decrypt(
+      [recoveredEvent.encryptedBalance],
+      new Uint8Array(recoveredEvent.balanceNonce.toArray("le", 16))
+    );
+    expect(balance).to.equal(BigInt(1000));
+  });
+
+  it("Should freeze the guardian set while a recovery is in flight", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("recover_account", "initRecoverAccountCompDef");
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    const guardiansPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("guardians"), account.pda.toBuffer()],
+      program.programId
+    )[0];
+    const guardians = [Keypair.generate(), Keypair.generate()];
+    const setGuardians = () =>
+      program.methods
+        .setGuardians(
+          guardians.map((g) => g.publicKey),
+          2
+        )
+        .accountsPartial({
+          owner: owner.publicKey,
+          userAccount: account.pda,
+          guardians: guardiansPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    await setGuardians();
+
+    const newOwner = Keypair.generate();
+    const { publicKey: newEncPubkey } = await setupCipher();
+    const approve = (guardian: Keypair) =>
+      program.methods
+        .approveRecovery(newOwner.publicKey, Array.from(newEncPubkey))
+        .accountsPartial({
+          guardian: guardian.publicKey,
+          userAccount: account.pda,
+          guardians: guardiansPda,
+        })
+        .signers([guardian])
+        .rpc({ commitment: "confirmed" });
+    const queueRecover = async () => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .recoverAccount(offset, toBN(account.accountId), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "recover_account"),
+          userAccount: account.pda,
+          guardians: guardiansPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      return offset;
+    };
+
+    // Replacing the guardians discards the approvals gathered so far
+    await approve(guardians[0]);
+    await approve(guardians[1]);
+    await setGuardians();
+    try {
+      await queueRecover();
+      expect.fail("approvals must not survive a new guardian set");
+    } catch (e) {
+      expect(e.toString()).to.include("RecoveryQuorumNotMet");
+    }
+
+    await approve(guardians[0]);
+    await approve(guardians[1]);
+    const recoveredEventPromise = awaitEvent("accountRecoveredEvent");
+    const offset = await queueRecover();
+    try {
+      await setGuardians();
+      expect.fail("guardians must not change under a queued recovery");
+    } catch (e) {
+      expect(e.toString()).to.include("RecoveryInProgress");
+    }
+    try {
+      await approve(guardians[0]);
+      expect.fail("approvals must not change under a queued recovery");
+    } catch (e) {
+      expect(e.toString()).to.include("RecoveryInProgress");
+    }
+
+    await finalize(offset);
+    await recoveredEventPromise;
+    const recovered = await program.account.userAccount.fetch(account.pda);
+    expect(recovered.ownerPubkey.toBase58()).to.equal(
+      newOwner.publicKey.toBase58()
+    );
+    const record = await program.account.guardians.fetch(guardiansPda);
+    expect(record.recoveryQueuedAt.toNumber()).to.equal(0);
+  });
+
+  it("Should top up an encrypted balance with a deposit", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("deposit", "initDepositCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+
+    const offset = new anchor.BN(randomBytes(8));
+    const depositEventPromise = awaitEvent("depositEvent");
+    await program.methods
+      .deposit(offset, toBN(account.accountId), toBN(750), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "deposit"),
+        userAccount: account.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const depositEvent = await depositEventPromise;
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(depositEvent.newBalanceNonce.toString()).to.equal(
+      state.mxeBalanceNonce.toString()
+    );
+    expect(await checkBalanceAbove(account, 1750)).to.be.true;
+    expect(await checkBalanceAbove(account, 1751)).to.be.false;
+    // The owner-readable copy moves with it
+    expect(await decryptBalance(cipher, account.pda)).to.equal(BigInt(1750));
+  });
+
+  it("Should only let the admin credit a balance without tokens", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("deposit", "initDepositCompDef");
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    const intruder = await fundedKeypair();
+
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .deposit(offset, toBN(account.accountId), toBN(750), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "deposit"),
+          userAccount: account.pda,
+          payer: intruder.publicKey,
+        })
+        .signers([intruder])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("only the admin may mint without tokens");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+    expect(await checkBalanceAbove(account, 1001)).to.be.false;
+  });
+
+  it("Should pay a batch of receivers all or nothing", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("batch_payment", "initBatchPaymentCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+
+    const payBatch = async (receivers: PublicKey[], amounts: number[]) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .batchPayment(
+          offset,
+          receivers.map((receiver, i) => ({
+            receiver,
+            amount: toBN(amounts[i]),
+            receiverNewNonce: randomNonce(),
+          }))
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "batch_payment"),
+          senderAccount: sender.pda,
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .remainingAccounts(
+          receivers.map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    const receivers = [];
+    for (let i = 0; i < 3; i++) {
+      receivers.push((await createUserAccount(0, publicKey)).pda);
+    }
+    const before = await Promise.all(
+      receivers.map((pda) => program.account.userAccount.fetch(pda))
+    );
+
+    // 1200 in total exceeds the balance of 1000, so nobody gets paid
+    const failedEventPromise = awaitEvent("batchPaymentFailedEvent");
+    await payBatch(receivers, [400, 400, 400]);
+    await failedEventPromise;
+    for (let i = 0; i < receivers.length; i++) {
+      const after = await program.account.userAccount.fetch(receivers[i]);
+      expect(after.mxeEncryptedBalance).to.deep.equal(
+        before[i].mxeEncryptedBalance
+      );
+      expect(after.transactionCount.toNumber()).to.equal(0);
+    }
+
+    await payBatch(receivers, [300, 200, 100]);
+    expect(await decryptBalance(cipher, receivers[0])).to.equal(BigInt(300));
+    expect(await decryptBalance(cipher, receivers[1])).to.equal(BigInt(200));
+    expect(await decryptBalance(cipher, receivers[2])).to.equal(BigInt(100));
+  });
+
+  it("Should cap and revoke delegated spending", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("set_spending_cap", "initSetSpendingCapCompDef");
+    await initCompDef("delegated_payment", "initDelegatedPaymentCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const delegate = await fundedKeypair();
+    const spendingDelegatePda = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("spending_delegate"),
+        account.pda.toBuffer(),
+        delegate.publicKey.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+    const grantOffset = new anchor.BN(randomBytes(8));
+    const capNonce = randomBytes(16);
+    const [capCiphertext] = cipher.encrypt([BigInt(1000)], capNonce);
+    await program.methods
+      .grantSpendingDelegate(
+        grantOffset,
+        toBN(account.accountId),
+        delegate.publicKey,
+        Array.from(capCiphertext),
+        new anchor.BN(deserializeLE(capNonce).toString()),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(grantOffset, "set_spending_cap"),
+        userAccount: account.pda,
+        spendingDelegate: spendingDelegatePda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(grantOffset);
+    expect(
+      (await program.account.spendingDelegate.fetch(spendingDelegatePda)).active
+    ).to.be.true;
+
+    const spend = async (amount: number) => {
+      const transactionId = randomId();
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .delegatedPayment(
+          offset,
+          toBN(transactionId),
+          toBN(amount),
+          randomNonce(),
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "delegated_payment"),
+          senderAccount: account.pda,
+          receiverAccount: receiver.pda,
+          spendingDelegate: spendingDelegatePda,
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          payer: delegate.publicKey,
+        })
+        .signers([delegate])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      return transactionId;
+    };
+
+    await spend(600);
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(600));
+
+    // 600 + 500 exceeds the cap of 1000 even though the balance covers it
+    const rejected = await spend(500);
+    const rejectedTx = await program.account.transaction.fetch(
+      transactionPda(rejected)
+    );
+    expect(rejectedTx.status).to.not.deep.equal({ completed: {} });
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(600));
+
+    await program.methods
+      .revokeSpendingDelegate()
+      .accountsPartial({
+        owner: owner.publicKey,
+        userAccount: account.pda,
+        spendingDelegate: spendingDelegatePda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    try {
+      await spend(100);
+      expect.fail("a revoked delegate must not be able to spend");
+    } catch (e) {
+      expect(e.toString()).to.include("AccountNotInitialized");
+    }
+  });
+
+  it("Should reject payments from a frozen account until unfrozen", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const setFrozen = async (frozen: boolean) => {
+      const method = frozen
+        ? program.methods.freezeAccount(toBN(sender.accountId))
+        : program.methods.unfreezeAccount(toBN(sender.accountId));
+      await method
+        .accountsPartial({
+          authority: owner.publicKey,
+          userAccount: sender.pda,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    };
+
+    const frozenEventPromise = awaitEvent("accountFrozenEvent");
+    await setFrozen(true);
+    await frozenEventPromise;
+    const state = await program.account.userAccount.fetch(sender.pda);
+    expect(state.accountState).to.deep.equal({ frozen: {} });
+
+    try {
+      await sendPayment(sender.pda, receiver.pda, 1000, cipher);
+      expect.fail("a frozen sender must not be able to pay");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidAccountState");
+    }
+    try {
+      await checkBalanceAbove(sender, 1000);
+      expect.fail("balance checks must reject a frozen account");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidAccountState");
+    }
+
+    await setFrozen(false);
+    await sendPayment(sender.pda, receiver.pda, 1000, cipher);
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should combine every premium criterion into one eligibility bit", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef(
+      "check_premium_eligibility",
+      "initCheckPremiumEligibilityCompDef"
+    );
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    // Leaves a balance of 4000 and one transaction
+    await sendPayment(account.pda, receiver.pda, 1000, cipher);
+    await openSession(account.pda, 600);
+
+    const isEligible = async (
+      minBalance: number,
+      minTransactions: number,
+      minAgeSeconds: number
+    ) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const eventPromise = awaitEvent("premiumEligibilityEvent");
+      await program.methods
+        .checkPremiumEligibility(
+          offset,
+          toBN(account.accountId),
+          toBN(minBalance),
+          toBN(minTransactions),
+          toBN(minAgeSeconds)
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "check_premium_eligibility"),
+          userAccount: account.pda,
+          sessionAccount: sessionAccountPda(account.pda),
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      return (await eventPromise).eligible;
+    };
+
+    expect(await isEligible(4000, 1, 0)).to.be.true;
+    expect(await isEligible(4001, 1, 0)).to.be.false;
+    expect(await isEligible(4000, 2, 0)).to.be.false;
+    expect(await isEligible(4000, 1, 86400)).to.be.false;
+  });
+
+  it("Should award the balance tier bonus and report the tier", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const premium = await createUserAccount(10000, publicKey);
+    const basic = await createUserAccount(500, publicKey);
+
+    // Neither account has transactions, so the points are the bonus alone
+    const premiumEvent = await calculateRewards(premium);
+    expect(premiumEvent.tier).to.equal(3);
+    expect(premiumEvent.rewardPoints.toNumber()).to.equal(100);
+
+    const basicEvent = await calculateRewards(basic);
+    expect(basicEvent.tier).to.equal(0);
+    expect(basicEvent.rewardPoints.toNumber()).to.equal(0);
+  });
+
+  it("Should redeem reward points into balance", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    await initCompDef("redeem_rewards", "initRedeemRewardsCompDef");
+    const { publicKey } = await setupCipher();
+    // No transactions, Premium tier: 100 points, i.e. one unit at the default rate
+    const account = await createUserAccount(10000, publicKey);
+    await calculateRewards(account);
+
+    const redeem = async (points: number) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .redeemRewards(offset, toBN(account.accountId), toBN(points))
+        .accountsPartial({
+          ...queueAccounts(offset, "redeem_rewards"),
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    try {
+      await redeem(200);
+      expect.fail("redeeming more points than owned must fail");
+    } catch (e) {
+      expect(e.toString()).to.include("InsufficientRewards");
+    }
+
+    await redeem(100);
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(state.rewardPoints.toNumber()).to.equal(0);
+    expect(await checkBalanceAbove(account, 10001)).to.be.true;
+    expect(await checkBalanceAbove(account, 10002)).to.be.false;
+  });
+
+  it("Should bucket balances into compliance brackets", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("check_balance_range", "initCheckBalanceRangeCompDef");
+    const { publicKey } = await setupCipher();
+
+    const bracketOf = async (balance: number) => {
+      const account = await createUserAccount(balance, publicKey);
+      const offset = new anchor.BN(randomBytes(8));
+      const eventPromise = awaitEvent("balanceRangeEvent");
+      await program.methods
+        .checkBalanceRange(offset, toBN(account.accountId), toBN(1000), toBN(5000))
+        .accountsPartial({
+          ...queueAccounts(offset, "check_balance_range"),
+          programConfig: programConfigPda,
+          userAccount: account.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      return (await eventPromise).bracket;
+    };
+
+    expect(await bracketOf(999)).to.equal(0);
+    expect(await bracketOf(1000)).to.equal(1);
+    expect(await bracketOf(5000)).to.equal(1);
+    expect(await bracketOf(5001)).to.equal(2);
+  });
+
+  it("Should emit ComputationAbortedEvent when a callback rejects its result", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("deposit", "initDepositCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    const before = await program.account.userAccount.fetch(account.pda);
+
+    // u64::MAX on top of 1000 overflows inside the circuit
+    const offset = new anchor.BN(randomBytes(8));
+    const abortedEventPromise = awaitEvent("computationAbortedEvent");
+    await program.methods
+      .deposit(
+        offset,
+        toBN(account.accountId),
+        toBN(BigInt("18446744073709551615")),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "deposit"),
+        userAccount: account.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const abortedEvent = await abortedEventPromise;
+    expect(abortedEvent.operation).to.equal("deposit");
+    expect(abortedEvent.accountId.toString()).to.equal(
+      account.accountId.toString()
+    );
+    const after = await program.account.userAccount.fetch(account.pda);
+    expect(after.inFlightCount).to.equal(before.inFlightCount);
+    expect(after.mxeEncryptedBalance).to.deep.equal(before.mxeEncryptedBalance);
+    expect(after.encryptedBalance).to.deep.equal(before.encryptedBalance);
+    expect(await checkBalanceAbove(account, 1000)).to.be.true;
+    expect(await checkBalanceAbove(account, 1001)).to.be.false;
+    expect(await decryptBalance(cipher, account.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should fail a payment whose computation aborts and release both accounts", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const senderBefore = await program.account.userAccount.fetch(sender.pda);
+    const receiverBefore = await program.account.userAccount.fetch(receiver.pda);
+
+    // An amount that does not fit a u64 fails the circuit's input range check,
+    // so the cluster aborts the computation instead of returning outputs
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    const args = processPaymentArgs(
+      offset,
+      transactionId,
+      0,
+      cipher,
+      Array.from(publicKey),
+      0,
+      0,
+      noMemo,
+      senderBefore.sequence
+    );
+    const amountNonce = randomBytes(16);
+    const [amountCiphertext] = cipher.encrypt(
+      [BigInt("18446744073709551616") + BigInt(1)],
+      amountNonce
+    );
+    const withAmount = [
+      ...args.slice(0, 2),
+      Array.from(amountCiphertext),
+      new anchor.BN(deserializeLE(amountNonce).toString()),
+      ...args.slice(4),
+    ] as unknown as typeof args;
+    const abortedEventPromise = awaitEvent("computationAbortedEvent");
+    await program.methods
+      .processPayment(...withAmount)
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const abortedEvent = await abortedEventPromise;
+    expect(abortedEvent.operation).to.equal("process_payment");
+    expect(abortedEvent.accountId.toString()).to.equal(sender.accountId.toString());
+    expect(
+      (await program.account.transaction.fetch(transactionPda(transactionId))).status
+    ).to.deep.equal({ failed: {} });
+    const senderAfter = await program.account.userAccount.fetch(sender.pda);
+    const receiverAfter = await program.account.userAccount.fetch(receiver.pda);
+    expect(senderAfter.inFlightCount).to.equal(senderBefore.inFlightCount);
+    expect(receiverAfter.inFlightCount).to.equal(receiverBefore.inFlightCount);
+    expect(senderAfter.mxeEncryptedBalance).to.deep.equal(
+      senderBefore.mxeEncryptedBalance
+    );
+    expect(receiverAfter.mxeEncryptedBalance).to.deep.equal(
+      receiverBefore.mxeEncryptedBalance
+    );
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(0));
+
+    // Released, so the sender can pay again straight away
+    await sendPayment(sender.pda, receiver.pda, 100, cipher);
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(100));
+  });
+
+  it("Should bootstrap the remaining pieces of a partial deployment", async () => {
+    const missing: string[] = [];
+    for (const circuit of bootstrapCircuits) {
+      if (!(await isCompDefInitialized(circuit))) missing.push(circuit);
+    }
+    const configBefore = await program.account.programConfig.fetch(
+      programConfigPda
+    );
+
+    await bootstrap(missing);
+    for (const circuit of bootstrapCircuits) {
+      expect(await isCompDefInitialized(circuit)).to.be.true;
+    }
+
+    // A second run has nothing left to do and must not fail
+    await bootstrap([]);
+    const configAfter = await program.account.programConfig.fetch(
+      programConfigPda
+    );
+    expect(configAfter.admin.toBase58()).to.equal(
+      configBefore.admin.toBase58()
+    );
+  });
+
+  it("Should only let the upgrade authority create the program config", async () => {
+    const intruder = await fundedKeypair();
+    try {
+      await program.methods
+        .initProgramConfig(zeroFeeSchedule())
+        .accountsPartial({
+          admin: intruder.publicKey,
+          programData: programDataPda,
+          programConfig: programConfigPda,
+        })
+        .signers([intruder])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("only the upgrade authority may become admin");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+  });
+
+  it("Should rotate the client encryption key of an account", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("rotate_encryption_key", "initRotateEncryptionKeyCompDef");
+    const { publicKey: oldPublicKey, cipher: oldCipher } = await setupCipher();
+    const account = await createUserAccount(1500, oldPublicKey);
+    expect(await decryptBalance(oldCipher, account.pda)).to.equal(BigInt(1500));
+
+    const rotate = async (clientPubkey: number[]) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .rotateEncryptionKey(
+          offset,
+          toBN(account.accountId),
+          clientPubkey,
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "rotate_encryption_key"),
+          userAccount: account.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    try {
+      await rotate(new Array(32).fill(0));
+      expect.fail("an all-zero encryption key must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidEncryptionPubkey");
+    }
+
+    const { publicKey: newPublicKey, cipher: newCipher } = await setupCipher();
+    const rotatedEventPromise = awaitEvent("encryptionKeyRotatedEvent");
+    await rotate(Array.from(newPublicKey));
+    await rotatedEventPromise;
+
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(state.ownerEncPubkey).to.deep.equal(Array.from(newPublicKey));
+    expect(await decryptBalance(oldCipher, account.pda)).to.not.equal(
+      BigInt(1500)
+    );
+    expect(await decryptBalance(newCipher, account.pda)).to.equal(BigInt(1500));
+  });
+
+  it("Should reject a payment that crosses the daily spend limit", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("set_daily_limit", "initSetDailyLimitCompDef");
+    await initCompDef("withdraw", "initWithdrawCompDef");
+    await initCompDef("sweep_balance", "initSweepBalanceCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const limit = encryptU64(cipher, 1000);
+    const limitOffset = new anchor.BN(randomBytes(8));
+    const limitSetEventPromise = awaitEvent("dailyLimitSetEvent");
+    await program.methods
+      .setDailyLimit(
+        limitOffset,
+        toBN(sender.accountId),
+        limit.ciphertext,
+        limit.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(limitOffset, "set_daily_limit"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(limitOffset);
+    await limitSetEventPromise;
+    expect(
+      (await program.account.userAccount.fetch(sender.pda)).hasDailyLimit
+    ).to.be.true;
+
+    await sendPayment(sender.pda, receiver.pda, 600, cipher);
+
+    // 600 + 500 exceeds the limit of 1000 although the balance covers it
+    const exceededEventPromise = awaitEvent("spendLimitExceededEvent");
+    const rejected = await sendPayment(sender.pda, receiver.pda, 500, cipher);
+    const exceededEvent = await exceededEventPromise;
+    expect(exceededEvent.transactionId.toString()).to.equal(rejected.toString());
+    const rejectedTx = await program.account.transaction.fetch(
+      transactionPda(rejected)
+    );
+    expect(rejectedTx.status).to.deep.equal({ failed: {} });
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(600));
+
+    // Exactly reaching the limit is still allowed
+    await sendPayment(sender.pda, receiver.pda, 400, cipher);
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+    expect(await checkBalanceAbove(sender, 4000)).to.be.true;
+    expect(await checkBalanceAbove(sender, 4001)).to.be.false;
+
+    // Only process_payment counts towards the limit, so the other debits refuse
+    await openSession(sender.pda, 600);
+    try {
+      await withdraw(sender, 100);
+      expect.fail("a withdrawal would bypass the daily limit");
+    } catch (e) {
+      expect(e.toString()).to.include("UnsupportedAccountSettings");
+    }
+    const sweepOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .sweepBalance(sweepOffset, randomNonce(), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(sweepOffset, "sweep_balance"),
+          sourceAccount: sender.pda,
+          destinationAccount: receiver.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a sweep would bypass the daily limit");
+    } catch (e) {
+      expect(e.toString()).to.include("UnsupportedAccountSettings");
+    }
+
+    // Only the owner may lift the limit, which lets them withdraw again
+    const stranger = await fundedKeypair();
+    try {
+      await program.methods
+        .clearDailyLimit(toBN(sender.accountId))
+        .accountsPartial({ owner: stranger.publicKey, userAccount: sender.pda })
+        .signers([stranger])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("only the owner may clear the daily limit");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+    const clearedEventPromise = awaitEvent("dailyLimitClearedEvent");
+    await program.methods
+      .clearDailyLimit(toBN(sender.accountId))
+      .accountsPartial({ owner: owner.publicKey, userAccount: sender.pda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await clearedEventPromise;
+    expect(
+      (await program.account.userAccount.fetch(sender.pda)).hasDailyLimit
+    ).to.be.false;
+    await withdraw(sender, 100);
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(3900));
+  });
+
+  it("Should reset the daily spend counter at the owner's local midnight", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("set_daily_limit", "initSetDailyLimitCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const limit = encryptU64(cipher, 1000);
+    const limitOffset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .setDailyLimit(
+        limitOffset,
+        toBN(sender.accountId),
+        limit.ciphertext,
+        limit.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(limitOffset, "set_daily_limit"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(limitOffset);
+
+    const setUtcOffset = (utcOffsetSeconds: number) =>
+      program.methods
+        .setUtcOffset(toBN(sender.accountId), new anchor.BN(utcOffsetSeconds))
+        .accountsPartial({ userAccount: sender.pda, owner: owner.publicKey })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    // Offset placing the owner's local midnight at unix time `midnight`
+    const offsetWithMidnightAt = (midnight: number) => {
+      const offset = (((-midnight) % 86400) + 86400) % 86400;
+      return offset > 43200 ? offset - 86400 : offset;
+    };
+
+    try {
+      await setUtcOffset(14 * 3600 + 1);
+      expect.fail("an offset beyond 14 hours should be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidUtcOffset");
+    }
+
+    await sendPayment(sender.pda, receiver.pda, 600, cipher);
+    const lastReset = (
+      await program.account.userAccount.fetch(sender.pda)
+    ).lastSpendReset.toNumber();
+
+    // Local midnight still twelve hours away: the 600 counts and 500 more is over
+    await setUtcOffset(offsetWithMidnightAt(lastReset + 43200));
+    const beforeMidnight = await sendPayment(sender.pda, receiver.pda, 500, cipher);
+    expect(
+      (await program.account.transaction.fetch(transactionPda(beforeMidnight)))
+        .status
+    ).to.deep.equal({ failed: {} });
+
+    // Local midnight just passed: the counter starts again from zero
+    await setUtcOffset(offsetWithMidnightAt(lastReset + 1));
+    const afterMidnight = await sendPayment(sender.pda, receiver.pda, 500, cipher);
+    expect(
+      (await program.account.transaction.fetch(transactionPda(afterMidnight)))
+        .status
+    ).to.deep.equal({ completed: {} });
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1100));
+  });
+
+  it("Should return the sender's new balance encrypted to their key", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const processedEventPromise = awaitEvent("paymentProcessedEvent");
+    await sendPayment(sender.pda, receiver.pda, 1250, cipher, 50);
+    const processedEvent = await processedEventPromise;
+
+    const [senderBalance] = cipher.decrypt(
+      [processedEvent.senderNewBalance],
+      new Uint8Array(processedEvent.senderNewBalanceNonce.toArray("le", 16))
+    );
+    expect(senderBalance).to.equal(BigInt(5000 - 1250 - 50));
+    // The same copy is stored as the sender's owner-readable balance
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(senderBalance);
+  });
+
+  it("Should reveal the balance to its owner under a fresh nonce", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("reveal_balance_to_owner", "initRevealBalanceToOwnerCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(4321, publicKey);
+    await openSession(account.pda, 600);
+
+    const reveal = async (nonce: anchor.BN) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const revealedEventPromise = awaitEvent("balanceRevealedEvent");
+      await revealBalance(account, offset, nonce);
+      await finalize(offset);
+      return revealedEventPromise;
+    };
+
+    const first = await reveal(randomNonce());
+    const second = await reveal(randomNonce());
+    expect(first.encryptedBalance).to.not.deep.equal(second.encryptedBalance);
+    for (const event of [first, second]) {
+      const [balance] = cipher.decrypt(
+        [event.encryptedBalance],
+        new Uint8Array(event.balanceNonce.toArray("le", 16))
+      );
+      expect(balance).to.equal(BigInt(4321));
+    }
+  });
+
+  it("Should refuse to reveal the balance once the session has expired", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("reveal_balance_to_owner", "initRevealBalanceToOwnerCompDef");
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+    await openSession(account.pda, 2);
+
+    await sleep(3000);
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await revealBalance(account, offset, randomNonce());
+      expect.fail("reveal_balance_to_owner should require an active session");
+    } catch (e) {
+      expect(e.toString()).to.include("SessionExpired");
+    }
+  });
+
+  it("Should compound interest only over elapsed periods", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("accrue_interest", "initAccrueInterestCompDef");
+    const { publicKey } = await setupCipher();
+
+    const setInterestPeriod = (seconds: number) =>
+      program.methods
+        .updateInterestPeriod(toBN(seconds))
+        .accountsPartial({
+          admin: owner.publicKey,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    const accrue = async (account: { accountId: bigint; pda: PublicKey }) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const accruedEventPromise = awaitEvent("interestAccruedEvent");
+      await program.methods
+        .accrueInterest(offset, toBN(account.accountId), 1000)
+        .accountsPartial({
+          ...queueAccounts(offset, "accrue_interest"),
+          programConfig: programConfigPda,
+          userAccount: account.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      return (await accruedEventPromise).periods;
+    };
+
+    await setInterestPeriod(86400);
+    const account = await createUserAccount(1000, publicKey);
+
+    // Less than a day since opening, so nothing accrues even at 10% per period
+    expect(await accrue(account)).to.equal(0);
+    expect(await checkBalanceAbove(account, 1000)).to.be.true;
+    expect(await checkBalanceAbove(account, 1001)).to.be.false;
+
+    await setInterestPeriod(1);
+    try {
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      expect(await accrue(account)).to.be.greaterThan(0);
+    } finally {
+      await setInterestPeriod(86400);
+    }
+    expect(await checkBalanceAbove(account, 1100)).to.be.true;
+  });
+
+  it("Should reject balance queries signed by someone other than the owner", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(3000, publicKey);
+    const stranger = await fundedKeypair();
+    await openSession(account.pda, 600);
+
+    const checkOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .checkBalance(checkOffset, toBN(account.accountId), toBN(1000), 0)
+        .accountsPartial({
+          ...queueAccounts(checkOffset, "check_balance"),
+          userAccount: account.pda,
+          sessionAccount: sessionAccountPda(account.pda),
+          payer: stranger.publicKey,
+        })
+        .signers([stranger])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a non-owner must not query the balance threshold");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+
+    const rewardsOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .calculateRewards(rewardsOffset, toBN(account.accountId))
+        .accountsPartial({
+          ...queueAccounts(rewardsOffset, "calculate_rewards"),
+          userAccount: account.pda,
+          sessionAccount: sessionAccountPda(account.pda),
+          programConfig: programConfigPda,
+          payer: stranger.publicKey,
+        })
+        .signers([stranger])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a non-owner must not trigger reward calculation");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+
+    // The owner can still query
+    expect(await checkBalanceAbove(account, 1000)).to.be.true;
+  });
+
+  it("Should pay a receiver and a fee collector all or nothing", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("process_payment_with_fee", "initProcessPaymentWithFeeCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const collector = await createUserAccount(0, publicKey);
+    const parties = [sender.pda, receiver.pda, collector.pda];
+
+    const payWithFee = async (amount: number, fee: number) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .processPaymentWithFee(
+          offset,
+          toBN(amount),
+          toBN(fee),
+          randomNonce(),
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment_with_fee"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          feeCollector: collector.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    const before = await Promise.all(
+      parties.map((pda) => program.account.userAccount.fetch(pda))
+    );
+
+    // 990 + 20 exceeds the balance of 1000, so none of the three changes
+    const failedEventPromise = awaitEvent("feePaymentFailedEvent");
+    await payWithFee(990, 20);
+    await failedEventPromise;
+    for (let i = 0; i < parties.length; i++) {
+      const after = await program.account.userAccount.fetch(parties[i]);
+      expect(after.mxeEncryptedBalance).to.deep.equal(
+        before[i].mxeEncryptedBalance
+      );
+      expect(after.mxeBalanceNonce.toString()).to.equal(
+        before[i].mxeBalanceNonce.toString()
+      );
+      expect(after.transactionCount.toNumber()).to.equal(0);
+    }
+
+    const paidEventPromise = awaitEvent("feePaymentEvent");
+    await payWithFee(600, 15);
+    await paidEventPromise;
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(385));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(600));
+    expect(await decryptBalance(cipher, collector.pda)).to.equal(BigInt(15));
+  });
+
+  it("Should index recent transactions per owner, evicting the oldest", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const sent: bigint[] = [];
+    for (let i = 0; i < 3; i++) {
+      sent.push(await sendPayment(sender.pda, receiver.pda, 10, cipher));
+    }
+    expect((await recentTransactionIds(owner.publicKey)).slice(-3)).to.deep.equal(
+      sent
+    );
+
+    // Filling every slot pushes the earlier payments out
+    const capacity = (
+      await program.account.userTxIndex.fetch(userTxIndexPda(owner.publicKey))
+    ).transactionIds.length;
+    for (let i = 0; i < capacity; i++) {
+      sent.push(await sendPayment(sender.pda, receiver.pda, 10, cipher));
+    }
+    const recent = await recentTransactionIds(owner.publicKey);
+    expect(recent).to.deep.equal(sent.slice(-capacity));
+    expect(recent).to.not.include(sent[0]);
+  });
+
+  it("Should compute rewards with the configured rate and thresholds", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(2000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    await sendPayment(account.pda, receiver.pda, 100, cipher);
+
+    const setRewardConfig = (
+      pointsPerTx: number,
+      thresholds: [number, number, number]
+    ) =>
+      program.methods
+        .updateRewardConfig({
+          pointsPerTx: toBN(pointsPerTx),
+          silverThreshold: toBN(thresholds[0]),
+          goldThreshold: toBN(thresholds[1]),
+          premiumThreshold: toBN(thresholds[2]),
+          thresholdDecimals: 2,
+        })
+        .accountsPartial({
+          admin: owner.publicKey,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    try {
+      // Defaults: one transaction at 10 points plus the Silver bonus of 25
+      const defaultEvent = await calculateRewards(account);
+      expect(defaultEvent.tier).to.equal(1);
+      expect(defaultEvent.rewardPoints.toNumber()).to.equal(35);
+
+      // 40 points per transaction, and 1900 now reaches Premium
+      await setRewardConfig(40, [500, 1000, 1500]);
+      const tunedEvent = await calculateRewards(account);
+      expect(tunedEvent.tier).to.equal(3);
+      expect(tunedEvent.rewardPoints.toNumber()).to.equal(140);
+
+      try {
+        await setRewardConfig(10, [5000, 1000, 10000]);
+        expect.fail("thresholds out of order must be rejected");
+      } catch (e) {
+        expect(e.toString()).to.include("InvalidRewardConfig");
+      }
+    } finally {
+      await setRewardConfig(10, [1000, 5000, 10000]);
+    }
+  });
+
+  it("Should reject a payment that reuses a transaction id", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const pay = async (transactionId: bigint) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const senderState = await program.account.userAccount.fetch(sender.pda);
+      await program.methods
+        .processPayment(
+          ...processPaymentArgs(
+            offset,
+            transactionId,
+            10,
+            cipher,
+            Array.from(publicKey),
+            0,
+            0,
+            noMemo,
+            senderState.sequence
+          )
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    const transactionId = randomId();
+    await pay(transactionId);
+    try {
+      await pay(transactionId);
+      expect.fail("a reused transaction id must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("DuplicateTransactionId");
+    }
+  });
+
+  it("Should let an account whose initialization aborted be retried", async () => {
+    // An MPC abort cannot be forced on localnet, so check the abort path's
+    // surface in the IDL and that a healthy account cannot be re-initialized
+    const failedState = (program.idl.types.find(
+      (t) => t.name === "accountState"
+    ).type as any).variants.map((v: any) => v.name);
+    expect(failedState).to.include("initFailed");
+    const eventType = program.idl.types.find(
+      (t) => t.name === "accountInitFailedEvent"
+    );
+    expect(
+      (eventType.type as any).fields.map((f: any) => f.name)
+    ).to.deep.equal(["accountId", "timestamp"]);
+
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(100, publicKey);
+    const retry = (clientPubkey: number[]) => {
+      const offset = new anchor.BN(randomBytes(8));
+      return program.methods
+        .retryInitialization(
+          offset,
+          toBN(account.accountId),
+          toBN(100),
+          randomNonce(),
+          clientPubkey,
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "initialize_accounts"),
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    };
+    try {
+      await retry(Array.from(publicKey));
+      expect.fail("an active account must not be re-initialized");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidAccountState");
+    }
+
+    // Like account creation, a retry refuses a key nothing can decrypt with
+    try {
+      await retry(new Array(32).fill(0));
+      expect.fail("an all-zero encryption key must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidEncryptionPubkey");
+    }
+    try {
+      await createUserAccount(100, new Uint8Array(32));
+      expect.fail("an all-zero encryption key must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidEncryptionPubkey");
+    }
+  });
+
+  it("Should cap the balance an account can be opened with", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    try {
+      await setMaxInitialBalance(BigInt(500));
+      try {
+        await createUserAccount(501, publicKey);
+        expect.fail("an initial balance above the maximum must be rejected");
+      } catch (e) {
+        expect(e.toString()).to.include("InitialBalanceTooHigh");
+      }
+      await createUserAccount(500, publicKey);
+
+      // The default of zero still allows empty accounts
+      await setMaxInitialBalance(BigInt(0));
+      const empty = await createUserAccount(0, publicKey);
+      expect(
+        (await program.account.userAccount.fetch(empty.pda)).accountState
+      ).to.deep.equal({ active: {} });
+    } finally {
+      await setMaxInitialBalance(BigInt("18446744073709551615"));
+    }
+  });
+
+  it("Should let a payment dip into the credit line but not past it", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("open_credit_line", "initOpenCreditLineCompDef");
+    await initCompDef("process_payment_signed", "initProcessPaymentSignedCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(100, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    // The admin (owner in this suite) grants a limit of 500
+    const openOffset = new anchor.BN(randomBytes(8));
+    const limit = encryptU64(cipher, 500);
+    const openedEventPromise = awaitEvent("creditLineOpenedEvent");
+    await program.methods
+      .openCreditLine(
+        openOffset,
+        toBN(sender.accountId),
+        Array.from(publicKey),
+        limit.ciphertext,
+        limit.nonce,
+        randomNonce(),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(openOffset, "open_credit_line"),
+        userAccount: sender.pda,
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(openOffset);
+    await openedEventPromise;
+
+    const paySigned = async (amount: number) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const encryptedAmount = encryptU64(cipher, amount);
+      await program.methods
+        .processPaymentSigned(
+          offset,
+          encryptedAmount.ciphertext,
+          encryptedAmount.nonce,
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment_signed"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    // 400 against a balance of 100 draws 300 of the 500 limit
+    const paidEventPromise = awaitEvent("signedPaymentProcessedEvent");
+    await paySigned(400);
+    await paidEventPromise;
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(0));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(400));
+
+    // Another 300 would draw 600 in total
+    const before = await program.account.userAccount.fetch(sender.pda);
+    const exceededEventPromise = awaitEvent("creditLimitExceededEvent");
+    await paySigned(300);
+    await exceededEventPromise;
+    const after = await program.account.userAccount.fetch(sender.pda);
+    expect(after.creditLine.encryptedDrawn).to.deep.equal(
+      before.creditLine.encryptedDrawn
+    );
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(400));
+  });
+
+  it("Should reveal available credit on either side of zero", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("open_credit_line", "initOpenCreditLineCompDef");
+    await initCompDef("process_payment_signed", "initProcessPaymentSignedCompDef");
+    await initCompDef("reveal_available_credit", "initRevealAvailableCreditCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(100, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    await openSession(sender.pda, 600);
+
+    const openOffset = new anchor.BN(randomBytes(8));
+    const limit = encryptU64(cipher, 500);
+    const openedEventPromise = awaitEvent("creditLineOpenedEvent");
+    await program.methods
+      .openCreditLine(
+        openOffset,
+        toBN(sender.accountId),
+        Array.from(publicKey),
+        limit.ciphertext,
+        limit.nonce,
+        randomNonce(),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(openOffset, "open_credit_line"),
+        userAccount: sender.pda,
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(openOffset);
+    await openedEventPromise;
+
+    const revealAvailable = async () => {
+      const offset = new anchor.BN(randomBytes(8));
+      const revealedEventPromise = awaitEvent("availableCreditRevealedEvent");
+      await program.methods
+        .revealAvailableCredit(offset, toBN(sender.accountId), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "reveal_available_credit"),
+          userAccount: sender.pda,
+          sessionAccount: sessionAccountPda(sender.pda),
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      const event = await revealedEventPromise;
+      const [available] = cipher.decrypt(
+        [event.encryptedAvailable],
+        new Uint8Array(event.availableNonce.toArray("le", 16))
+      );
+      return available;
+    };
+
+    // A positive balance of 100 adds to the untouched limit of 500
+    expect(await revealAvailable()).to.equal(BigInt(600));
+
+    // Paying 400 empties the balance and draws 300 of the limit
+    const offset = new anchor.BN(randomBytes(8));
+    const amount = encryptU64(cipher, 400);
+    const paidEventPromise = awaitEvent("signedPaymentProcessedEvent");
+    await program.methods
+      .processPaymentSigned(offset, amount.ciphertext, amount.nonce, randomNonce(), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment_signed"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    await paidEventPromise;
+
+    expect(await revealAvailable()).to.equal(BigInt(200));
+  });
+
+  it("Should move one currency while another stays untouched", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("open_currency_balance", "initOpenCurrencyBalanceCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const currencyA = 7;
+    const currencyB = 9;
+
+    const addCurrency = async (
+      account: { accountId: bigint; pda: PublicKey },
+      currencyId: number
+    ) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .addCurrency(
+          offset,
+          toBN(account.accountId),
+          currencyId,
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "open_currency_balance"),
+          userAccount: account.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+    const decryptCurrency = async (pda: PublicKey, currencyId: number) => {
+      const account = await program.account.userAccount.fetch(pda);
+      const slot = account.currencies.find(
+        (currency) => currency.initialized && currency.currencyId === currencyId
+      );
+      return cipher.decrypt(
+        [slot.encryptedBalance],
+        new Uint8Array(slot.balanceNonce.toArray("le", 16))
+      )[0];
+    };
+
+    // The sender holds 1000 of A and nothing of B; the receiver the reverse
+    const sender = await createUserAccount(1000, publicKey, owner, currencyA);
+    const receiver = await createUserAccount(500, publicKey, owner, currencyB);
+    await addCurrency(sender, currencyB);
+    await addCurrency(receiver, currencyA);
+    const receiverBefore = await program.account.userAccount.fetch(receiver.pda);
+
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .processPayment(
+        ...processPaymentArgs(
+          offset,
+          transactionId,
+          300,
+          cipher,
+          Array.from(publicKey),
+          0,
+          currencyA
+        )
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(700));
+    expect(await decryptCurrency(receiver.pda, currencyA)).to.equal(BigInt(300));
+    // Currency B is untouched on both sides
+    const receiverAfter = await program.account.userAccount.fetch(receiver.pda);
+    expect(receiverAfter.mxeEncryptedBalance).to.deep.equal(
+      receiverBefore.mxeEncryptedBalance
+    );
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(500));
+    expect(await decryptCurrency(sender.pda, currencyB)).to.equal(BigInt(0));
+    expect(await checkBalanceAbove(receiver, 300, currencyA)).to.be.true;
+
+    await ensureSession(sender.pda);
+    const unknownOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .checkBalance(unknownOffset, toBN(sender.accountId), toBN(1), 42)
+        .accountsPartial({
+          ...queueAccounts(unknownOffset, "check_balance"),
+          userAccount: sender.pda,
+          sessionAccount: sessionAccountPda(sender.pda),
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a currency the account does not hold must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("CurrencyNotSupported");
+    }
+  });
+
+  it("Should store a memo only the payment's parties can read", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const senderKeys = await setupCipher();
+    const receiverKeys = await setupCipher();
+    const sender = await createUserAccount(1000, senderKeys.publicKey);
+    const receiver = await createUserAccount(0, receiverKeys.publicKey);
+
+    const memoText = "INV-2026-0042";
+    const memo = encryptMemo(
+      new RescueCipher(
+        x25519.getSharedSecret(senderKeys.privateKey, receiverKeys.publicKey)
+      ),
+      memoText
+    );
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .processPayment(
+        ...processPaymentArgs(
+          offset,
+          transactionId,
+          250,
+          senderKeys.cipher,
+          Array.from(senderKeys.publicKey),
+          0,
+          0,
+          memo
+        )
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const stored = await program.account.transaction.fetch(
+      transactionPda(transactionId)
+    );
+    expect(stored.status).to.deep.equal({ completed: {} });
+    expect(Buffer.from(stored.encryptedMemo).includes(Buffer.from(memoText))).to
+      .be.false;
+
+    // The receiver derives the same secret from the sender's stored key
+    const senderState = await program.account.userAccount.fetch(sender.pda);
+    const receiverCipher = new RescueCipher(
+      x25519.getSharedSecret(
+        receiverKeys.privateKey,
+        Uint8Array.from(senderState.ownerEncPubkey)
+      )
+    );
+    const chunks = receiverCipher.decrypt(
+      [stored.encryptedMemo.slice(0, 32), stored.encryptedMemo.slice(32)],
+      new Uint8Array(stored.memoNonce.toArray("le", 16))
+    );
+    const decoded = Buffer.concat(chunks.map((chunk) => serializeLE(chunk, 16)));
+    expect(decoded.toString().replace(/\0+$/, "")).to.equal(memoText);
+  });
+
+  it("Should chain the sender's balance nonces across payments", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const initialNonce = (await program.account.userAccount.fetch(sender.pda))
+      .balanceNonce;
+    const firstEventPromise = awaitEvent("paymentProcessedEvent");
+    await sendPayment(sender.pda, receiver.pda, 100, cipher);
+    const firstEvent = await firstEventPromise;
+    expect(firstEvent.prevBalanceNonce.toString()).to.equal(
+      initialNonce.toString()
+    );
+
+    const secondEventPromise = awaitEvent("paymentProcessedEvent");
+    await sendPayment(sender.pda, receiver.pda, 200, cipher);
+    const secondEvent = await secondEventPromise;
+    expect(secondEvent.prevBalanceNonce.toString()).to.equal(
+      firstEvent.senderNewBalanceNonce.toString()
+    );
+    expect(
+      (await program.account.userAccount.fetch(sender.pda)).balanceNonce.toString()
+    ).to.equal(secondEvent.senderNewBalanceNonce.toString());
+  });
+
+  it("Should reject a receiver account not owned by the program", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    // A system-owned account with lamports but no UserAccount data
+    const foreign = await fundedKeypair();
+
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .processPayment(
+          ...processPaymentArgs(
+            offset,
+            transactionId,
+            100,
+            cipher,
+            Array.from(publicKey)
+          )
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: sender.pda,
+          receiverAccount: foreign.publicKey,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a receiver owned by another program must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("AccountOwnedByWrongProgram");
+    }
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should refund a completed payment back to its sender", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("refund_payment", "initRefundPaymentCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(200, publicKey);
+
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 300, cipher);
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(500));
+
+    const refund = async (refundTransactionId: bigint) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .refundTransaction(
+          offset,
+          toBN(transactionId),
+          toBN(refundTransactionId),
+          randomNonce(),
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "refund_payment"),
+          originalTransaction: transactionPda(transactionId),
+          refundTransaction: transactionPda(refundTransactionId),
+          receiverAccount: receiver.pda,
+          senderAccount: sender.pda,
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    const refundTransactionId = randomId();
+    const refundedEventPromise = awaitEvent("transactionRefundedEvent");
+    await refund(refundTransactionId);
+    const refundedEvent = await refundedEventPromise;
+    expect(refundedEvent.refundTransactionId.toString()).to.equal(
+      refundTransactionId.toString()
+    );
+
+    // The payment had no fee, so both balances are back where they started
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(200));
+
+    const original = await program.account.transaction.fetch(
+      transactionPda(transactionId)
+    );
+    expect(original.status).to.deep.equal({ refunded: {} });
+    const refundRecord = await program.account.transaction.fetch(
+      transactionPda(refundTransactionId)
+    );
+    expect(refundRecord.status).to.deep.equal({ completed: {} });
+    expect(refundRecord.refundedTransactionId.toString()).to.equal(
+      transactionId.toString()
+    );
+    expect(refundRecord.sender.toBase58()).to.equal(receiver.pda.toBase58());
+
+    try {
+      await refund(randomId());
+      expect.fail("a refunded payment must not be refunded again");
+    } catch (e) {
+      expect(e.toString()).to.include("TransactionAlreadyRefunded");
+    }
+  });
+
+  it("Should compare two balances revealing only their ordering", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("compare_balances", "initCompareBalancesCompDef");
+    const { publicKey } = await setupCipher();
+    const low = await createUserAccount(100, publicKey);
+    const high = await createUserAccount(200, publicKey);
+    const alsoHigh = await createUserAccount(200, publicKey);
+
+    const compare = async (
+      first: { accountId: bigint; pda: PublicKey },
+      second: { accountId: bigint; pda: PublicKey }
+    ) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const eventPromise = awaitEvent("balanceComparisonEvent");
+      await program.methods
+        .compareBalances(offset, toBN(first.accountId), toBN(second.accountId))
+        .accountsPartial({
+          ...queueAccounts(offset, "compare_balances"),
+          programConfig: programConfigPda,
+          firstAccount: first.pda,
+          secondAccount: second.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      return (await eventPromise).ordering;
+    };
+
+    expect(await compare(low, high)).to.equal(0);
+    expect(await compare(high, alsoHigh)).to.equal(1);
+    expect(await compare(high, low)).to.equal(2);
+  });
+
+  it("Should size the sign PDA for SignerAccount and keep it rent exempt", async () => {
+    // Mirrors SIGN_PDA_SPACE: discriminator plus SignerAccount's one-byte bump
+    const signPdaSpace = 8 + 1;
+    const signPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("SignerAccount")],
+      program.programId
+    )[0];
+    // Every earlier queued computation has created it by now
+    const info = await provider.connection.getAccountInfo(signPda);
+    expect(info).to.not.be.null;
+    expect(info.data.length).to.equal(signPdaSpace);
+    expect(info.lamports).to.be.at.least(
+      await provider.connection.getMinimumBalanceForRentExemption(signPdaSpace)
+    );
+  });
+
+  it("Should reject a payment signed by someone other than the sender's owner", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const stranger = await fundedKeypair();
+
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .processPayment(
+          ...processPaymentArgs(
+            offset,
+            transactionId,
+            100,
+            cipher,
+            Array.from(publicKey)
+          )
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: stranger.publicKey,
+        })
+        .signers([stranger])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a non-owner must not spend from the account");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should sweep a whole balance into another account", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("sweep_balance", "initSweepBalanceCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const source = await createUserAccount(700, publicKey);
+    const destination = await createUserAccount(300, publicKey);
+
+    const offset = new anchor.BN(randomBytes(8));
+    const sweptEventPromise = awaitEvent("balanceSweptEvent");
+    await program.methods
+      .sweepBalance(offset, randomNonce(), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "sweep_balance"),
+        sourceAccount: source.pda,
+        destinationAccount: destination.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    const sweptEvent = await sweptEventPromise;
+    expect(sweptEvent.sourceAccountId.toString()).to.equal(
+      source.accountId.toString()
+    );
+
+    expect(await checkBalanceAbove(source, 1)).to.be.false;
+    expect(await decryptBalance(cipher, source.pda)).to.equal(BigInt(0));
+    expect(await decryptBalance(cipher, destination.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should cap reward points at u64::MAX instead of overflowing", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    // Below every tier threshold, so only the per-transaction points count
+    const account = await createUserAccount(500, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    await sendPayment(account.pda, receiver.pda, 100, cipher);
+
+    const maxU64 = BigInt("18446744073709551615");
+    const setPointsPerTx = (pointsPerTx: bigint) =>
+      program.methods
+        .updateRewardConfig({
+          pointsPerTx: toBN(pointsPerTx),
+          silverThreshold: toBN(1000),
+          goldThreshold: toBN(5000),
+          premiumThreshold: toBN(10000),
+          thresholdDecimals: 2,
+        })
+        .accountsPartial({
+          admin: owner.publicKey,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    try {
+      // One transaction earns just under the cap
+      await setPointsPerTx(maxU64 - BigInt(200));
+      await calculateRewards(account);
+      const nearMax = (await program.account.userAccount.fetch(account.pda))
+        .rewardPoints;
+      expect(nearMax.toString()).to.equal((maxU64 - BigInt(200)).toString());
+
+      // A second round would pass u64::MAX, so the total stops there
+      await setPointsPerTx(BigInt(500));
+      const overflowEventPromise = awaitEvent("rewardsOverflowEvent");
+      const event = await calculateRewards(account);
+      expect(event.totalRewards.toString()).to.equal(maxU64.toString());
+      const overflowEvent = await overflowEventPromise;
+      expect(overflowEvent.discardedPoints.toNumber()).to.equal(300);
+    } finally {
+      await setPointsPerTx(BigInt(10));
+    }
+  });
+
+  it("Should hold a scheduled payment in escrow until its release time", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("schedule_payment", "initSchedulePaymentCompDef");
+    await initCompDef(
+      "release_scheduled_payment",
+      "initReleaseScheduledPaymentCompDef"
+    );
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const scheduleId = randomId();
+    const [scheduledPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("scheduled_payment"), toBN(scheduleId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const now = await provider.connection.getBlockTime(
+      await provider.connection.getSlot("confirmed")
+    );
+    const amount = encryptU64(cipher, 300);
+    const scheduleOffset = new anchor.BN(randomBytes(8));
+    const scheduledEventPromise = awaitEvent("paymentScheduledEvent");
+    await program.methods
+      .schedulePayment(
+        scheduleOffset,
+        toBN(scheduleId),
+        amount.ciphertext,
+        amount.nonce,
+        randomNonce(),
+        randomNonce(),
+        new anchor.BN(now + 3600)
+      )
+      .accountsPartial({
+        ...queueAccounts(scheduleOffset, "schedule_payment"),
+        scheduledPayment: scheduledPda,
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(scheduleOffset);
+    await scheduledEventPromise;
+    // The amount has left the sender but not yet reached the receiver
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(700));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(0));
+
+    const executeOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .executeScheduledPayment(executeOffset, toBN(scheduleId), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(executeOffset, "release_scheduled_payment"),
+          scheduledPayment: scheduledPda,
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a scheduled payment must not execute before release_ts");
+    } catch (e) {
+      expect(e.toString()).to.include("ScheduledPaymentLocked");
+    }
+
+    // Before the release the sender can take the amount back
+    const cancelOffset = new anchor.BN(randomBytes(8));
+    const cancelledEventPromise = awaitEvent("scheduledPaymentCancelledEvent");
+    await program.methods
+      .cancelScheduledPayment(cancelOffset, toBN(scheduleId), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(cancelOffset, "release_scheduled_payment"),
+        scheduledPayment: scheduledPda,
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(cancelOffset);
+    await cancelledEventPromise;
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(
+      (await program.account.scheduledPayment.fetch(scheduledPda)).status
+    ).to.deep.equal({ cancelled: {} });
+  });
+
+  it("Should hold a large transfer until the co-signer approves it", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef(
+      "set_large_transfer_threshold",
+      "initSetLargeTransferThresholdCompDef"
+    );
+    await initCompDef("approve_large_transfer", "initApproveLargeTransferCompDef");
+    await initCompDef("sweep_balance", "initSweepBalanceCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const coSigner = await fundedKeypair();
+
+    const threshold = encryptU64(cipher, 500);
+    const coSignerOffset = new anchor.BN(randomBytes(8));
+    const coSignerSetPromise = awaitEvent("coSignerSetEvent");
+    await program.methods
+      .setCoSigner(
+        coSignerOffset,
+        toBN(sender.accountId),
+        coSigner.publicKey,
+        threshold.ciphertext,
+        threshold.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(coSignerOffset, "set_large_transfer_threshold"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(coSignerOffset);
+    await coSignerSetPromise;
+
+    const approvalRequiredPromise = awaitEvent("approvalRequiredEvent");
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 800, cipher);
+    const approvalRequired = await approvalRequiredPromise;
+    expect(approvalRequired.coSigner.toBase58()).to.equal(coSigner.publicKey.toBase58());
+    expect(
+      (await program.account.transaction.fetch(transactionPda(transactionId))).status
+    ).to.deep.equal({ pendingApproval: {} });
+    // Nothing moves until the co-signer signs off
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(0));
+
+    const approveOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .approveLargeTransfer(
+          approveOffset,
+          toBN(transactionId),
+          randomNonce(),
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(approveOffset, "approve_large_transfer"),
+          transaction: transactionPda(transactionId),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("only the co-signer may approve a large transfer");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+
+    const approvedPromise = awaitEvent("largeTransferApprovedEvent");
+    await program.methods
+      .approveLargeTransfer(
+        approveOffset,
+        toBN(transactionId),
+        randomNonce(),
+        randomNonce(),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(approveOffset, "approve_large_transfer"),
+        transaction: transactionPda(transactionId),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        programConfig: programConfigPda,
+        payer: coSigner.publicKey,
+      })
+      .signers([coSigner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(approveOffset);
+    await approvedPromise;
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(800));
+    expect(
+      (await program.account.transaction.fetch(transactionPda(transactionId))).status
+    ).to.deep.equal({ completed: {} });
+
+    // Only process_payment holds large transfers, so the other debits refuse
+    const sweepOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .sweepBalance(sweepOffset, randomNonce(), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(sweepOffset, "sweep_balance"),
+          sourceAccount: sender.pda,
+          destinationAccount: receiver.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a sweep would bypass the co-signer");
+    } catch (e) {
+      expect(e.toString()).to.include("UnsupportedAccountSettings");
+    }
+
+    // The owner cannot drop the co-signer alone
+    try {
+      await program.methods
+        .removeCoSigner(toBN(sender.accountId))
+        .accountsPartial({
+          owner: owner.publicKey,
+          coSigner: owner.publicKey,
+          userAccount: sender.pda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("removing the co-signer needs the co-signer's signature");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+    const removedEventPromise = awaitEvent("coSignerRemovedEvent");
+    await program.methods
+      .removeCoSigner(toBN(sender.accountId))
+      .accountsPartial({
+        owner: owner.publicKey,
+        coSigner: coSigner.publicKey,
+        userAccount: sender.pda,
+      })
+      .signers([owner, coSigner])
+      .rpc({ commitment: "confirmed" });
+    await removedEventPromise;
+    const senderState = await program.account.userAccount.fetch(sender.pda);
+    expect(senderState.coSigner).to.be.null;
+    expect(senderState.hasLargeTransferThreshold).to.be.false;
+
+    // Without a co-signer the sweep goes through
+    const sweptEventPromise = awaitEvent("balanceSweptEvent");
+    await program.methods
+      .sweepBalance(sweepOffset, randomNonce(), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(sweepOffset, "sweep_balance"),
+        sourceAccount: sender.pda,
+        destinationAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(sweepOffset);
+    await sweptEventPromise;
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should count an approved large transfer against the daily limit", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("set_daily_limit", "initSetDailyLimitCompDef");
+    await initCompDef(
+      "set_large_transfer_threshold",
+      "initSetLargeTransferThresholdCompDef"
+    );
+    await initCompDef("approve_large_transfer", "initApproveLargeTransferCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const coSigner = await fundedKeypair();
+
+    const limit = encryptU64(cipher, 1000);
+    const limitOffset = new anchor.BN(randomBytes(8));
+    const limitSetEventPromise = awaitEvent("dailyLimitSetEvent");
+    await program.methods
+      .setDailyLimit(
+        limitOffset,
+        toBN(sender.accountId),
+        limit.ciphertext,
+        limit.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(limitOffset, "set_daily_limit"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(limitOffset);
+    await limitSetEventPromise;
+
+    const threshold = encryptU64(cipher, 500);
+    const coSignerOffset = new anchor.BN(randomBytes(8));
+    const coSignerSetPromise = awaitEvent("coSignerSetEvent");
+    await program.methods
+      .setCoSigner(
+        coSignerOffset,
+        toBN(sender.accountId),
+        coSigner.publicKey,
+        threshold.ciphertext,
+        threshold.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(coSignerOffset, "set_large_transfer_threshold"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(coSignerOffset);
+    await coSignerSetPromise;
+
+    const approve = async (transactionId: bigint) => {
+      const approveOffset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .approveLargeTransfer(
+          approveOffset,
+          toBN(transactionId),
+          randomNonce(),
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(approveOffset, "approve_large_transfer"),
+          transaction: transactionPda(transactionId),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          programConfig: programConfigPda,
+          payer: coSigner.publicKey,
+        })
+        .signers([coSigner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(approveOffset);
+    };
+
+    // Held when sent, counted when approved
+    const approvedPromise = awaitEvent("largeTransferApprovedEvent");
+    const held = await sendPayment(sender.pda, receiver.pda, 700, cipher);
+    await approve(held);
+    await approvedPromise;
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(700));
+
+    // 700 + 400 exceeds the limit
+    const paymentExceededPromise = awaitEvent("spendLimitExceededEvent");
+    const rejected = await sendPayment(sender.pda, receiver.pda, 400, cipher);
+    expect((await paymentExceededPromise).transactionId.toString()).to.equal(
+      rejected.toString()
+    );
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(700));
+
+    // 700 + 800 exceeds it too, so the approval leaves the transfer held
+    const heldOverLimit = await sendPayment(sender.pda, receiver.pda, 800, cipher);
+    const approvalExceededPromise = awaitEvent("spendLimitExceededEvent");
+    await approve(heldOverLimit);
+    expect((await approvalExceededPromise).transactionId.toString()).to.equal(
+      heldOverLimit.toString()
+    );
+    expect(
+      (await program.account.transaction.fetch(transactionPda(heldOverLimit))).status
+    ).to.deep.equal({ pendingApproval: {} });
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(4300));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(700));
+  });
+
+  it("Should store and echo an account's display decimals", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const initializedPromise = awaitEvent("accountInitializedEvent");
+    const account = await createUserAccount(1000, publicKey, owner, 0, 6);
+    const initialized = await initializedPromise;
+    expect(initialized.decimals).to.equal(6);
+    expect(
+      (await program.account.userAccount.fetch(account.pda)).decimals
+    ).to.equal(6);
+
+    try {
+      await createUserAccount(1000, publicKey, owner, 0, 19);
+      expect.fail("more than 18 decimals must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidDecimals");
+    }
+  });
+
+  it("Should total several balances for an auditor without revealing them", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("aggregate_balances", "initAggregateBalancesCompDef");
+    const { publicKey } = await setupCipher();
+    const accounts = [
+      await createUserAccount(100, publicKey),
+      await createUserAccount(250, publicKey),
+      await createUserAccount(650, publicKey),
+    ];
+    const auditor = await setupCipher();
+
+    const offset = new anchor.BN(randomBytes(8));
+    const aggregatePromise = awaitEvent("aggregateComputedEvent");
+    await program.methods
+      .aggregateBalances(offset, Array.from(auditor.publicKey), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "aggregate_balances"),
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .remainingAccounts(
+        accounts.map((account) => ({
+          pubkey: account.pda,
+          isWritable: false,
+          isSigner: false,
+        }))
+      )
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    const aggregate = await aggregatePromise;
+    expect(aggregate.accountCount).to.equal(3);
+    expect(aggregate.overflow).to.be.false;
+    const [total] = auditor.cipher.decrypt(
+      [aggregate.total],
+      new Uint8Array(aggregate.totalNonce.toArray("le", 16))
+    );
+    expect(total).to.equal(BigInt(1000));
+  });
+
+  it("Should hold a disputed payment and release it", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 400, cipher);
+
+    const heldPromise = awaitEvent("transactionHeldEvent");
+    await program.methods
+      .flagTransaction(toBN(transactionId), true)
+      .accountsPartial({
+        programConfig: programConfigPda,
+        transaction: transactionPda(transactionId),
+        receiverAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    expect((await heldPromise).receiverFrozen).to.be.true;
+    const held = await program.account.transaction.fetch(transactionPda(transactionId));
+    expect(held.status).to.deep.equal({ held: {} });
+    expect(
+      (await program.account.userAccount.fetch(receiver.pda)).accountState
+    ).to.deep.equal({ frozen: {} });
+
+    const resolvedPromise = awaitEvent("disputeResolvedEvent");
+    await program.methods
+      .resolveDispute(toBN(transactionId))
+      .accountsPartial({
+        programConfig: programConfigPda,
+        transaction: transactionPda(transactionId),
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    expect((await resolvedPromise).refunded).to.be.false;
+    const released = await program.account.transaction.fetch(
+      transactionPda(transactionId)
+    );
+    expect(released.status).to.deep.equal({ completed: {} });
+    // Releasing the hold leaves the payment where it landed
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(400));
+  });
+
+  it("Should keep an account label readable only with the owner's key", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const label = encryptLabel(cipher, "Savings");
+    const account = await createUserAccount(100, publicKey, owner, 0, 2, label);
+
+    const decryptLabel = async (labelCipher: RescueCipher) => {
+      const state = await program.account.userAccount.fetch(account.pda);
+      const [plaintext] = labelCipher.decrypt(
+        [state.encryptedLabel],
+        new Uint8Array(state.labelNonce.toArray("le", 16))
+      );
+      return plaintext;
+    };
+    expect(await decryptLabel(cipher)).to.equal(label.plaintext);
+    // Stored opaque: the plaintext bytes do not appear and another key cannot read it
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(Buffer.from(state.encryptedLabel).includes(Buffer.from("Savings"))).to.be.false;
+    const stranger = await setupCipher();
+    expect(await decryptLabel(stranger.cipher)).to.not.equal(label.plaintext);
+
+    const renamed = encryptLabel(cipher, "Travel");
+    await program.methods
+      .setLabel(toBN(account.accountId), renamed.ciphertext, renamed.nonce)
+      .accountsPartial({ userAccount: account.pda, owner: owner.publicKey })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    expect(await decryptLabel(cipher)).to.equal(renamed.plaintext);
+  });
+
+  it("Should collect each payment's fee into the treasury", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("reveal_treasury", "initRevealTreasuryCompDef");
+    await initCompDef("withdraw", "initWithdrawCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(5000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const admin = await setupCipher();
+
+    const revealTreasury = async () => {
+      const offset = new anchor.BN(randomBytes(8));
+      const revealedPromise = awaitEvent("treasuryRevealedEvent");
+      await program.methods
+        .revealTreasury(offset, Array.from(admin.publicKey), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "reveal_treasury"),
+          programConfig: programConfigPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      const revealed = await revealedPromise;
+      const [total] = admin.cipher.decrypt(
+        [revealed.encryptedTotal],
+        new Uint8Array(revealed.totalNonce.toArray("le", 16))
+      );
+      return total;
+    };
+
+    const before = await revealTreasury();
+    // 1% + 10 flat on payments
+    await setFeeSchedule({ ...zeroFeeSchedule(), payment: feeRule(100, 10) });
+    try {
+      await sendPayment(sender.pda, receiver.pda, 1000, cipher);
+    } finally {
+      await setFeeSchedule(zeroFeeSchedule());
+    }
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+    expect(await revealTreasury()).to.equal(before + BigInt(20));
+
+    // Fees on the other operations land there too
+    await setFeeSchedule({ ...zeroFeeSchedule(), withdrawal: feeRule(0, 5) });
+    try {
+      await openSession(sender.pda, 600);
+      await withdraw(sender, 100);
+    } finally {
+      await setFeeSchedule(zeroFeeSchedule());
+    }
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(3875));
+    expect(await revealTreasury()).to.equal(before + BigInt(25));
+  });
+
+  it("Should reject a resubmitted payment built against an old sequence", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const staleSequence = (await program.account.userAccount.fetch(sender.pda)).sequence;
+    await sendPayment(sender.pda, receiver.pda, 300, cipher);
+    expect(
+      (await program.account.userAccount.fetch(sender.pda)).sequence.toNumber()
+    ).to.equal(staleSequence.toNumber() + 1);
+
+    // The same payment retried under a fresh transaction id
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .processPayment(
+          ...processPaymentArgs(
+            offset,
+            transactionId,
+            300,
+            cipher,
+            Array.from(publicKey),
+            0,
+            0,
+            noMemo,
+            staleSequence
+          )
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a payment with a stale sequence must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("StaleSequence");
+    }
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(300));
+  });
+
+  it("Should let only the seizure authority zero a frozen account", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("seize_funds", "initSeizeFundsCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(700, publicKey);
+    const regulator = await fundedKeypair();
+    const regulatorKeys = await setupCipher();
+    await program.methods
+      .updateSeizureAuthority(regulator.publicKey)
+      .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    const seize = async (signer: Keypair) => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .seizeFunds(
+          offset,
+          toBN(account.accountId),
+          Array.from(regulatorKeys.publicKey),
+          randomNonce(),
+          randomNonce()
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "seize_funds"),
+          programConfig: programConfigPda,
+          userAccount: account.pda,
+          payer: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    try {
+      await program.methods
+        .freezeAccount(toBN(account.accountId))
+        .accountsPartial({
+          authority: owner.publicKey,
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+      try {
+        await seize(owner);
+        expect.fail("only the seizure authority may seize funds");
+      } catch (e) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+
+      const seizedPromise = awaitEvent("fundsSeizedEvent");
+      await seize(regulator);
+      const seized = await seizedPromise;
+      const [seizedAmount] = regulatorKeys.cipher.decrypt(
+        [seized.seizedAmount],
+        new Uint8Array(seized.seizedAmountNonce.toArray("le", 16))
+      );
+      expect(seizedAmount).to.equal(BigInt(700));
+      expect(await decryptBalance(cipher, account.pda)).to.equal(BigInt(0));
+    } finally {
+      await program.methods
+        .updateSeizureAuthority(owner.publicKey)
+        .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    }
+  });
+
+  it("Should refuse a payment that would dip below the minimum reserve", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("set_min_reserve", "initSetMinReserveCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const reserve = encryptU64(cipher, 400);
+    const reserveOffset = new anchor.BN(randomBytes(8));
+    const reserveSetPromise = awaitEvent("minReserveSetEvent");
+    await program.methods
+      .setMinReserve(
+        reserveOffset,
+        toBN(sender.accountId),
+        reserve.ciphertext,
+        reserve.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(reserveOffset, "set_min_reserve"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(reserveOffset);
+    await reserveSetPromise;
+
+    // 700 is covered by the balance but would leave only 300 of the 400 reserve
+    const rejectedId = await sendPayment(sender.pda, receiver.pda, 700, cipher);
+    expect(
+      (await program.account.transaction.fetch(transactionPda(rejectedId))).status
+    ).to.not.deep.equal({ completed: {} });
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(0));
+
+    // Exactly down to the reserve is allowed
+    await sendPayment(sender.pda, receiver.pda, 600, cipher);
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(400));
+
+    // Withdrawals keep the reserve too
+    await initCompDef("withdraw", "initWithdrawCompDef");
+    await openSession(sender.pda, 600);
+    const failedEventPromise = awaitEvent("withdrawalFailedEvent");
+    await withdraw(sender, 1);
+    await failedEventPromise;
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(400));
+  });
+
+  it("Should echo each payment's computation offset in its result event", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    // Separate parties, since an account allows one payment in flight at a time
+    const senders = [
+      await createUserAccount(1000, publicKey),
+      await createUserAccount(1000, publicKey),
+    ];
+    const receivers = [
+      await createUserAccount(0, publicKey),
+      await createUserAccount(0, publicKey),
+    ];
+
+    // Both payments are in flight before either result arrives. Every payment
+    // collects into the same fee and tip pools, so the limit is raised for them.
+    const seen = new Map<string, string>();
+    let listenerId: number;
+    const bothSeen = new Promise<void>((res) => {
+      listenerId = program.addEventListener("paymentProcessedEvent", (event) => {
+        seen.set(event.computationOffset.toString(), event.transactionId.toString());
+        if (seen.size === senders.length) res();
+      });
+    });
+    await setMaxInFlight(2);
+    try {
+      const queued = await Promise.all(
+        senders.map(async (sender, i) => {
+          const transactionId = randomId();
+          const offset = new anchor.BN(randomBytes(8));
+          await program.methods
+            .processPayment(
+              ...processPaymentArgs(offset, transactionId, 100, cipher, Array.from(publicKey))
+            )
+            .accountsPartial({
+              ...queueAccounts(offset, "process_payment"),
+              senderAccount: sender.pda,
+              receiverAccount: receivers[i].pda,
+              senderTxIndex: userTxIndexPda(owner.publicKey),
+              receiverTxIndex: userTxIndexPda(owner.publicKey),
+              transaction: transactionPda(transactionId),
+              programConfig: programConfigPda,
+              validatorRewards: validatorRewardsPda,
+              treasury: treasuryPda,
+              payer: owner.publicKey,
+            })
+            .signers([owner])
+            .rpc({ commitment: "confirmed" });
+          return { offset, transactionId };
+        })
+      );
+      await Promise.all(queued.map(({ offset }) => finalize(offset)));
+      await bothSeen;
+      await program.removeEventListener(listenerId);
+
+      for (const { offset, transactionId } of queued) {
+        expect(seen.get(offset.toString())).to.equal(transactionId.toString());
+        const stored = await program.account.transaction.fetch(transactionPda(transactionId));
+        expect(stored.computationOffset.toString()).to.equal(offset.toString());
+      }
+    } finally {
+      await setMaxInFlight(1);
+    }
+  });
+
+  it("Should freeze an account left inactive past the dormancy threshold", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+
+    const setDormancyThreshold = (seconds: number) =>
+      program.methods
+        .updateDormancyThreshold(toBN(seconds))
+        .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    const markDormant = () =>
+      program.methods
+        .markDormant(toBN(account.accountId))
+        .accountsPartial({
+          admin: owner.publicKey,
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    // Just opened, so well within the default threshold
+    try {
+      await markDormant();
+      expect.fail("a recently active account must not be marked dormant");
+    } catch (e) {
+      expect(e.toString()).to.include("AccountNotDormant");
+    }
+
+    await setDormancyThreshold(1);
+    try {
+      await sleep(2000);
+      const dormantEventPromise = awaitEvent("accountDormantEvent");
+      await markDormant();
+      const dormantEvent = await dormantEventPromise;
+      expect(dormantEvent.accountId.toString()).to.equal(account.accountId.toString());
+    } finally {
+      await setDormancyThreshold(365 * 86400);
+    }
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(state.accountState).to.deep.equal({ frozen: {} });
+    expect(state.lastActivityTs.toNumber()).to.equal(state.createdAt.toNumber());
+  });
+
+  it("Should refund the sender in full when the receiver rejects a payment", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("release_escrowed_payment", "initReleaseEscrowedPaymentCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(200, publicKey);
+
+    await program.methods
+      .setRequireAcceptance(toBN(receiver.accountId), true)
+      .accountsPartial({ owner: owner.publicKey, userAccount: receiver.pda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    // The sender is debited straight away, the receiver only on acceptance
+    const pendingEventPromise = awaitEvent("paymentPendingAcceptanceEvent");
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 300, cipher);
+    const pendingEvent = await pendingEventPromise;
+    expect(pendingEvent.transactionId.toString()).to.equal(transactionId.toString());
+    expect(
+      (await program.account.transaction.fetch(transactionPda(transactionId))).status
+    ).to.deep.equal({ pendingAcceptance: {} });
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(700));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(200));
+
+    const rejectOffset = new anchor.BN(randomBytes(8));
+    const rejectedEventPromise = awaitEvent("paymentRejectedEvent");
+    await program.methods
+      .rejectPayment(rejectOffset, toBN(transactionId), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(rejectOffset, "release_escrowed_payment"),
+        transaction: transactionPda(transactionId),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(rejectOffset);
+    await rejectedEventPromise;
+
+    expect(
+      (await program.account.transaction.fetch(transactionPda(transactionId))).status
+    ).to.deep.equal({ rejected: {} });
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(200));
+  });
+
+  it("Should escrow an approved large transfer until the receiver accepts it", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef(
+      "set_large_transfer_threshold",
+      "initSetLargeTransferThresholdCompDef"
+    );
+    await initCompDef("approve_large_transfer", "initApproveLargeTransferCompDef");
+    await initCompDef("release_escrowed_payment", "initReleaseEscrowedPaymentCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(200, publicKey);
+    const coSigner = await fundedKeypair();
+
+    await program.methods
+      .setRequireAcceptance(toBN(receiver.accountId), true)
+      .accountsPartial({ owner: owner.publicKey, userAccount: receiver.pda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    const threshold = encryptU64(cipher, 500);
+    const coSignerOffset = new anchor.BN(randomBytes(8));
+    const coSignerSetPromise = awaitEvent("coSignerSetEvent");
+    await program.methods
+      .setCoSigner(
+        coSignerOffset,
+        toBN(sender.accountId),
+        coSigner.publicKey,
+        threshold.ciphertext,
+        threshold.nonce,
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(coSignerOffset, "set_large_transfer_threshold"),
+        userAccount: sender.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(coSignerOffset);
+    await coSignerSetPromise;
+
+    const approvalRequiredPromise = awaitEvent("approvalRequiredEvent");
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 800, cipher);
+    await approvalRequiredPromise;
+
+    // Approval debits the sender but only escrows the amount for the receiver
+    const approveOffset = new anchor.BN(randomBytes(8));
+    const pendingEventPromise = awaitEvent("paymentPendingAcceptanceEvent");
+    await program.methods
+      .approveLargeTransfer(
+        approveOffset,
+        toBN(transactionId),
+        randomNonce(),
+        randomNonce(),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(approveOffset, "approve_large_transfer"),
+        transaction: transactionPda(transactionId),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        programConfig: programConfigPda,
+        payer: coSigner.publicKey,
+      })
+      .signers([coSigner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(approveOffset);
+    await pendingEventPromise;
+    expect(
+      (await program.account.transaction.fetch(transactionPda(transactionId))).status
+    ).to.deep.equal({ pendingAcceptance: {} });
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(200));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(200));
+
+    const acceptOffset = new anchor.BN(randomBytes(8));
+    const acceptedEventPromise = awaitEvent("paymentAcceptedEvent");
+    await program.methods
+      .acceptPayment(acceptOffset, toBN(transactionId), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(acceptOffset, "release_escrowed_payment"),
+        transaction: transactionPda(transactionId),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(acceptOffset);
+    await acceptedEventPromise;
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(1000));
+  });
+
+  it("Should refuse payment paths that skip acceptance for such a receiver", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("sweep_balance", "initSweepBalanceCompDef");
+    await initCompDef("schedule_payment", "initSchedulePaymentCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(200, publicKey);
+
+    await program.methods
+      .setRequireAcceptance(toBN(receiver.accountId), true)
+      .accountsPartial({ owner: owner.publicKey, userAccount: receiver.pda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    const sweepOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .sweepBalance(sweepOffset, randomNonce(), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(sweepOffset, "sweep_balance"),
+          sourceAccount: sender.pda,
+          destinationAccount: receiver.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a sweep would credit the receiver without acceptance");
+    } catch (e) {
+      expect(e.toString()).to.include("UnsupportedAccountSettings");
+    }
+
+    const scheduleId = randomId();
+    const [scheduledPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("scheduled_payment"), toBN(scheduleId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const now = await provider.connection.getBlockTime(
+      await provider.connection.getSlot("confirmed")
+    );
+    const amount = encryptU64(cipher, 300);
+    const scheduleOffset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .schedulePayment(
+          scheduleOffset,
+          toBN(scheduleId),
+          amount.ciphertext,
+          amount.nonce,
+          randomNonce(),
+          randomNonce(),
+          new anchor.BN(now + 3600)
+        )
+        .accountsPartial({
+          ...queueAccounts(scheduleOffset, "schedule_payment"),
+          scheduledPayment: scheduledPda,
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a scheduled payment would credit the receiver without acceptance");
+    } catch (e) {
+      expect(e.toString()).to.include("UnsupportedAccountSettings");
+    }
+
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(1000));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(200));
+  });
+
+  it("Should draw a multi-source payment from the first account that covers it", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("process_payment_multi_source", "initProcessPaymentMultiSourceCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sources = [
+      await createUserAccount(100, publicKey),
+      await createUserAccount(1000, publicKey),
+    ];
+    const receiver = await createUserAccount(0, publicKey);
+
+    const amount = encryptU64(cipher, 500);
+    const offset = new anchor.BN(randomBytes(8));
+    const paidEventPromise = awaitEvent("multiSourcePaymentEvent");
+    await program.methods
+      .processPaymentMultiSource(
+        offset,
+        amount.ciphertext,
+        amount.nonce,
+        sources.map(() => randomNonce()),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment_multi_source"),
+        receiverAccount: receiver.pda,
+        programConfig: programConfigPda,
+        payer: owner.publicKey,
+      })
+      .remainingAccounts(
+        sources.map(({ pda }) => ({ pubkey: pda, isWritable: true, isSigner: false }))
+      )
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    const paidEvent = await paidEventPromise;
+
+    // The first source only holds 100, so the second one paid
+    const [sourceIndex] = cipher.decrypt(
+      [paidEvent.sourceIndex],
+      new Uint8Array(paidEvent.sourceIndexNonce.toArray("le", 16))
+    );
+    expect(sourceIndex).to.equal(BigInt(1));
+    expect(await decryptBalance(cipher, sources[0].pda)).to.equal(BigInt(100));
+    expect(await decryptBalance(cipher, sources[1].pda)).to.equal(BigInt(500));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(500));
+  });
+
+  it("Should reject a payment reusing the sender's previous output nonce", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const pay = async (senderNewNonce: anchor.BN) => {
+      const transactionId = randomId();
+      const offset = new anchor.BN(randomBytes(8));
+      const senderState = await program.account.userAccount.fetch(sender.pda);
+      const args = processPaymentArgs(
+        offset,
+        transactionId,
+        100,
+        cipher,
+        Array.from(publicKey),
+        0,
+        0,
+        noMemo,
+        senderState.sequence
+      );
+      // Index 8 is the nonce of the sender's re-encrypted balance
+      const withNonce = [
+        ...args.slice(0, 8),
+        senderNewNonce,
+        ...args.slice(9),
+      ] as unknown as typeof args;
+      await program.methods
+        .processPayment(...withNonce)
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    const nonce = randomNonce();
+    await pay(nonce);
+    try {
+      await pay(nonce);
+      expect.fail("a reused nonce must be rejected");
+    } catch (e) {
+      expect(e.toString()).to.include("NonceReused");
+    }
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(900));
+  });
+
+  it("Should report an account's public state", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    await sendPayment(sender.pda, receiver.pda, 100, cipher);
+
+    const getStatus = async () => {
+      const statusEventPromise = awaitEvent("accountStatusEvent");
+      await program.methods
+        .getAccountStatus(toBN(sender.accountId))
+        .accountsPartial({ userAccount: sender.pda })
+        .rpc({ commitment: "confirmed" });
+      return statusEventPromise;
+    };
+
+    const stored = await program.account.userAccount.fetch(sender.pda);
+    const status = await getStatus();
+    expect(status.accountId.toString()).to.equal(sender.accountId.toString());
+    expect(status.state).to.deep.equal({ active: {} });
+    expect(status.transactionCount.toNumber()).to.equal(1);
+    expect(status.transactionCount.toString()).to.equal(stored.transactionCount.toString());
+    expect(status.rewardPoints.toString()).to.equal(stored.rewardPoints.toString());
+
+    await program.methods
+      .freezeAccount(toBN(sender.accountId))
+      .accountsPartial({
+        authority: owner.publicKey,
+        userAccount: sender.pda,
+        programConfig: programConfigPda,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    expect((await getStatus()).state).to.deep.equal({ frozen: {} });
+  });
+
+  it("Should credit an encrypted balance with deposited SPL tokens", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCheckBalanceCompDef(program as any, owner, false, false);
+    await initCompDef("deposit", "initDepositCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+
+    const mint = await createMint(provider.connection, owner, owner.publicKey, null, 2);
+    const ownerTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      owner,
+      mint,
+      owner.publicKey
+    );
+    await mintTo(provider.connection, owner, mint, ownerTokenAccount.address, owner, 500);
+    const tokenVault = PublicKey.findProgramAddressSync(
+      [Buffer.from("token_vault"), mint.toBuffer()],
+      program.programId
+    )[0];
+
+    await program.methods
+      .updateDepositMint(mint)
+      .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .depositFromToken(offset, toBN(account.accountId), toBN(250), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "deposit"),
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+          mint,
+          userTokenAccount: ownerTokenAccount.address,
+          tokenVault,
+          pendingDeposit: pendingDepositPda(offset),
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    } finally {
+      await program.methods
+        .updateDepositMint(PublicKey.default)
+        .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    expect(Number((await getAccount(provider.connection, tokenVault)).amount)).to.equal(250);
+    expect(
+      Number((await getAccount(provider.connection, ownerTokenAccount.address)).amount)
+    ).to.equal(250);
+    expect(await checkBalanceAbove(account, 1250)).to.be.true;
+    expect(await checkBalanceAbove(account, 1251)).to.be.false;
+    expect(await decryptBalance(cipher, account.pda)).to.equal(BigInt(1250));
+    // Credited, so the callback closed the refund record
+    expect(
+      await provider.connection.getAccountInfo(pendingDepositPda(offset))
+    ).to.be.null;
+  });
+
+  it("Should refund deposited tokens whose credit would overflow the balance", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("deposit", "initDepositCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+
+    // Fill the balance up to u64::MAX so that any token deposit overflows
+    const fillOffset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .deposit(
+        fillOffset,
+        toBN(account.accountId),
+        toBN(BigInt("18446744073709551615") - BigInt(1000)),
+        randomNonce()
+      )
+      .accountsPartial({
+        ...queueAccounts(fillOffset, "deposit"),
+        userAccount: account.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(fillOffset);
+
+    const mint = await createMint(provider.connection, owner, owner.publicKey, null, 2);
+    const ownerTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      owner,
+      mint,
+      owner.publicKey
+    );
+    await mintTo(provider.connection, owner, mint, ownerTokenAccount.address, owner, 500);
+    const tokenVault = PublicKey.findProgramAddressSync(
+      [Buffer.from("token_vault"), mint.toBuffer()],
+      program.programId
+    )[0];
+    const tokenBalance = async (address: PublicKey) =>
+      Number((await getAccount(provider.connection, address)).amount);
+
+    await program.methods
+      .updateDepositMint(mint)
+      .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      const abortedEventPromise = awaitEvent("computationAbortedEvent");
+      await program.methods
+        .depositFromToken(offset, toBN(account.accountId), toBN(250), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "deposit"),
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+          mint,
+          userTokenAccount: ownerTokenAccount.address,
+          tokenVault,
+          pendingDeposit: pendingDepositPda(offset),
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      expect((await abortedEventPromise).operation).to.equal("deposit");
+    } finally {
+      await program.methods
+        .updateDepositMint(PublicKey.default)
+        .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    // The tokens sit in the vault, recorded as refundable
+    const pending = await program.account.pendingDeposit.fetch(pendingDepositPda(offset));
+    expect(pending.failed).to.be.true;
+    expect(pending.amount.toNumber()).to.equal(250);
+    expect(await tokenBalance(tokenVault)).to.equal(250);
+    expect(await tokenBalance(ownerTokenAccount.address)).to.equal(250);
+
+    const reclaimedEventPromise = awaitEvent("depositReclaimedEvent");
+    await program.methods
+      .reclaimDeposit(offset)
+      .accountsPartial({
+        owner: owner.publicKey,
+        pendingDeposit: pendingDepositPda(offset),
+        mint,
+        userTokenAccount: ownerTokenAccount.address,
+        tokenVault,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    const reclaimedEvent = await reclaimedEventPromise;
+    expect(reclaimedEvent.amount.toNumber()).to.equal(250);
+    expect(reclaimedEvent.userAccount.toBase58()).to.equal(account.pda.toBase58());
+
+    expect(await tokenBalance(tokenVault)).to.equal(0);
+    expect(await tokenBalance(ownerTokenAccount.address)).to.equal(500);
+    expect(
+      await provider.connection.getAccountInfo(pendingDepositPda(offset))
+    ).to.be.null;
+    expect(await decryptBalance(cipher, account.pda)).to.equal(
+      BigInt("18446744073709551615")
+    );
+  });
+
+  it("Should refuse new payments while paused but settle queued ones", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .setPaused(paused)
+        .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    // Queue before pausing, but only await the callback afterwards
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    const senderState = await program.account.userAccount.fetch(sender.pda);
+    await program.methods
+      .processPayment(
+        ...processPaymentArgs(
+          offset,
+          transactionId,
+          300,
+          cipher,
+          senderState.ownerEncPubkey,
+          0,
+          0,
+          noMemo,
+          senderState.sequence
+        )
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(owner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+    await setPaused(true);
+    try {
+      await finalize(offset);
+      const settled = await program.account.transaction.fetch(transactionPda(transactionId));
+      expect(settled.status).to.deep.equal({ completed: {} });
+      expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(300));
+
+      try {
+        await sendPayment(sender.pda, receiver.pda, 100, cipher);
+        expect.fail("a payment must not queue while paused");
+      } catch (e) {
+        expect(e.toString()).to.include("ProgramPaused");
+      }
+    } finally {
+      await setPaused(false);
+    }
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(700));
+  });
+
+  it("Should reject account state transitions that are not allowed", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    const account = await createUserAccount(1000, publicKey);
+
+    const setFrozen = (frozen: boolean) =>
+      (frozen
+        ? program.methods.freezeAccount(toBN(account.accountId))
+        : program.methods.unfreezeAccount(toBN(account.accountId))
+      )
+        .accountsPartial({
+          authority: owner.publicKey,
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    const expectRejected = async (frozen: boolean) => {
+      try {
+        await setFrozen(frozen);
+        expect.fail("the transition must be rejected");
+      } catch (e) {
+        expect(e.toString()).to.include("InvalidStateTransition");
+      }
+    };
+
+    // Active -> Active
+    await expectRejected(false);
+    // Active -> Frozen, then Frozen -> Frozen
+    await setFrozen(true);
+    await expectRejected(true);
+    // Frozen -> Active
+    await setFrozen(false);
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(state.accountState).to.deep.equal({ active: {} });
+  });
+
+  it("Should close an account once its balance has moved out", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("close_account", "initCloseAccountCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(700, publicKey);
+    const destination = await createUserAccount(300, publicKey);
+
+    const offset = new anchor.BN(randomBytes(8));
+    const closedEventPromise = awaitEvent("accountClosedEvent");
+    await program.methods
+      .closeAccount(offset, randomNonce(), randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "close_account"),
+        sourceAccount: account.pda,
+        destinationAccount: destination.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+    const closedEvent = await closedEventPromise;
+    expect(closedEvent.accountId.toString()).to.equal(account.accountId.toString());
+
+    const state = await program.account.userAccount.fetch(account.pda);
+    expect(state.accountState).to.deep.equal({ closed: {} });
+    expect(await decryptBalance(cipher, account.pda)).to.equal(BigInt(0));
+    expect(await decryptBalance(cipher, destination.pda)).to.equal(BigInt(1000));
+
+    // Nothing leaves Closed, and a closed account neither pays nor is paid
+    try {
+      await program.methods
+        .freezeAccount(toBN(account.accountId))
+        .accountsPartial({
+          authority: owner.publicKey,
+          userAccount: account.pda,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a closed account cannot be frozen");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidStateTransition");
+    }
+    for (const [sender, receiver] of [
+      [account.pda, destination.pda],
+      [destination.pda, account.pda],
+    ]) {
+      try {
+        await sendPayment(sender, receiver, 100, cipher);
+        expect.fail("a closed account takes no payments");
+      } catch (e) {
+        expect(e.toString()).to.include("InvalidAccountState");
+      }
+    }
+  });
+
+  it("Should expire reward points left unspent past the expiry window", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const account = await createUserAccount(500, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    await sendPayment(account.pda, receiver.pda, 100, cipher);
+
+    const setPointsExpiryPeriod = (seconds: number) =>
+      program.methods
+        .updatePointsExpiryPeriod(new anchor.BN(seconds))
+        .accountsPartial({ admin: owner.publicKey, programConfig: programConfigPda })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+
+    await setPointsExpiryPeriod(2);
+    try {
+      const firstEvent = await calculateRewards(account);
+      const earned = firstEvent.totalRewards.toNumber();
+      expect(earned).to.be.greaterThan(0);
+
+      await sleep(4000);
+      const expiredEventPromise = awaitEvent("rewardsExpiredEvent");
+      const secondEvent = await calculateRewards(account);
+      const expiredEvent = await expiredEventPromise;
+      expect(expiredEvent.expiredPoints.toNumber()).to.equal(earned);
+      // Only the points from this round remain
+      expect(secondEvent.totalRewards.toNumber()).to.equal(
+        secondEvent.rewardPoints.toNumber()
+      );
+
+      const state = await program.account.userAccount.fetch(account.pda);
+      expect(state.pointsExpiryTs.toNumber()).to.equal(
+        state.pointsUpdatedTs.toNumber() + 2
+      );
+    } finally {
+      await setPointsExpiryPeriod(365 * 86400);
+    }
+  });
+
+  it("Should issue threshold receipts for a payment without revealing its amount", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("prove_payment_received", "initProvePaymentReceivedCompDef");
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+    const transactionId = await sendPayment(sender.pda, receiver.pda, 250, cipher);
+    // Both accounts share an owner, who is therefore treated as the sender
+    await openSession(sender.pda, 600);
+
+    const receipt = async (threshold: number) => {
+      const offset = new anchor.BN(randomBytes(8));
+      const receiptEventPromise = awaitEvent("paymentReceiptEvent");
+      await program.methods
+        .provePaymentReceived(offset, toBN(transactionId), toBN(threshold))
+        .accountsPartial({
+          ...queueAccounts(offset, "prove_payment_received"),
+          transaction: transactionPda(transactionId),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          sessionAccount: sessionAccountPda(sender.pda),
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+      const event = await receiptEventPromise;
+      expect(event.transactionId.toString()).to.equal(transactionId.toString());
+      expect(event.computationOffset.toString()).to.equal(offset.toString());
+      return event.meetsThreshold;
+    };
+
+    expect(await receipt(250)).to.be.true;
+    expect(await receipt(251)).to.be.false;
+  });
+
+  it("Should suspend a frozen sender's scheduled payment until unfrozen", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("schedule_payment", "initSchedulePaymentCompDef");
+    await initCompDef(
+      "release_scheduled_payment",
+      "initReleaseScheduledPaymentCompDef"
+    );
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const scheduleId = randomId();
+    const [scheduledPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("scheduled_payment"), toBN(scheduleId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const now = await provider.connection.getBlockTime(
+      await provider.connection.getSlot("confirmed")
+    );
+    const amount = encryptU64(cipher, 300);
+    const scheduleOffset = new anchor.BN(randomBytes(8));
+    await program.methods
+      .schedulePayment(
+        scheduleOffset,
+        toBN(scheduleId),
+        amount.ciphertext,
+        amount.nonce,
+        randomNonce(),
+        randomNonce(),
+        new anchor.BN(now + 2)
+      )
+      .accountsPartial({
+        ...queueAccounts(scheduleOffset, "schedule_payment"),
+        scheduledPayment: scheduledPda,
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(scheduleOffset);
+
+    const setFrozen = (frozen: boolean) =>
+      (frozen
+        ? program.methods.freezeAccount(toBN(sender.accountId))
+        : program.methods.unfreezeAccount(toBN(sender.accountId))
+      )
+        .accountsPartial({
+          authority: owner.publicKey,
+          userAccount: sender.pda,
+          programConfig: programConfigPda,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+    const execute = async () => {
+      const offset = new anchor.BN(randomBytes(8));
+      await program.methods
+        .executeScheduledPayment(offset, toBN(scheduleId), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(offset, "release_scheduled_payment"),
+          scheduledPayment: scheduledPda,
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      await finalize(offset);
+    };
+
+    await setFrozen(true);
+    await sleep(3000);
+    try {
+      await execute();
+      expect.fail("a frozen sender's scheduled payment must not execute");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidAccountState");
+    }
+
+    await setFrozen(false);
+    const executedEventPromise = awaitEvent("scheduledPaymentExecutedEvent");
+    await execute();
+    await executedEventPromise;
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(300));
+  });
+
+  it("Should refuse a second payment while the first is still in flight", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey);
+
+    const queuePayment = async (amount: number) => {
+      const transactionId = randomId();
+      const offset = new anchor.BN(randomBytes(8));
+      const senderState = await program.account.userAccount.fetch(sender.pda);
+      await program.methods
+        .processPayment(
+          ...processPaymentArgs(
+            offset,
+            transactionId,
+            amount,
+            cipher,
+            senderState.ownerEncPubkey,
+            0,
+            0,
+            noMemo,
+            senderState.sequence
+          )
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      return offset;
+    };
+
+    const firstOffset = await queuePayment(100);
+    expect(
+      (await program.account.userAccount.fetch(sender.pda)).inFlightCount
+    ).to.equal(1);
+    try {
+      await queuePayment(200);
+      expect.fail("a second payment must wait for the first callback");
+    } catch (e) {
+      expect(e.toString()).to.include("ComputationInFlight");
+    }
+
+    await finalize(firstOffset);
+    expect(
+      (await program.account.userAccount.fetch(sender.pda)).inFlightCount
+    ).to.equal(0);
+    await finalize(await queuePayment(200));
+    expect(await decryptBalance(cipher, sender.pda)).to.equal(BigInt(700));
+    expect(await decryptBalance(cipher, receiver.pda)).to.equal(BigInt(300));
+  });
+
+  it("Should hold back a second fee-collecting payment while the pools are in flight", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    const { publicKey, cipher } = await setupCipher();
+    // Unrelated parties, so only the shared fee and tip pools conflict
+    const senders = [
+      await createUserAccount(1000, publicKey),
+      await createUserAccount(1000, publicKey),
+    ];
+    const receivers = [
+      await createUserAccount(0, publicKey),
+      await createUserAccount(0, publicKey),
+    ];
+
+    const queuePayment = async (i: number) => {
+      const transactionId = randomId();
+      const offset = new anchor.BN(randomBytes(8));
+      const senderState = await program.account.userAccount.fetch(senders[i].pda);
+      await program.methods
+        .processPayment(
+          ...processPaymentArgs(
+            offset,
+            transactionId,
+            100,
+            cipher,
+            senderState.ownerEncPubkey,
+            0,
+            0,
+            noMemo,
+            senderState.sequence
+          )
+        )
+        .accountsPartial({
+          ...queueAccounts(offset, "process_payment"),
+          senderAccount: senders[i].pda,
+          receiverAccount: receivers[i].pda,
+          senderTxIndex: userTxIndexPda(owner.publicKey),
+          receiverTxIndex: userTxIndexPda(owner.publicKey),
+          transaction: transactionPda(transactionId),
+          programConfig: programConfigPda,
+          validatorRewards: validatorRewardsPda,
+          treasury: treasuryPda,
+          payer: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      return offset;
+    };
+
+    const firstOffset = await queuePayment(0);
+    expect((await program.account.treasury.fetch(treasuryPda)).inFlightCount).to.equal(1);
+    expect(
+      (await program.account.validatorRewards.fetch(validatorRewardsPda)).inFlightCount
+    ).to.equal(1);
+    try {
+      await queuePayment(1);
+      expect.fail("a second payment would overwrite the first one's fee");
+    } catch (e) {
+      expect(e.toString()).to.include("ComputationInFlight");
+    }
+
+    await finalize(firstOffset);
+    expect((await program.account.treasury.fetch(treasuryPda)).inFlightCount).to.equal(0);
+    expect(
+      (await program.account.validatorRewards.fetch(validatorRewardsPda)).inFlightCount
+    ).to.equal(0);
+    await finalize(await queuePayment(1));
+    expect(await decryptBalance(cipher, receivers[0].pda)).to.equal(BigInt(100));
+    expect(await decryptBalance(cipher, receivers[1].pda)).to.equal(BigInt(100));
+  });
+
+  it("Should evaluate reward tiers in the account's own decimals", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCalculateRewardsCompDef(program as any, owner, false, false);
+    const { publicKey } = await setupCipher();
+    // The default thresholds are 10.00 / 50.00 / 100.00 in two decimals
+    const belowSilver = await createUserAccount(5_000_000, publicKey, owner, 0, 6);
+    const gold = await createUserAccount(50_000_000, publicKey, owner, 0, 6);
+
+    // 5.000000 would be Premium if compared against the raw 10000
+    expect((await calculateRewards(belowSilver)).tier).to.equal(0);
+    expect((await calculateRewards(gold)).tier).to.equal(2);
+  });
+
+  it("Should reveal a payment's amount to either party but nobody else", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initProcessPaymentCompDef(program as any, owner, false, false);
+    await initCompDef("reveal_transaction_amount", "initRevealTransactionAmountCompDef");
+    const senderKeys = await setupCipher();
+    const receiverKeys = await setupCipher();
+    const receiverOwner = await fundedKeypair();
+    const stranger = await fundedKeypair();
+    const sender = await createUserAccount(1000, senderKeys.publicKey);
+    const receiver = await createUserAccount(0, receiverKeys.publicKey, receiverOwner);
+
+    const transactionId = randomId();
+    const offset = new anchor.BN(randomBytes(8));
+    const senderState = await program.account.userAccount.fetch(sender.pda);
+    await program.methods
+      .processPayment(
+        ...processPaymentArgs(
+          offset,
+          transactionId,
+          275,
+          senderKeys.cipher,
+          senderState.ownerEncPubkey,
+          0,
+          0,
+          noMemo,
+          senderState.sequence
+        )
+      )
+      .accountsPartial({
+        ...queueAccounts(offset, "process_payment"),
+        senderAccount: sender.pda,
+        receiverAccount: receiver.pda,
+        senderTxIndex: userTxIndexPda(owner.publicKey),
+        receiverTxIndex: userTxIndexPda(receiverOwner.publicKey),
+        transaction: transactionPda(transactionId),
+        programConfig: programConfigPda,
+        validatorRewards: validatorRewardsPda,
+        treasury: treasuryPda,
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const reveal = async (caller: Keypair, party: PublicKey) => {
+      const revealOffset = new anchor.BN(randomBytes(8));
+      const revealedEventPromise = awaitEvent("transactionAmountRevealedEvent");
+      await program.methods
+        .revealTransactionAmount(revealOffset, toBN(transactionId), randomNonce())
+        .accountsPartial({
+          ...queueAccounts(revealOffset, "reveal_transaction_amount"),
+          transaction: transactionPda(transactionId),
+          senderAccount: sender.pda,
+          receiverAccount: receiver.pda,
+          sessionAccount: sessionAccountPda(party),
+          payer: caller.publicKey,
+        })
+        .signers([caller])
+        .rpc({ commitment: "confirmed" });
+      await finalize(revealOffset);
+      return revealedEventPromise;
+    };
+    const decryptAmount = (
+      cipher: RescueCipher,
+      event: { encryptedAmount: number[]; amountNonce: anchor.BN }
+    ) =>
+      cipher.decrypt(
+        [event.encryptedAmount],
+        new Uint8Array(event.amountNonce.toArray("le", 16))
+      )[0];
+
+    await openSession(sender.pda, 600);
+    await openSession(receiver.pda, 600, receiverOwner);
+
+    const senderEvent = await reveal(owner, sender.pda);
+    expect(senderEvent.accountId.toString()).to.equal(sender.accountId.toString());
+    expect(decryptAmount(senderKeys.cipher, senderEvent)).to.equal(BigInt(275));
+
+    const receiverEvent = await reveal(receiverOwner, receiver.pda);
+    expect(receiverEvent.accountId.toString()).to.equal(receiver.accountId.toString());
+    expect(decryptAmount(receiverKeys.cipher, receiverEvent)).to.equal(BigInt(275));
+
+    try {
+      await reveal(stranger, sender.pda);
+      expect.fail("a third party must not be able to reveal the amount");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+  });
+
+  it("Should round-trip a ping through the MPC cluster", async () => {
+    await initCompDef("ping", "initPingCompDef");
+    const offset = new anchor.BN(randomBytes(8));
+    const pingEventPromise = awaitEvent("pingEvent");
+    await program.methods
+      .ping(offset, randomNonce())
+      .accountsPartial({
+        ...queueAccounts(offset, "ping"),
+        payer: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    await finalize(offset);
+
+    const pingEvent = await pingEventPromise;
+    expect(pingEvent.success).to.equal(true);
+  });
+
+  it("Should refuse a batch receiver whose base currency differs", async () => {
+    await initInitializeAccountsCompDef(program as any, owner, false, false);
+    await initCompDef("batch_payment", "initBatchPaymentCompDef");
+    const { publicKey } = await setupCipher();
+    const sender = await createUserAccount(1000, publicKey);
+    const receiver = await createUserAccount(0, publicKey, owner, 1);
+
+    const offset = new anchor.BN(randomBytes(8));
+    try {
+      await program.methods
+        .batchPayment(offset, [
+          { receiver: receiver.pda, amount: toBN(100), receiverNewNonce: randomNonce() },
+        ])
+        .accountsPartial({
+          ...queueAccounts(offset, "batch_payment"),
+          senderAccount: sender.pda,
+          programConfig: programConfigPda,
+          payer: owner.publicKey,
+        })
+        .remainingAccounts([{ pubkey: receiver.pda, isWritable: true, isSigner: false }])
+        .signers([owner])
+        .rpc({ commitment: "confirmed" });
+      expect.fail("a base balance must not be paid into another currency");
+    } catch (e) {
+      expect(e.toString()).to.include("CurrencyNotSupported");
+    }
+  });
+
+  // --- Helper Functions for Computation Definition Initialization ---
+
+  // Generic variant of the helpers below for circuits added later on
+  async function initCompDef(
+    circuitName: string,
+    initMethod: string
+  ): Promise<string> {
+    const baseSeedCompDefAcc = getArciumAccountBaseSeed(
+      "ComputationDefinitionAccount"
+    );
+    const offset = getCompDefAccOffset(circuitName);
+
+    const compDefPDA = PublicKey.findProgramAddressSync(
+      [baseSeedCompDefAcc, program.programId.toBuffer(), offset],
+      getArciumProgAddress()
+    )[0];
+
+    try {
+      await program.account.computationDefinitionAccount.fetch(compDefPDA);
+      return "Already Initialized";
+    } catch (e) {
+      // Not initialized, proceed
+    }
+
+    const sig = await (program.methods as any)
+      [initMethod]()
+      .accounts({
+        compDefAccount: compDefPDA,
+        payer: owner.publicKey,
+        mxeAccount: getMXEAccAddress(program.programId),
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const finalizeTx = await buildFinalizeCompDefTx(
+      provider,
+      Buffer.from(offset).readUInt32LE(),
+      program.programId
+    );
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    finalizeTx.recentBlockhash = latestBlockhash.blockhash;
+    finalizeTx.lastValidBlockHeight = latestBlockhash.lastValidBlockHeight;
+    finalizeTx.sign(owner);
+    await provider.sendAndConfirm(finalizeTx, [owner], {
+      commitment: "confirmed",
+    });
+    return sig;
+  }
 
   async function initInitializeAccountsCompDef(
     program: Program<Ibank>,